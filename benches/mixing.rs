@@ -0,0 +1,85 @@
+//! The mixing search over a full brand of paints, for each strategy
+//!
+//! Run with `cargo bench --features ssr`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use aop::models::{srgb_to_xyz, PaintAttributes, PaintRole};
+use aop::services::colorimetry::Colorimetry;
+use aop::services::optimization::ks_curve;
+use aop::services::paint_mixing::{MixPaint, PaintMixingService};
+
+const STRATEGIES: &[&str] = &[
+    "Black + White + 2 colours",
+    "Black + White + 3 colours",
+    "All available colours",
+    "Neutral greys",
+    "No black",
+];
+
+/// 8-bit sRGB of a hue (0..1) at full saturation and the given value
+fn hue_rgb(hue: f64, value: f64) -> [u8; 3] {
+    let channel = |offset: f64| {
+        let k = (hue * 6.0 + offset) % 6.0;
+        let c = value * (1.0 - (k.min(4.0 - k).clamp(0.0, 1.0)));
+        (c * 255.0).round() as u8
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+/// A brand-sized palette: a white, a black, a neutral grey and colours around
+/// the hue circle at three strengths, with curves reconstructed from sRGB
+fn brand(service: &PaintMixingService) -> Vec<MixPaint> {
+    let mut colours = vec![
+        ("White".to_string(), [250, 250, 245], Some(PaintRole::White)),
+        ("Black".to_string(), [22, 22, 24], Some(PaintRole::Black)),
+        ("Grey".to_string(), [128, 128, 128], Some(PaintRole::NeutralGrey)),
+    ];
+    for hue in 0..11 {
+        for value in [1.0, 0.7, 0.45] {
+            let rgb = hue_rgb(hue as f64 / 11.0, value);
+            colours.push((format!("Hue {} at {}", hue, value), rgb, None));
+        }
+    }
+
+    colours
+        .into_iter()
+        .map(|(name, rgb, role)| {
+            let reflectance = service
+                .calculate_target_reflectance(srgb_to_xyz(rgb))
+                .expect("sRGB colours are reconstructable");
+            MixPaint {
+                name,
+                brand: "Bench".to_string(),
+                ks: ks_curve(&reflectance),
+                reflectance,
+                hex: format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]),
+                attributes: PaintAttributes {
+                    role,
+                    ..Default::default()
+                },
+                preferred: false,
+            }
+        })
+        .collect()
+}
+
+fn brand_search(c: &mut Criterion) {
+    let service = PaintMixingService::new(Colorimetry::default());
+    let paints = brand(&service);
+    let target = service
+        .calculate_target_reflectance(srgb_to_xyz([107, 142, 35]))
+        .unwrap();
+
+    let mut group = c.benchmark_group("brand search");
+    group.sample_size(10);
+    for strategy in STRATEGIES {
+        group.bench_function(*strategy, |b| {
+            b.iter(|| service.find_combinations(&target, &paints, strategy).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, brand_search);
+criterion_main!(benches);
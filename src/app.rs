@@ -30,6 +30,13 @@ pub fn App() -> impl IntoView {
                     <Route path=path!("/settings") view=SettingsPage/>
                     <Route path=path!("/target-mix") view=TargetMixPage/>
                     <Route path=path!("/test-mix") view=TestMixPage/>
+                    <Route path=path!("/batch") view=BatchMixPage/>
+                    <Route path=path!("/my-paints") view=MyPaintsPage/>
+                    <Route path=path!("/admin") view=AdminPage/>
+                    <Route path=path!("/history") view=HistoryPage/>
+                    <Route path=path!("/projects") view=ProjectsPage/>
+                    <Route path=path!("/projects/:id") view=ProjectPage/>
+                    <Route path=path!("/projects/:id/summary") view=ProjectSummaryPage/>
                 </Routes>
             </main>
         </Router>
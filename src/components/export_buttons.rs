@@ -0,0 +1,68 @@
+use leptos::prelude::*;
+
+use crate::server_fns::{ExportFile, SheetFormat, SwatchFormat};
+
+/// Buttons to generate a mixing sheet, and a link to download the result
+#[component]
+pub fn SheetExport(export: Action<SheetFormat, Result<ExportFile, ServerFnError>>) -> impl IntoView {
+    view! {
+        <div class="sheet-export no-print">
+            <button
+                class="btn btn-small"
+                on:click=move |_| { export.dispatch(SheetFormat::Pdf); }
+                disabled=move || export.pending().get()
+            >
+                "Export PDF"
+            </button>
+            <button
+                class="btn btn-small"
+                on:click=move |_| { export.dispatch(SheetFormat::Svg); }
+                disabled=move || export.pending().get()
+            >
+                "Export SVG"
+            </button>
+            {move || export.value().get().map(download_link)}
+        </div>
+    }
+}
+
+/// A button per swatch file format, and a link to download the result
+#[component]
+pub fn SwatchExport(
+    export: Action<SwatchFormat, Result<ExportFile, ServerFnError>>,
+    #[prop(into, optional)] disabled: Signal<bool>,
+) -> impl IntoView {
+    view! {
+        <div class="sheet-export no-print">
+            {SwatchFormat::all()
+                .into_iter()
+                .map(|format| {
+                    view! {
+                        <button
+                            class="btn btn-small"
+                            on:click=move |_| { export.dispatch(format); }
+                            disabled=move || disabled.get() || export.pending().get()
+                        >
+                            {format.label()}
+                        </button>
+                    }
+                })
+                .collect_view()}
+            {move || export.value().get().map(download_link)}
+        </div>
+    }
+}
+
+fn download_link(result: Result<ExportFile, ServerFnError>) -> AnyView {
+    match result {
+        Ok(file) => {
+            view! {
+                <a class="download-link" href=file.data_url() download=file.filename.clone()>
+                    "Download "{file.filename.clone()}
+                </a>
+            }
+                .into_any()
+        }
+        Err(e) => view! { <span class="error">{e.to_string()}</span> }.into_any(),
+    }
+}
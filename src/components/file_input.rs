@@ -0,0 +1,155 @@
+use leptos::web_sys;
+#[cfg(feature = "hydrate")]
+use wasm_bindgen::JsCast;
+
+/// Read the file chosen in a file input, passing its name and contents as a data URL
+#[allow(unused_variables)]
+pub fn read_file(ev: &web_sys::Event, on_load: impl FnOnce(String, String) + 'static) {
+    #[cfg(feature = "hydrate")]
+    {
+        use ::web_sys::{FileReader, HtmlInputElement};
+
+        let Some(input) = ev.target().and_then(|t| t.dyn_into::<HtmlInputElement>().ok()) else {
+            return;
+        };
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let name = file.name();
+        let reader = FileReader::new().unwrap();
+        let reader_clone = reader.clone();
+
+        let closure = wasm_bindgen::closure::Closure::once(Box::new(move || {
+            if let Some(data_url) = reader_clone.result().ok().and_then(|r| r.as_string()) {
+                on_load(name, data_url);
+            }
+        }) as Box<dyn FnOnce()>);
+
+        reader.set_onload(Some(closure.as_ref().unchecked_ref()));
+        closure.forget();
+
+        let _ = reader.read_as_data_url(&file);
+    }
+}
+
+/// Read an image chosen in a file input as a data URL.
+/// When `max_dimension` is set the image is downscaled and re-encoded as JPEG
+/// so it is small enough to store on the server.
+#[allow(unused_variables)]
+pub fn read_image_file(
+    ev: &web_sys::Event,
+    max_dimension: Option<u32>,
+    on_load: impl FnOnce(String) + 'static,
+) {
+    read_file(ev, move |_, data_url| {
+        #[cfg(feature = "hydrate")]
+        if let Some(max) = max_dimension {
+            downscale_image(data_url, max, on_load);
+            return;
+        }
+        on_load(data_url);
+    });
+}
+
+#[cfg(feature = "hydrate")]
+fn downscale_image(data_url: String, max: u32, on_load: impl FnOnce(String) + 'static) {
+    use ::web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+    let img = HtmlImageElement::new().unwrap();
+    let img_clone = img.clone();
+
+    let closure = wasm_bindgen::closure::Closure::once(Box::new(move || {
+        let (width, height) = (img_clone.natural_width(), img_clone.natural_height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        let scale = (max as f64 / width.max(height) as f64).min(1.0);
+        let (w, h) = (
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        );
+
+        let document = ::web_sys::window().unwrap().document().unwrap();
+        let canvas = document
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<HtmlCanvasElement>()
+            .unwrap();
+        canvas.set_width(w);
+        canvas.set_height(h);
+        let ctx = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<CanvasRenderingContext2d>()
+            .unwrap();
+        let _ = ctx.draw_image_with_html_image_element_and_dw_and_dh(
+            &img_clone, 0.0, 0.0, w as f64, h as f64,
+        );
+        if let Ok(url) = canvas.to_data_url_with_type("image/jpeg") {
+            on_load(url);
+        }
+    }) as Box<dyn FnOnce()>);
+
+    img.set_onload(Some(closure.as_ref().unchecked_ref()));
+    closure.forget();
+    img.set_src(&data_url);
+}
+
+/// Sample the pixel under a click on an `<img>`, in the image's natural resolution.
+/// Uses offsetX/offsetY, which give the position relative to the clicked element.
+#[allow(unused_variables)]
+pub fn sample_image_pixel(ev: &web_sys::MouseEvent) -> Option<[u8; 3]> {
+    #[cfg(feature = "hydrate")]
+    {
+        use ::web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+        let img = ev.target()?.dyn_into::<HtmlImageElement>().ok()?;
+
+        // Get natural (original) dimensions of the image
+        let natural_width = img.natural_width();
+        let natural_height = img.natural_height();
+
+        // Get the displayed dimensions
+        let displayed_width = img.width() as f64;
+        let displayed_height = img.height() as f64;
+
+        if displayed_width == 0.0 || displayed_height == 0.0 || natural_width == 0 || natural_height == 0 {
+            return None;
+        }
+
+        // Scale from displayed size to natural size
+        let scale_x = natural_width as f64 / displayed_width;
+        let scale_y = natural_height as f64 / displayed_height;
+
+        let x = (ev.offset_x() as f64 * scale_x).round() as u32;
+        let y = (ev.offset_y() as f64 * scale_y).round() as u32;
+
+        // Clamp to valid range
+        let x = x.min(natural_width - 1);
+        let y = y.min(natural_height - 1);
+
+        // Create canvas at natural size for accurate sampling
+        let document = ::web_sys::window()?.document()?;
+        let canvas = document
+            .create_element("canvas")
+            .ok()?
+            .dyn_into::<HtmlCanvasElement>()
+            .ok()?;
+        canvas.set_width(natural_width);
+        canvas.set_height(natural_height);
+
+        let ctx = canvas
+            .get_context("2d")
+            .ok()??
+            .dyn_into::<CanvasRenderingContext2d>()
+            .ok()?;
+
+        // Draw image at natural size, then sample the pixel
+        let _ = ctx.draw_image_with_html_image_element(&img, 0.0, 0.0);
+        let data = ctx.get_image_data(x as f64, y as f64, 1.0, 1.0).ok()?.data();
+        Some([data[0], data[1], data[2]])
+    }
+    #[cfg(not(feature = "hydrate"))]
+    None
+}
@@ -0,0 +1,80 @@
+use leptos::prelude::*;
+
+use crate::models::MixingResult;
+
+/// One recommended mixture: proportions bar, warnings and paint list
+#[component]
+pub fn MixResultCard(mix: MixingResult, rank: usize) -> impl IntoView {
+    let total_weight: f64 = mix.weights.iter().sum();
+
+    view! {
+        <div class="mix-result-card">
+            <div class="card-header">
+                <span class="mix-rank">{"#"}{rank}</span>
+                <span class="mix-error">"ΔE: "{format!("{:.2}", mix.error)}</span>
+            </div>
+
+            // Horizontal bar chart showing paint proportions
+            <div class="mix-bar-chart">
+                {mix
+                    .weights
+                    .iter()
+                    .zip(mix.hex_colors.iter())
+                    .zip(mix.paints.iter())
+                    .map(|((weight, hex), name)| {
+                        let percentage = (weight / total_weight * 100.0).round();
+                        view! {
+                            <div
+                                class="bar-segment"
+                                style=format!("background-color: {}; width: {}%;", hex, percentage)
+                                title=format!("{}: {}%", name, percentage)
+                            >
+                                {(percentage >= 10.0).then(|| format!("{}%", percentage))}
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+
+            // Pigment warnings
+            {(!mix.warnings.is_empty())
+                .then(|| {
+                    view! {
+                        <ul class="mix-warnings">
+                            {mix
+                                .warnings
+                                .iter()
+                                .map(|w| view! { <li>{w.message()}</li> })
+                                .collect_view()}
+                        </ul>
+                    }
+                })}
+
+            // Paint list with details
+            <div class="paint-details">
+                {mix
+                    .paints
+                    .iter()
+                    .zip(mix.weights.iter())
+                    .zip(mix.hex_colors.iter())
+                    .enumerate()
+                    .map(|(i, ((name, weight), hex))| {
+                        let percentage = (weight / total_weight * 100.0).round();
+                        let brand = mix.brands.get(i).cloned().unwrap_or_default();
+                        view! {
+                            <div class="paint-row">
+                                <div
+                                    class="paint-swatch"
+                                    style=format!("background-color: {}", hex)
+                                ></div>
+                                <span class="paint-name">{name.clone()}</span>
+                                <span class="paint-brand">{brand}</span>
+                                <span class="paint-percentage">{percentage}"%"</span>
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </div>
+    }
+}
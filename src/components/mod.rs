@@ -1,5 +1,13 @@
 mod nav;
 mod auth_guard;
+mod export_buttons;
+mod file_input;
+mod mix_result_card;
+mod palette_selector;
 
 pub use nav::Nav;
 pub use auth_guard::AuthGuard;
+pub use export_buttons::{SheetExport, SwatchExport};
+pub use file_input::{read_file, read_image_file, sample_image_pixel};
+pub use mix_result_card::MixResultCard;
+pub use palette_selector::PaletteSelector;
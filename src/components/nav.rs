@@ -36,7 +36,12 @@ pub fn Nav() -> impl IntoView {
                                 Ok(Some(u)) => view! {
                                     <A href="/target-mix">"Mix Colour"</A>
                                     <A href="/test-mix">"Test Mix"</A>
+                                    <A href="/batch">"Batch Mix"</A>
+                                    <A href="/history">"History"</A>
+                                    <A href="/projects">"Projects"</A>
+                                    <A href="/my-paints">"My Paints"</A>
                                     <A href="/settings">"Settings"</A>
+                                    {u.is_admin.then(|| view! { <A href="/admin">"Admin"</A> })}
                                     <span class="user-email">{u.email}</span>
                                     <ActionForm action=logout_action attr:class="logout-form">
                                         <button type="submit" class="btn btn-small">"Sign Out"</button>
@@ -0,0 +1,69 @@
+use leptos::prelude::*;
+
+use crate::server_fns::{list_palettes, set_active_palette, Palette};
+
+/// Dropdown for switching between the user's palettes. Starts on the active
+/// palette and remembers the choice as the new active palette.
+#[component]
+pub fn PaletteSelector(selected: RwSignal<Option<Palette>>) -> impl IntoView {
+    let palettes = Resource::new(|| (), |_| list_palettes());
+    let activate = Action::new(|id: &String| set_active_palette(id.clone()));
+
+    Effect::new(move || {
+        if let Some(Ok(list)) = palettes.get() {
+            if selected.get_untracked().is_none() {
+                selected.set(list.iter().find(|p| p.active).or(list.first()).cloned());
+            }
+        }
+    });
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || {
+                palettes
+                    .get()
+                    .map(|result| {
+                        match result {
+                            Ok(list) if list.is_empty() => {
+                                view! { <span class="hint">"No palettes yet"</span> }.into_any()
+                            }
+                            Ok(list) => {
+                                let options = list.clone();
+                                view! {
+                                    <select
+                                        class="select-input palette-select"
+                                        on:change=move |ev| {
+                                            let id = event_target_value(&ev);
+                                            if let Some(p) = list.iter().find(|p| p.id == id) {
+                                                selected.set(Some(p.clone()));
+                                                activate.dispatch(id);
+                                            }
+                                        }
+                                    >
+                                        {options
+                                            .into_iter()
+                                            .map(|p| {
+                                                let id = p.id.clone();
+                                                view! {
+                                                    <option
+                                                        value=p.id.clone()
+                                                        selected=move || {
+                                                            selected.get().is_some_and(|s| s.id == id)
+                                                        }
+                                                    >
+                                                        {p.name}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </select>
+                                }
+                                    .into_any()
+                            }
+                            Err(e) => view! { <span class="error">{e.to_string()}</span> }.into_any(),
+                        }
+                    })
+            }}
+        </Suspense>
+    }
+}
@@ -0,0 +1,1194 @@
+//! Versioned schema migrations
+//!
+//! The user database and the paint reference database each have their own
+//! ordered list of migrations, and a Postgres user database has its own list
+//! in the `postgres` module. They run in order, each in its own
+//! transaction, and every database records the versions applied to it in
+//! its own `seaql_migrations` table. Databases created before migrations
+//! were versioned already have some of the schema, so every migration
+//! tolerates finding its tables and columns in place.
+//!
+//! Add new migrations to the end of a list; never edit or reorder one that
+//! has shipped.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use sqlx::SqliteConnection;
+
+use super::{CurveOrigin, Db, SpectralCurve};
+
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// A migration step written in Rust, for changes plain SQL cannot express
+type MigrationFn<C> = for<'c> fn(&'c mut C) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+/// How a migration changes the schema
+pub enum MigrationStep<C: 'static = SqliteConnection> {
+    /// One or more SQL statements
+    Sql(&'static str),
+    Code(MigrationFn<C>),
+}
+
+pub struct Migration<C: 'static = SqliteConnection> {
+    /// Sortable id, recorded once applied
+    pub version: &'static str,
+    pub description: &'static str,
+    pub(crate) up: MigrationStep<C>,
+    /// Reverts `up`; data migrations cannot be reverted
+    pub(crate) down: Option<MigrationStep<C>>,
+}
+
+impl<C> Migration<C> {
+    pub fn is_reversible(&self) -> bool {
+        self.down.is_some()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("migration {version} failed: {source}")]
+    Failed {
+        version: &'static str,
+        source: sqlx::Error,
+    },
+    #[error("migration {0} cannot be reverted")]
+    Irreversible(&'static str),
+    #[error("the database has migration {0} applied, which this build does not know")]
+    Unknown(String),
+    #[error("the reference database still holds accounts; copy them to Postgres with `aop import-users` first")]
+    AccountsNotImported,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// A migration and when it was applied, if it has been
+pub struct MigrationStatus {
+    pub version: &'static str,
+    pub description: &'static str,
+    pub reversible: bool,
+    /// Unix time
+    pub applied_at: Option<i64>,
+}
+
+/// The migrations of one kind of database
+pub struct MigrationSet<C: 'static = SqliteConnection> {
+    pub label: &'static str,
+    pub migrations: &'static [Migration<C>],
+    /// Migrations that may also be recorded in the same table, when one file
+    /// once held both kinds of data; their versions are skipped
+    pub shared_with: &'static [Migration<C>],
+}
+
+/// The user database on SQLite
+pub static USER_DATABASE: MigrationSet = MigrationSet {
+    label: "user",
+    migrations: USER_MIGRATIONS,
+    shared_with: REFERENCE_MIGRATIONS,
+};
+
+/// The paint reference database
+pub static REFERENCE_DATABASE: MigrationSet = MigrationSet {
+    label: "reference",
+    migrations: REFERENCE_MIGRATIONS,
+    shared_with: USER_MIGRATIONS,
+};
+
+/// A database migrations can be applied to
+pub trait MigrationDb {
+    type Connection: 'static;
+
+    /// Applied versions with when they were applied
+    fn applied_migrations(&self) -> BoxFuture<'_, Result<Vec<(String, i64)>, sqlx::Error>>;
+
+    /// Run one step in a transaction that also records the version as
+    /// applied, or as reverted when `apply` is false
+    fn run_migration<'a>(
+        &'a self,
+        version: &'static str,
+        step: &'a MigrationStep<Self::Connection>,
+        apply: bool,
+    ) -> BoxFuture<'a, Result<(), MigrationError>>;
+}
+
+impl MigrationDb for Db {
+    type Connection = SqliteConnection;
+
+    fn applied_migrations(&self) -> BoxFuture<'_, Result<Vec<(String, i64)>, sqlx::Error>> {
+        Box::pin(async move {
+            // Read without creating the table, so a read-only database can be checked
+            let (tables,): (i64,) = sqlx::query_as(
+                r#"
+                SELECT COUNT(*) FROM main.sqlite_master
+                WHERE type = 'table' AND name = 'seaql_migrations'
+                "#,
+            )
+            .fetch_one(self)
+            .await?;
+            if tables == 0 {
+                return Ok(vec![]);
+            }
+            sqlx::query_as(r#"SELECT "version", "applied_at" FROM main."seaql_migrations""#)
+                .fetch_all(self)
+                .await
+        })
+    }
+
+    fn run_migration<'a>(
+        &'a self,
+        version: &'static str,
+        step: &'a MigrationStep,
+        apply: bool,
+    ) -> BoxFuture<'a, Result<(), MigrationError>> {
+        Box::pin(async move {
+            let mut tx = self.begin().await?;
+            let result = match step {
+                MigrationStep::Sql(sql) => sqlx::raw_sql(sql).execute(&mut *tx).await.map(|_| ()),
+                MigrationStep::Code(f) => f(&mut tx).await,
+            };
+            result.map_err(|source| MigrationError::Failed { version, source })?;
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS main."seaql_migrations" (
+                    "version" text NOT NULL PRIMARY KEY,
+                    "applied_at" bigint NOT NULL
+                )
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+            if apply {
+                sqlx::query(r#"INSERT INTO main."seaql_migrations" ("version", "applied_at") VALUES (?, ?)"#)
+                    .bind(version)
+                    .bind(chrono::Utc::now().timestamp())
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query(r#"DELETE FROM main."seaql_migrations" WHERE "version" = ?"#)
+                    .bind(version)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+}
+
+/// Migrations of the user database, oldest first
+pub static USER_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "m20261018_000000_split_combined_database",
+        description: "user tables copied out of a reference database that also held accounts",
+        up: MigrationStep::Code(split_combined_database),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000001_create_auth_tables",
+        description: "users and their email verification and password reset tokens",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS users (
+                id TEXT PRIMARY KEY,
+                email TEXT UNIQUE NOT NULL,
+                email_verified INTEGER DEFAULT 0,
+                password_hash TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                failed_attempts INTEGER DEFAULT 0,
+                locked_until TEXT
+            );
+            CREATE TABLE IF NOT EXISTS tokens (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            );
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE tokens; DROP TABLE users;")),
+    },
+    Migration {
+        version: "m20261018_000002_user_settings_primary_key",
+        description: "user settings keyed by user, rebuilding the old table that had no key",
+        up: MigrationStep::Code(user_settings_primary_key),
+        down: Some(MigrationStep::Sql("DROP TABLE user_settings;")),
+    },
+    Migration {
+        version: "m20261018_000003_create_palettes",
+        description: "named palettes, starting from each user's saved selection",
+        up: MigrationStep::Code(create_palettes),
+        down: Some(MigrationStep::Sql(
+            "DROP TABLE palettes; ALTER TABLE user_settings DROP COLUMN active_palette_id;",
+        )),
+    },
+    Migration {
+        version: "m20261018_000004_create_mix_history",
+        description: "per-user history of mixes, which can be starred and named as recipes",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS mix_history (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                target_hex TEXT NOT NULL,
+                palette_id TEXT,
+                palette_name TEXT,
+                mix_choice TEXT,
+                filters TEXT,
+                results TEXT NOT NULL,
+                starred INTEGER NOT NULL DEFAULT 0,
+                name TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_mix_history_user ON mix_history(user_id, created_at);
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE mix_history;")),
+    },
+    Migration {
+        version: "m20261018_000005_create_projects",
+        description: "project boards: a reference image with its target swatches and chosen recipes",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS projects (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                notes TEXT NOT NULL DEFAULT '',
+                reference_image TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS project_targets (
+                id TEXT PRIMARY KEY,
+                project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                label TEXT NOT NULL,
+                target_hex TEXT NOT NULL,
+                palette_name TEXT,
+                mix_choice TEXT,
+                results TEXT NOT NULL,
+                chosen INTEGER,
+                notes TEXT NOT NULL DEFAULT '',
+                created_at TEXT NOT NULL
+            );
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE project_targets; DROP TABLE projects;")),
+    },
+    Migration {
+        version: "m20261018_000006_create_user_paints",
+        description: "paints users add themselves, mixed alongside the built-in brands",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_paints (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                spectral_curve BLOB NOT NULL,
+                hex TEXT NOT NULL,
+                source TEXT NOT NULL,
+                opacity TEXT,
+                pigments TEXT,
+                lightfastness TEXT,
+                series INTEGER,
+                drying_speed TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE (user_id, name)
+            );
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE user_paints;")),
+    },
+    Migration {
+        version: "m20261018_000012_user_paint_curves",
+        description: "user paint curves from bare bincode blobs to the versioned encoding",
+        up: MigrationStep::Code(user_paint_curves),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000013_user_paint_roles",
+        description: "user paint roles detected from the curves, and each palette's white and black",
+        up: MigrationStep::Code(user_paint_roles),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000014_admin_users",
+        description: "users who may edit the paint catalogue",
+        up: MigrationStep::Code(admin_users),
+        down: Some(MigrationStep::Sql("ALTER TABLE users DROP COLUMN is_admin;")),
+    },
+    Migration {
+        version: "m20261018_000016_create_sessions",
+        description: "sign-in sessions that survive a restart",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
+            CREATE INDEX idx_sessions_expires_at ON sessions(expires_at);
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE sessions;")),
+    },
+    Migration {
+        version: "m20261018_000017_create_batch_jobs",
+        description: "batch mix jobs, so any server can report their progress",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE batch_jobs (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                total INTEGER NOT NULL,
+                completed INTEGER NOT NULL DEFAULT 0,
+                finished BOOLEAN NOT NULL DEFAULT 0,
+                results TEXT,
+                error TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE INDEX idx_batch_jobs_user_id ON batch_jobs(user_id);
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE batch_jobs;")),
+    },
+    Migration {
+        version: "m20261018_000019_catalogue_overlay",
+        description: "admins' catalogue edits and their audit log, out of the reference database",
+        up: MigrationStep::Code(catalogue_overlay),
+        down: Some(MigrationStep::Sql(
+            "DROP TABLE audit_log; DROP TABLE catalogue_paints; DROP TABLE catalogue_brands;",
+        )),
+    },
+];
+
+/// Migrations of the paint reference database, oldest first. These run after
+/// the user migrations, so accounts are copied out before the last one drops them.
+pub static REFERENCE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "m20261018_000007_paint_attributes",
+        description: "opacity, pigments, lightfastness, series and drying speed on brand tables",
+        up: MigrationStep::Code(paint_attributes),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000008_versioned_spectral_curves",
+        description: "brand spectral curves from bare bincode blobs to the versioned encoding",
+        up: MigrationStep::Code(versioned_spectral_curves),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000009_paint_roles",
+        description: "brand paint roles detected from the curves",
+        up: MigrationStep::Code(paint_roles),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000010_brand_catalogue",
+        description: "brand catalogue and one paints table, replacing a table per brand",
+        up: MigrationStep::Code(brand_catalogue),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000011_admin_catalogue",
+        description: "retired paints and the catalogue audit log",
+        up: MigrationStep::Code(admin_catalogue),
+        down: Some(MigrationStep::Sql(
+            "DROP TABLE audit_log; ALTER TABLE paints DROP COLUMN retired_at;",
+        )),
+    },
+    Migration {
+        version: "m20261018_000015_drop_user_tables",
+        description: "user tables removed once the user database has its own copy",
+        up: MigrationStep::Sql(
+            r#"
+            DROP TABLE IF EXISTS tokens;
+            DROP TABLE IF EXISTS user_settings;
+            DROP TABLE IF EXISTS palettes;
+            DROP TABLE IF EXISTS mix_history;
+            DROP TABLE IF EXISTS project_targets;
+            DROP TABLE IF EXISTS projects;
+            DROP TABLE IF EXISTS user_paints;
+            DROP TABLE IF EXISTS users;
+            "#,
+        ),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000018_redetect_paint_roles",
+        description: "brand paint roles detected again, so crimsons and blues are not earths",
+        up: MigrationStep::Code(redetect_paint_roles),
+        down: None,
+    },
+    Migration {
+        version: "m20261018_000020_redetect_black_roles",
+        description: "brand paint roles detected again, so the darkest blacks are blacks",
+        up: MigrationStep::Code(redetect_paint_roles),
+        down: None,
+    },
+];
+
+/// Apply every pending migration in order; returns the versions applied
+pub async fn migrate_up<D: MigrationDb>(
+    db: &D,
+    set: &'static MigrationSet<D::Connection>,
+) -> Result<Vec<&'static str>, MigrationError> {
+    let mut applied = Vec::new();
+    for migration in pending_migrations(db, set).await? {
+        db.run_migration(migration.version, &migration.up, true).await?;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Revert the `steps` most recently applied migrations, newest first; returns
+/// the versions reverted. Nothing is reverted unless every one of them can be.
+pub async fn migrate_down<D: MigrationDb>(
+    db: &D,
+    set: &'static MigrationSet<D::Connection>,
+    steps: usize,
+) -> Result<Vec<&'static str>, MigrationError> {
+    let applied = applied_versions(db, set).await?;
+    let mut to_revert = Vec::new();
+    for migration in set
+        .migrations
+        .iter()
+        .rev()
+        .filter(|m| applied.iter().any(|v| v == m.version))
+        .take(steps)
+    {
+        let down = migration
+            .down
+            .as_ref()
+            .ok_or(MigrationError::Irreversible(migration.version))?;
+        to_revert.push((migration.version, down));
+    }
+
+    let mut reverted = Vec::new();
+    for (version, down) in to_revert {
+        db.run_migration(version, down, false).await?;
+        reverted.push(version);
+    }
+    Ok(reverted)
+}
+
+/// Migrations not yet applied, in the order they will run
+pub async fn pending_migrations<D: MigrationDb>(
+    db: &D,
+    set: &'static MigrationSet<D::Connection>,
+) -> Result<Vec<&'static Migration<D::Connection>>, MigrationError> {
+    let applied = applied_versions(db, set).await?;
+    Ok(set
+        .migrations
+        .iter()
+        .filter(|m| !applied.iter().any(|v| v == m.version))
+        .collect())
+}
+
+/// Every migration with when it was applied
+pub async fn migration_status<D: MigrationDb>(
+    db: &D,
+    set: &'static MigrationSet<D::Connection>,
+) -> Result<Vec<MigrationStatus>, MigrationError> {
+    let applied = db.applied_migrations().await?;
+    Ok(set
+        .migrations
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description,
+            reversible: migration.is_reversible(),
+            applied_at: applied
+                .iter()
+                .find(|(version, _)| version == migration.version)
+                .map(|(_, at)| *at),
+        })
+        .collect())
+}
+
+/// Versions of the set applied to the database. Versions of the set it
+/// shares a table with are skipped; a version in neither comes from a newer build.
+async fn applied_versions<D: MigrationDb>(
+    db: &D,
+    set: &'static MigrationSet<D::Connection>,
+) -> Result<Vec<String>, MigrationError> {
+    let rows = db.applied_migrations().await?;
+    let known = |v: &str| set.migrations.iter().chain(set.shared_with).any(|m| m.version == v);
+    if let Some((unknown, _)) = rows.iter().find(|(v, _)| !known(v)) {
+        return Err(MigrationError::Unknown(unknown.clone()));
+    }
+    Ok(rows
+        .into_iter()
+        .map(|(v, _)| v)
+        .filter(|v| set.migrations.iter().any(|m| m.version == v))
+        .collect())
+}
+
+/// Tables that held user data when it shared a file with the paint data
+pub(crate) const USER_TABLES: [&str; 8] = [
+    "users",
+    "tokens",
+    "user_settings",
+    "palettes",
+    "mix_history",
+    "projects",
+    "project_targets",
+    "user_paints",
+];
+
+/// Whether the reference database is an old combined one that still holds accounts
+pub async fn reference_holds_accounts(reference: &Db) -> Result<bool, sqlx::Error> {
+    let (tables,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM main.sqlite_master WHERE type = 'table' AND name = 'users'",
+    )
+    .fetch_one(reference)
+    .await?;
+    if tables == 0 {
+        return Ok(false);
+    }
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM main.users)")
+        .fetch_one(reference)
+        .await
+}
+
+/// Copy user tables, with their indexes, from the attached reference database
+/// when it is an old combined database and the user database lacks them
+fn split_combined_database(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        for table in USER_TABLES {
+            if table_exists(conn, table).await? {
+                continue;
+            }
+            let schema: Vec<(String,)> = sqlx::query_as(
+                r#"
+                SELECT sql FROM reference.sqlite_master
+                WHERE tbl_name = ? AND sql IS NOT NULL
+                ORDER BY type = 'index'
+                "#,
+            )
+            .bind(table)
+            .fetch_all(&mut *conn)
+            .await?;
+            let Some(((create,), indexes)) = schema.split_first() else {
+                continue;
+            };
+
+            sqlx::query(create).execute(&mut *conn).await?;
+            sqlx::query(&format!("INSERT INTO main.{0} SELECT * FROM reference.{0}", table))
+                .execute(&mut *conn)
+                .await?;
+            for (index,) in indexes {
+                sqlx::query(index).execute(&mut *conn).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn user_settings_primary_key(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // The first user_settings table had no primary key, so rebuild it
+        let (keys,): (i32,) =
+            sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info('user_settings', 'main') WHERE pk = 1")
+                .fetch_one(&mut *conn)
+                .await?;
+        let rebuild = keys == 0 && table_exists(conn, "user_settings").await?;
+        if rebuild {
+            sqlx::query("ALTER TABLE user_settings RENAME TO user_settings_old")
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_settings (
+                _id TEXT PRIMARY KEY,
+                email TEXT,
+                colour_mix_choice TEXT,
+                selected_colors TEXT
+            )
+            "#,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if rebuild {
+            sqlx::raw_sql(
+                r#"
+                INSERT OR REPLACE INTO user_settings (_id, email, colour_mix_choice, selected_colors)
+                SELECT _id, email, colour_mix_choice, selected_colors FROM user_settings_old
+                WHERE _id IS NOT NULL;
+                DROP TABLE user_settings_old;
+                "#,
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(())
+    })
+}
+
+fn create_palettes(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        add_column_if_missing(conn, "user_settings", "active_palette_id", "TEXT").await?;
+
+        // Each user's single saved selection becomes their active "Default" palette
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS palettes (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                mix_choice TEXT NOT NULL,
+                paints TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (user_id, name)
+            );
+            INSERT INTO palettes (id, user_id, name, mix_choice, paints, created_at, updated_at)
+            SELECT lower(hex(randomblob(16))), _id, 'Default',
+                   COALESCE(colour_mix_choice, 'black + white + 2 colours'),
+                   COALESCE(selected_colors, '{}'), datetime('now'), datetime('now')
+            FROM user_settings
+            WHERE selected_colors IS NOT NULL
+              AND _id IN (SELECT id FROM users)
+              AND _id NOT IN (SELECT user_id FROM palettes);
+            UPDATE user_settings SET active_palette_id =
+                (SELECT id FROM palettes WHERE user_id = user_settings._id AND name = 'Default')
+            WHERE active_palette_id IS NULL;
+            "#,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    })
+}
+
+fn paint_attributes(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        for table in legacy_brand_tables(conn).await? {
+            for (column, decl) in PAINT_ATTRIBUTE_COLUMNS {
+                add_column_if_missing(conn, &table, column, decl).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn versioned_spectral_curves(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        for table in legacy_brand_tables(conn).await? {
+            let failures = migrate_spectral_curves(conn, &table, "_id", "NULL").await?;
+            record_curve_failures(conn, &table, &failures).await?;
+        }
+        Ok(())
+    })
+}
+
+fn user_paint_curves(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // Users re-upload a paint whose curve is lost; the warning names it
+        migrate_spectral_curves(conn, "user_paints", "name", "source").await?;
+        Ok(())
+    })
+}
+
+fn paint_roles(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // Roles are detected only where the column is new, so later corrections are kept
+        for table in legacy_brand_tables(conn).await? {
+            if add_column_if_missing(conn, &table, "role", "TEXT").await? {
+                detect_paint_roles(conn, &table).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn user_paint_roles(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        if add_column_if_missing(conn, "user_paints", "role", "TEXT").await? {
+            detect_paint_roles(conn, "user_paints").await?;
+        }
+
+        // The paints a palette mixes with as its white and black, when chosen
+        add_column_if_missing(conn, "palettes", "white", "TEXT").await?;
+        add_column_if_missing(conn, "palettes", "black", "TEXT").await?;
+        Ok(())
+    })
+}
+
+fn redetect_paint_roles(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        use crate::services::colorimetry::Colorimetry;
+        use crate::services::paint_roles::detect_curve_role;
+
+        // Paints an admin has edited keep the role they were given
+        let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            r#"
+            SELECT rowid, spectral_curve FROM paints p
+            WHERE spectral_curve IS NOT NULL
+              AND NOT EXISTS (
+                  SELECT 1 FROM audit_log a
+                  WHERE a.brand_id = p.brand_id AND a.paint_name = p.name
+              )
+            "#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let colorimetry = Colorimetry::default();
+        for (rowid, blob) in rows {
+            let Ok(curve) = SpectralCurve::decode(&blob) else {
+                continue;
+            };
+            let role = detect_curve_role(&curve, &colorimetry);
+            sqlx::query("UPDATE paints SET role = ? WHERE rowid = ?")
+                .bind(role.map(|role| role.code()))
+                .bind(rowid)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    })
+}
+
+fn brand_catalogue(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS brands (
+                id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                manufacturer TEXT,
+                medium TEXT,
+                measurement_source TEXT,
+                data_version TEXT
+            );
+            CREATE TABLE IF NOT EXISTS paints (
+                brand_id TEXT NOT NULL REFERENCES brands(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                spectral_curve BLOB,
+                d65_10deg_hex TEXT,
+                opacity TEXT,
+                pigments TEXT,
+                lightfastness TEXT,
+                series INTEGER,
+                drying_speed TEXT,
+                role TEXT,
+                PRIMARY KEY (brand_id, name)
+            );
+            "#,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for brand in &LEGACY_BRANDS {
+            if table_exists(conn, brand.id).await? {
+                fold_legacy_brand_table(conn, brand).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn admin_users(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // Admins curate the paint catalogue; granted with `aop make-admin <email>`
+        add_column_if_missing(conn, "users", "is_admin", "INTEGER NOT NULL DEFAULT 0").await?;
+        Ok(())
+    })
+}
+
+fn catalogue_overlay(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // Deploys replace the reference database, so edits made on top of it live here
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE catalogue_brands (
+                id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                manufacturer TEXT,
+                medium TEXT,
+                measurement_source TEXT,
+                data_version TEXT
+            );
+            CREATE TABLE catalogue_paints (
+                brand_id TEXT NOT NULL,
+                base_name TEXT NOT NULL,
+                added BOOLEAN NOT NULL DEFAULT 0,
+                name TEXT NOT NULL,
+                spectral_curve BLOB,
+                d65_10deg_hex TEXT,
+                opacity TEXT,
+                pigments TEXT,
+                lightfastness TEXT,
+                series INTEGER,
+                drying_speed TEXT,
+                role TEXT,
+                retired_at TEXT,
+                PRIMARY KEY (brand_id, base_name),
+                UNIQUE (brand_id, name)
+            );
+            CREATE TABLE audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                user_email TEXT NOT NULL,
+                action TEXT NOT NULL,
+                brand_id TEXT,
+                paint_name TEXT,
+                details TEXT,
+                created_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // Changes made before then were written straight to the reference
+        // database and stay there; only their log comes across
+        let (logged,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM reference.sqlite_master
+            WHERE type = 'table' AND name = 'audit_log'
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if logged > 0 {
+            sqlx::query("INSERT INTO main.audit_log SELECT * FROM reference.audit_log ORDER BY id")
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    })
+}
+
+fn admin_catalogue(conn: &mut SqliteConnection) -> BoxFuture<'_, Result<(), sqlx::Error>> {
+    Box::pin(async move {
+        // Discontinued paints are retired rather than deleted so old mixes still make sense
+        add_column_if_missing(conn, "paints", "retired_at", "TEXT").await?;
+
+        // Who changed what in the paint catalogue
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                user_email TEXT NOT NULL,
+                action TEXT NOT NULL,
+                brand_id TEXT,
+                paint_name TEXT,
+                details TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    })
+}
+
+/// Catalogue entry for a brand that was stored in a table of its own
+struct LegacyBrand {
+    id: &'static str,
+    display_name: &'static str,
+    manufacturer: &'static str,
+    medium: &'static str,
+}
+
+/// The brand tables shipped before the catalogue
+const LEGACY_BRANDS: [LegacyBrand; 11] = [
+    LegacyBrand {
+        id: "winsor_newton_artist_oil_colour",
+        display_name: "Winsor & Newton Artists' Oil Colour",
+        manufacturer: "Winsor & Newton",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "daler_rowney_georgian_oil_colours",
+        display_name: "Daler-Rowney Georgian Oil Colours",
+        manufacturer: "Daler-Rowney",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "griffin_alkyd_fast_drying_oil_colour",
+        display_name: "Winsor & Newton Griffin Alkyd Fast Drying Oil Colour",
+        manufacturer: "Winsor & Newton",
+        medium: "alkyd",
+    },
+    LegacyBrand {
+        id: "gamblin_conservation_colors",
+        display_name: "Gamblin Conservation Colors",
+        manufacturer: "Gamblin",
+        medium: "resin",
+    },
+    LegacyBrand {
+        id: "michael_harding",
+        display_name: "Michael Harding Artists Oil Colours",
+        manufacturer: "Michael Harding",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "maimeri_puro_oil",
+        display_name: "Maimeri Puro Oil",
+        manufacturer: "Maimeri",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "schmincke_mussini_oils",
+        display_name: "Schmincke Mussini Oils",
+        manufacturer: "Schmincke",
+        medium: "resin oil",
+    },
+    LegacyBrand {
+        id: "sennellier_extra_fine_oils",
+        display_name: "Sennelier Extra Fine Oils",
+        manufacturer: "Sennelier",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "talens_van_gogh_oil_colour",
+        display_name: "Van Gogh Oil Colour",
+        manufacturer: "Royal Talens",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "williamsburg_handmade_oil_colors",
+        display_name: "Williamsburg Handmade Oil Colors",
+        manufacturer: "Golden Artist Colors",
+        medium: "oil",
+    },
+    LegacyBrand {
+        id: "winton_oil_colour",
+        display_name: "Winsor & Newton Winton Oil Colour",
+        manufacturer: "Winsor & Newton",
+        medium: "oil",
+    },
+];
+
+/// Data version recorded for brands carried over from the per-brand tables
+const LEGACY_DATA_VERSION: &str = "1";
+
+/// Legacy brand tables still present in the database
+async fn legacy_brand_tables(conn: &mut SqliteConnection) -> Result<Vec<String>, sqlx::Error> {
+    let mut tables = Vec::new();
+    for brand in &LEGACY_BRANDS {
+        if table_exists(conn, brand.id).await? {
+            tables.push(brand.id.to_string());
+        }
+    }
+    Ok(tables)
+}
+
+/// Move a legacy brand table's rows into `paints` under a catalogue entry
+async fn fold_legacy_brand_table(
+    conn: &mut SqliteConnection,
+    brand: &LegacyBrand,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO brands (id, display_name, manufacturer, medium, data_version)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(brand.id)
+    .bind(brand.display_name)
+    .bind(brand.manufacturer)
+    .bind(brand.medium)
+    .bind(LEGACY_DATA_VERSION)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(&format!(
+        r#"
+        INSERT OR IGNORE INTO paints (brand_id, name, spectral_curve, d65_10deg_hex, opacity,
+                                      pigments, lightfastness, series, drying_speed, role)
+        SELECT ?, _id, spectral_curve, d65_10deg_hex, opacity,
+               pigments, lightfastness, series, drying_speed, role
+        FROM {}
+        ORDER BY rowid
+        "#,
+        brand.id
+    ))
+    .bind(brand.id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(&format!("DROP TABLE {}", brand.id))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn table_exists(conn: &mut SqliteConnection, table: &str) -> Result<bool, sqlx::Error> {
+    let (count,): (i32,) =
+        sqlx::query_as("SELECT COUNT(*) FROM main.sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(conn)
+            .await?;
+    Ok(count > 0)
+}
+
+/// Fill in each paint's role from its spectral curve. Paints whose curve
+/// cannot be read, or that fit no role, are left without one.
+async fn detect_paint_roles(conn: &mut SqliteConnection, table: &str) -> Result<(), sqlx::Error> {
+    use crate::services::colorimetry::Colorimetry;
+    use crate::services::paint_roles::detect_curve_role;
+
+    let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(&format!(
+        "SELECT rowid, spectral_curve FROM {} WHERE spectral_curve IS NOT NULL",
+        table
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let colorimetry = Colorimetry::default();
+    for (rowid, blob) in rows {
+        let Some(role) = SpectralCurve::decode(&blob)
+            .ok()
+            .and_then(|curve| detect_curve_role(&curve, &colorimetry))
+        else {
+            continue;
+        };
+        sqlx::query(&format!("UPDATE {} SET role = ? WHERE rowid = ?", table))
+            .bind(role.code())
+            .bind(rowid)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Re-encode a table's unversioned `spectral_curve` blobs. `source_column`
+/// is an expression giving the user paint source ("measured" or "srgb"), or
+/// NULL when the origin is unknown. Blobs that cannot be read are left as
+/// they are; the `(name, error)` of each is returned so it can be reported.
+async fn migrate_spectral_curves(
+    conn: &mut SqliteConnection,
+    table: &str,
+    name_column: &str,
+    source_column: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows: Vec<(i64, String, Vec<u8>, Option<String>)> = sqlx::query_as(&format!(
+        "SELECT rowid, {}, spectral_curve, {} FROM {} WHERE spectral_curve IS NOT NULL",
+        name_column, source_column, table
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut failures = Vec::new();
+    for (rowid, name, blob, source) in rows {
+        if SpectralCurve::is_versioned(&blob) {
+            continue;
+        }
+        let mut curve = match SpectralCurve::from_legacy(&blob) {
+            Ok(curve) => curve,
+            Err(e) => {
+                failures.push((name, e.to_string()));
+                continue;
+            }
+        };
+        curve.metadata.origin = match source.as_deref() {
+            Some("measured") => CurveOrigin::Measured,
+            Some(_) => CurveOrigin::Reconstructed,
+            None => CurveOrigin::Unknown,
+        };
+        sqlx::query(&format!("UPDATE {} SET spectral_curve = ? WHERE rowid = ?", table))
+            .bind(curve.encode())
+            .bind(rowid)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    // Read the curves back, so an update lost to a damaged file is caught too
+    let stored: Vec<(String, Vec<u8>)> = sqlx::query_as(&format!(
+        "SELECT {}, spectral_curve FROM {} WHERE spectral_curve IS NOT NULL",
+        name_column, table
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+    for (name, blob) in stored {
+        if !SpectralCurve::is_versioned(&blob) && !failures.iter().any(|(n, _)| *n == name) {
+            failures.push((name, "still unversioned after conversion".to_string()));
+        }
+    }
+
+    for (name, error) in &failures {
+        eprintln!("WARNING: {} / {:?}: curve not converted: {}", table, name, error);
+    }
+    Ok(failures)
+}
+
+/// Keep the curves a migration could not convert in `curve_migration_failures`,
+/// which `aop validate` reports as errors
+async fn record_curve_failures(
+    conn: &mut SqliteConnection,
+    table: &str,
+    failures: &[(String, String)],
+) -> Result<(), sqlx::Error> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    sqlx::raw_sql(
+        r#"
+        CREATE TABLE IF NOT EXISTS curve_migration_failures (
+            brand_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            error TEXT NOT NULL,
+            PRIMARY KEY (brand_id, name)
+        );
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+    for (name, error) in failures {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO curve_migration_failures (brand_id, name, error)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(table)
+        .bind(name)
+        .bind(error)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+const PAINT_ATTRIBUTE_COLUMNS: [(&str, &str); 5] = [
+    ("opacity", "TEXT"),
+    ("pigments", "TEXT"),
+    ("lightfastness", "TEXT"),
+    ("series", "INTEGER"),
+    ("drying_speed", "TEXT"),
+];
+
+/// Add a column unless the table already has it; returns whether it was added
+async fn add_column_if_missing(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> Result<bool, sqlx::Error> {
+    let (count,): (i32,) =
+        sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info(?, 'main') WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&mut *conn)
+            .await?;
+    if count > 0 {
+        return Ok(false);
+    }
+    sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+        .execute(&mut *conn)
+        .await?;
+    Ok(true)
+}
@@ -1,6 +1,27 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{FromRow, Pool, Sqlite};
 
+use crate::models::{DryingSpeed, Lightfastness, Opacity, PaintAttributes, PaintRole};
+
+mod migrations;
+mod postgres;
+mod sessions;
+mod spectral_curve;
+mod sqlite;
+mod store;
+
+pub use migrations::*;
+pub use postgres::*;
+pub use sessions::*;
+pub use spectral_curve::*;
+pub use sqlite::*;
+pub use store::UserStore;
+
 pub type Db = Pool<Sqlite>;
 
 // User model
@@ -13,346 +34,580 @@ pub struct User {
     pub created_at: String,
     pub failed_attempts: i32,
     pub locked_until: Option<String>,
+    /// May edit the paint catalogue
+    pub is_admin: bool,
 }
 
-// Create connection pool - tries multiple paths for database file
-pub async fn create_pool(url: &str) -> Db {
-    // List of paths to try (in order of preference)
-    let paths_to_try = [
-        url.to_string(),
-        "sqlite:data.db".to_string(),
-        "sqlite:./data.db".to_string(),
-        "sqlite:target/site/data.db".to_string(),
-    ];
-
-    for db_url in &paths_to_try {
-        // Extract path from sqlite: URL
-        if let Some(path) = db_url.strip_prefix("sqlite:") {
-            let path = path.trim_start_matches("./");
-            if std::path::Path::new(path).exists() {
-                println!("Found database at: {}", path);
-                return sqlx::sqlite::SqlitePoolOptions::new()
-                    .max_connections(20)
-                    .connect(db_url)
-                    .await
-                    .expect("Failed to connect to database");
-            }
+/// Where the user database and the paint reference database live
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Accounts, settings, palettes and history: a `postgres://` URL, or a
+    /// SQLite one, created if missing
+    pub url: String,
+    /// Brands, paint spectra and colorimetry tables, shipped with the app
+    pub reference_url: String,
+}
+
+impl DbConfig {
+    /// Read `DATABASE_URL` and `REFERENCE_DATABASE_URL`
+    pub fn from_env() -> Self {
+        DbConfig {
+            url: std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:users.db".into()),
+            reference_url: std::env::var("REFERENCE_DATABASE_URL")
+                .unwrap_or_else(|_| "sqlite:data.db".into()),
         }
     }
 
-    // If no file found, print debug info and panic
-    eprintln!("ERROR: Could not find database file!");
-    eprintln!("Tried paths: {:?}", paths_to_try);
-    if let Ok(cwd) = std::env::current_dir() {
-        eprintln!("Current working directory: {:?}", cwd);
+    /// Whether user data is kept in Postgres
+    pub fn is_postgres(&self) -> bool {
+        self.url.starts_with("postgres://") || self.url.starts_with("postgresql://")
     }
-    if let Ok(entries) = std::fs::read_dir(".") {
-        eprintln!("Files in current directory:");
-        for entry in entries.flatten() {
-            eprintln!("  {:?}", entry.path());
+
+    fn reference_options(&self) -> SqliteConnectOptions {
+        let options = SqliteConnectOptions::from_str(&self.reference_url)
+            .unwrap_or_else(|e| panic!("Invalid REFERENCE_DATABASE_URL {}: {}", self.reference_url, e));
+        if !options.get_filename().exists() {
+            panic!(
+                "Reference database {} not found; set REFERENCE_DATABASE_URL",
+                options.get_filename().display()
+            );
         }
+        options
     }
-    panic!("Database file not found");
 }
 
-// Run migrations (create tables if not exist)
-pub async fn run_migrations(db: &Db) {
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id TEXT PRIMARY KEY,
-            email TEXT UNIQUE NOT NULL,
-            email_verified INTEGER DEFAULT 0,
-            password_hash TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            failed_attempts INTEGER DEFAULT 0,
-            locked_until TEXT
-        )
-        "#,
-    )
-    .execute(db)
-    .await
-    .expect("Failed to create users table");
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tokens (
-            id TEXT PRIMARY KEY,
-            user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-            kind TEXT NOT NULL,
-            hash TEXT NOT NULL,
-            expires_at TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(db)
-    .await
-    .expect("Failed to create tokens table");
-
-    // Migrate user_settings table to have proper primary key
-    // Check if old table exists without primary key
-    let has_pk: Option<(i32,)> = sqlx::query_as(
-        "SELECT COUNT(*) FROM pragma_table_info('user_settings') WHERE pk = 1"
-    )
-    .fetch_optional(db)
-    .await
-    .ok()
-    .flatten();
-
-    if has_pk.map(|(c,)| c).unwrap_or(0) == 0 {
-        // Table exists but without primary key - migrate it
-        let _ = sqlx::query("ALTER TABLE user_settings RENAME TO user_settings_old")
-            .execute(db)
-            .await;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS user_settings (
-                _id TEXT PRIMARY KEY,
-                email TEXT,
-                colour_mix_choice TEXT,
-                selected_colors TEXT
-            )
-            "#,
-        )
-        .execute(db)
+/// Connect to the paint reference database, read-only unless `writable`.
+/// Only migrations need it writable; catalogue edits go to the user database.
+pub async fn create_reference_pool(config: &DbConfig, writable: bool) -> Db {
+    let options = config.reference_options().read_only(!writable);
+    println!(
+        "Reference database: {} ({})",
+        options.get_filename().display(),
+        if writable { "writable" } else { "read-only" }
+    );
+    SqlitePoolOptions::new()
+        .max_connections(20)
+        .connect_with(options)
         .await
-        .expect("Failed to create user_settings table");
-
-        // Copy data from old table
-        let _ = sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO user_settings (_id, email, colour_mix_choice, selected_colors)
-            SELECT _id, email, colour_mix_choice, selected_colors FROM user_settings_old
-            WHERE _id IS NOT NULL
-            "#,
-        )
-        .execute(db)
-        .await;
-
-        let _ = sqlx::query("DROP TABLE IF EXISTS user_settings_old")
-            .execute(db)
-            .await;
-    } else {
-        // Just create if doesn't exist
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS user_settings (
-                _id TEXT PRIMARY KEY,
-                email TEXT,
-                colour_mix_choice TEXT,
-                selected_colors TEXT
-            )
-            "#,
-        )
-        .execute(db)
-        .await
-        .expect("Failed to create user_settings table");
-    }
+        .expect("Failed to connect to the reference database")
 }
 
-// User queries
-pub async fn get_user_by_email(db: &Db, email: &str) -> Option<User> {
-    sqlx::query_as("SELECT * FROM users WHERE email = ?")
-        .bind(email.to_lowercase())
-        .fetch_optional(db)
-        .await
-        .ok()
-        .flatten()
-}
+/// Connect to a SQLite user database, creating it if missing, with the
+/// reference database attached read-only as `reference`
+async fn create_user_pool(config: &DbConfig) -> Db {
+    let reference = config.reference_options();
+    // Characters with a meaning in URIs are escaped so any path can be attached
+    let path = reference
+        .get_filename()
+        .to_string_lossy()
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    let attach = format!("file:{}?mode=ro", path);
 
-pub async fn get_user_by_id(db: &Db, id: &str) -> Option<User> {
-    sqlx::query_as("SELECT * FROM users WHERE id = ?")
-        .bind(id)
-        .fetch_optional(db)
+    let options = SqliteConnectOptions::from_str(&config.url)
+        .unwrap_or_else(|e| panic!("Invalid DATABASE_URL {}: {}", config.url, e))
+        .create_if_missing(true);
+    SqlitePoolOptions::new()
+        .max_connections(20)
+        .after_connect(move |conn, _meta| {
+            let attach = attach.clone();
+            Box::pin(async move {
+                sqlx::query("ATTACH DATABASE ? AS reference")
+                    .bind(attach)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            })
+        })
+        .connect_with(options)
         .await
-        .ok()
-        .flatten()
+        .expect("Failed to connect to the user database")
 }
 
-pub async fn create_user(
-    db: &Db,
-    id: &str,
-    email: &str,
-    password_hash: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "INSERT INTO users (id, email, password_hash, created_at) VALUES (?, ?, ?, datetime('now'))",
-    )
-    .bind(id)
-    .bind(email.to_lowercase())
-    .bind(password_hash)
-    .execute(db)
-    .await?;
-    Ok(())
+/// The user database on whichever backend `DATABASE_URL` names
+#[derive(Clone)]
+pub enum UserDatabase {
+    Sqlite(SqliteStore),
+    Postgres(PostgresStore),
 }
 
-pub async fn verify_user_email(db: &Db, user_id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET email_verified = 1 WHERE id = ?")
-        .bind(user_id)
-        .execute(db)
-        .await?;
-    Ok(())
-}
+impl UserDatabase {
+    pub async fn connect(config: &DbConfig) -> Self {
+        if config.is_postgres() {
+            println!("User database: Postgres");
+            UserDatabase::Postgres(PostgresStore::connect(&config.url).await)
+        } else {
+            println!("User database: {}", config.url);
+            UserDatabase::Sqlite(SqliteStore::new(create_user_pool(config).await))
+        }
+    }
 
-pub async fn update_password(db: &Db, user_id: &str, hash: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
-        .bind(hash)
-        .bind(user_id)
-        .execute(db)
-        .await?;
-    Ok(())
+    pub fn store(&self) -> Arc<dyn UserStore> {
+        match self {
+            UserDatabase::Sqlite(store) => Arc::new(store.clone()),
+            UserDatabase::Postgres(store) => Arc::new(store.clone()),
+        }
+    }
+
+    pub async fn pending_migrations(&self) -> Result<usize, MigrationError> {
+        Ok(match self {
+            UserDatabase::Sqlite(store) => pending_migrations(store.pool(), &USER_DATABASE).await?.len(),
+            UserDatabase::Postgres(store) => {
+                pending_migrations(store.pool(), &POSTGRES_USER_DATABASE).await?.len()
+            }
+        })
+    }
+
+    pub async fn migrate_up(&self) -> Result<Vec<&'static str>, MigrationError> {
+        match self {
+            UserDatabase::Sqlite(store) => migrate_up(store.pool(), &USER_DATABASE).await,
+            UserDatabase::Postgres(store) => migrate_up(store.pool(), &POSTGRES_USER_DATABASE).await,
+        }
+    }
+
+    pub async fn migrate_down(&self, steps: usize) -> Result<Vec<&'static str>, MigrationError> {
+        match self {
+            UserDatabase::Sqlite(store) => migrate_down(store.pool(), &USER_DATABASE, steps).await,
+            UserDatabase::Postgres(store) => {
+                migrate_down(store.pool(), &POSTGRES_USER_DATABASE, steps).await
+            }
+        }
+    }
+
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, MigrationError> {
+        match self {
+            UserDatabase::Sqlite(store) => migration_status(store.pool(), &USER_DATABASE).await,
+            UserDatabase::Postgres(store) => {
+                migration_status(store.pool(), &POSTGRES_USER_DATABASE).await
+            }
+        }
+    }
 }
 
-pub async fn update_failed_attempts(
-    db: &Db,
-    user_id: &str,
-    count: i32,
-    locked_until: Option<&str>,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET failed_attempts = ?, locked_until = ? WHERE id = ?")
-        .bind(count)
-        .bind(locked_until)
-        .bind(user_id)
-        .execute(db)
-        .await?;
-    Ok(())
-}
-
-// Token queries
-pub async fn create_token(
-    db: &Db,
-    id: &str,
-    user_id: &str,
-    kind: &str,
-    hash: &str,
-    expires_at: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("INSERT INTO tokens (id, user_id, kind, hash, expires_at) VALUES (?, ?, ?, ?, ?)")
-        .bind(id)
-        .bind(user_id)
-        .bind(kind)
-        .bind(hash)
-        .bind(expires_at)
-        .execute(db)
-        .await?;
-    Ok(())
-}
-
-pub async fn get_token(db: &Db, hash: &str, kind: &str) -> Option<(String, String, String)> {
-    sqlx::query_as("SELECT id, user_id, expires_at FROM tokens WHERE hash = ? AND kind = ?")
-        .bind(hash)
-        .bind(kind)
-        .fetch_optional(db)
-        .await
-        .ok()
-        .flatten()
+/// Apply pending reference migrations. The last of them drops the accounts an
+/// old combined database held, so with Postgres those must be imported first.
+pub async fn migrate_reference(
+    users: &UserDatabase,
+    reference: &Db,
+) -> Result<Vec<&'static str>, MigrationError> {
+    if let UserDatabase::Postgres(store) = users {
+        if reference_holds_accounts(reference).await? && !store.has_users().await? {
+            return Err(MigrationError::AccountsNotImported);
+        }
+    }
+    migrate_up(reference, &REFERENCE_DATABASE).await
 }
 
-pub async fn delete_token(db: &Db, id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM tokens WHERE id = ?")
-        .bind(id)
-        .execute(db)
-        .await?;
-    Ok(())
+/// Apply every pending migration to the user database and then the reference
+/// database, panicking if one fails. `reference` must be writable.
+pub async fn run_migrations(users: &UserDatabase, reference: &Db) {
+    if let Err(e) = users.migrate_up().await {
+        panic!("Failed to migrate the user database: {}", e);
+    }
+    if let Err(e) = migrate_reference(users, reference).await {
+        panic!("Failed to migrate the reference database: {}", e);
+    }
 }
 
-// User settings queries
+// User settings
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct UserSettings {
     pub _id: String,
     pub email: Option<String>,
     pub colour_mix_choice: Option<String>,
     pub selected_colors: Option<String>,
+    pub active_palette_id: Option<String>,
+}
+
+// Palettes
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PaletteRow {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub mix_choice: String,
+    pub paints: String,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Paint chosen as the palette's white, as `{ "brand": ..., "name": ... }`
+    pub white: Option<String>,
+    pub black: Option<String>,
+}
+
+impl PaletteRow {
+    /// Decode `paints` (`{ "brand": ["color", ...], ... }`) into paints per brand
+    pub fn selected_by_brand(&self) -> BTreeMap<String, Vec<String>> {
+        serde_json::from_str(&self.paints).unwrap_or_default()
+    }
+}
+
+// Mix history
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MixHistoryRow {
+    pub id: String,
+    pub user_id: String,
+    pub kind: String,
+    pub target_hex: String,
+    pub palette_id: Option<String>,
+    pub palette_name: Option<String>,
+    pub mix_choice: Option<String>,
+    pub filters: Option<String>,
+    pub results: String,
+    pub starred: bool,
+    pub name: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// Projects
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProjectRow {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub notes: String,
+    pub reference_image: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Project list entry, without the (large) reference image
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProjectListRow {
+    pub id: String,
+    pub name: String,
+    pub target_count: i64,
+    pub updated_at: String,
 }
 
-pub async fn get_user_settings(db: &Db, user_id: &str) -> Option<UserSettings> {
-    sqlx::query_as("SELECT * FROM user_settings WHERE _id = ?")
-        .bind(user_id)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProjectTargetRow {
+    pub id: String,
+    pub project_id: String,
+    pub label: String,
+    pub target_hex: String,
+    pub palette_name: Option<String>,
+    pub mix_choice: Option<String>,
+    pub results: String,
+    pub chosen: Option<i64>,
+    pub notes: String,
+    pub created_at: String,
+}
+
+// Paint data queries
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PaintColor {
+    pub _id: String,
+    pub spectral_curve: Option<Vec<u8>>,
+    pub d65_10deg_hex: Option<String>,
+    pub opacity: Option<String>,
+    pub pigments: Option<String>,
+    pub lightfastness: Option<String>,
+    pub series: Option<i64>,
+    pub drying_speed: Option<String>,
+    pub role: Option<String>,
+}
+
+impl PaintColor {
+    /// Decode the stored spectral curve
+    pub fn curve(&self) -> Result<SpectralCurve, CurveDecodeError> {
+        SpectralCurve::decode(self.spectral_curve.as_deref().unwrap_or_default())
+    }
+
+    /// Parse the raw metadata columns into typed attributes, ignoring unknown codes
+    pub fn attributes(&self) -> PaintAttributes {
+        PaintAttributes {
+            opacity: self.opacity.as_deref().and_then(Opacity::from_code),
+            pigments: self
+                .pigments
+                .as_deref()
+                .map(PaintAttributes::parse_pigments)
+                .unwrap_or_default(),
+            lightfastness: self.lightfastness.as_deref().and_then(Lightfastness::from_code),
+            series: self.series.and_then(|s| u8::try_from(s).ok()),
+            drying_speed: self.drying_speed.as_deref().and_then(DryingSpeed::from_code),
+            role: self.role.as_deref().and_then(PaintRole::from_code),
+        }
+    }
+}
+
+// Brand catalogue queries
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BrandRow {
+    pub id: String,
+    pub display_name: String,
+    pub manufacturer: Option<String>,
+    /// Binder, e.g. "oil", "alkyd" or "resin"
+    pub medium: Option<String>,
+    /// Where the spectral measurements came from
+    pub measurement_source: Option<String>,
+    pub data_version: Option<String>,
+}
+
+/// Catalogue edits made by admins. They are kept in the user database, so a
+/// new reference database deployed over the old one does not lose them.
+#[derive(Debug, Clone, Default)]
+pub struct CatalogueOverlay {
+    /// Brands added, or replacing the reference brand with the same id
+    pub brands: Vec<BrandRow>,
+    /// Paints added, or replacing the reference paint named `base_name`
+    pub paints: Vec<BrandPaintRow>,
+}
+
+impl CatalogueOverlay {
+    fn apply_to_brands(&self, mut brands: Vec<BrandRow>) -> Vec<BrandRow> {
+        for edited in &self.brands {
+            match brands.iter_mut().find(|brand| brand.id == edited.id) {
+                Some(brand) => *brand = edited.clone(),
+                None => brands.push(edited.clone()),
+            }
+        }
+        brands.sort_by_key(|brand| brand.display_name.to_lowercase());
+        brands
+    }
+
+    /// Edited paints take the place of the reference paints they replace;
+    /// added ones follow, as do edits of paints no longer in the reference
+    fn apply_to_paints(&self, brand: &str, paints: Vec<BrandPaintRow>) -> Vec<BrandPaintRow> {
+        let mut edited: Vec<&BrandPaintRow> =
+            self.paints.iter().filter(|paint| paint.brand_id == brand).collect();
+        let mut merged: Vec<BrandPaintRow> = paints
+            .into_iter()
+            .map(|paint| {
+                match edited.iter().position(|e| !e.added && e.base_name == paint.base_name) {
+                    Some(i) => edited.remove(i).clone(),
+                    None => paint,
+                }
+            })
+            .collect();
+        merged.extend(edited.into_iter().cloned());
+        merged
+    }
+}
+
+pub async fn list_brands(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+) -> Result<Vec<BrandRow>, sqlx::Error> {
+    let brands = sqlx::query_as("SELECT * FROM brands").fetch_all(db).await?;
+    Ok(overlay.apply_to_brands(brands))
+}
+
+pub async fn get_brand(db: &Db, overlay: &CatalogueOverlay, id: &str) -> Option<BrandRow> {
+    if let Some(brand) = overlay.brands.iter().find(|brand| brand.id == id) {
+        return Some(brand.clone());
+    }
+    sqlx::query_as("SELECT * FROM brands WHERE id = ?")
+        .bind(id)
         .fetch_optional(db)
         .await
         .ok()
         .flatten()
 }
 
-pub async fn upsert_user_settings(
+/// Ids of every catalogued brand
+pub async fn get_paint_brands(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+) -> Result<Vec<String>, sqlx::Error> {
+    Ok(list_brands(db, overlay).await?.into_iter().map(|b| b.id).collect())
+}
+
+/// A brand's current paints, leaving out retired ones
+pub async fn get_paint_colors(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+    brand: &str,
+) -> Result<Vec<PaintColor>, sqlx::Error> {
+    Ok(read_brand_paints(db, overlay, brand)
+        .await?
+        .into_iter()
+        .filter(|paint| paint.retired_at.is_none())
+        .map(|paint| paint.color)
+        .collect())
+}
+
+/// Problems `PRAGMA integrity_check` finds in the database file; empty when it is sound
+pub async fn integrity_problems(db: &Db) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check").fetch_all(db).await?;
+    // Problems can come back several to a row, under a `*** in database main ***` heading
+    Ok(rows
+        .iter()
+        .flat_map(|(text,)| text.lines())
+        .filter(|line| *line != "ok" && !line.starts_with("***"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// A curve the migrations could not convert to the versioned encoding
+#[derive(Debug, Clone, FromRow)]
+pub struct CurveMigrationFailure {
+    pub brand_id: String,
+    pub name: String,
+    pub error: String,
+}
+
+/// Curves recorded as unconverted by the migrations, if there were any
+pub async fn list_curve_migration_failures(
     db: &Db,
-    user_id: &str,
-    email: &str,
-    mix_choice: &str,
-    selected_colors: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
+) -> Result<Vec<CurveMigrationFailure>, sqlx::Error> {
+    let (tables,): (i64,) = sqlx::query_as(
         r#"
-        INSERT INTO user_settings (_id, email, colour_mix_choice, selected_colors)
-        VALUES (?, ?, ?, ?)
-        ON CONFLICT(_id) DO UPDATE SET
-            email = excluded.email,
-            colour_mix_choice = excluded.colour_mix_choice,
-            selected_colors = excluded.selected_colors
+        SELECT COUNT(*) FROM sqlite_master
+        WHERE type = 'table' AND name = 'curve_migration_failures'
         "#,
     )
-    .bind(user_id)
-    .bind(email)
-    .bind(mix_choice)
-    .bind(selected_colors)
-    .execute(db)
+    .fetch_one(db)
     .await?;
-    Ok(())
+    if tables == 0 {
+        return Ok(vec![]);
+    }
+    sqlx::query_as(
+        "SELECT brand_id, name, error FROM curve_migration_failures ORDER BY brand_id, name",
+    )
+    .fetch_all(db)
+    .await
 }
 
-// Paint data queries
+// Catalogue editing, used by admins. Changes are written to the user
+// database with `UserStore::save_catalogue_paints` and `save_catalogue_brand`,
+// together with their audit log entries.
+
+/// A catalogued paint with its retirement date, as the reference database
+/// has it or as an admin left it
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
-pub struct PaintColor {
-    pub _id: String,
-    pub spectral_curve: Option<Vec<u8>>,
-    pub d65_10deg_hex: Option<String>,
+pub struct BrandPaintRow {
+    pub brand_id: String,
+    /// Name of the reference paint an edit replaces, which renames leave
+    /// alone; a paint an admin added has an id of its own here instead
+    pub base_name: String,
+    /// Added by an admin rather than shipped in the reference database
+    pub added: bool,
+    #[sqlx(flatten)]
+    pub color: PaintColor,
+    pub retired_at: Option<String>,
 }
 
-pub async fn get_paint_brands(_db: &Db) -> Vec<String> {
-    // Return list of paint brand table names
-    vec![
-        "winsor_newton_artist_oil_colour".into(),
-        "daler_rowney_georgian_oil_colours".into(),
-        "griffin_alkyd_fast_drying_oil_colour".into(),
-        "gamblin_conservation_colors".into(),
-        "michael_harding".into(),
-        "maimeri_puro_oil".into(),
-        "schmincke_mussini_oils".into(),
-        "sennellier_extra_fine_oils".into(),
-        "talens_van_gogh_oil_colour".into(),
-        "williamsburg_handmade_oil_colors".into(),
-        "winton_oil_colour".into(),
-    ]
-}
-
-pub async fn get_paint_colors(db: &Db, brand: &str) -> Vec<PaintColor> {
-    // Sanitize brand name to prevent SQL injection
-    let valid_brands = get_paint_brands(db).await;
-    if !valid_brands.contains(&brand.to_string()) {
-        return vec![];
+impl PaintColor {
+    /// Replace the metadata columns with typed attributes
+    pub fn set_attributes(&mut self, attributes: &PaintAttributes) {
+        self.opacity = attributes.opacity.map(|o| o.code().to_string());
+        self.pigments = Some(attributes.pigments.join(", ")).filter(|p| !p.is_empty());
+        self.lightfastness = attributes.lightfastness.map(|lf| lf.code().to_string());
+        self.series = attributes.series.map(i64::from);
+        self.drying_speed = attributes.drying_speed.map(|d| d.code().to_string());
+        self.role = attributes.role.map(|r| r.code().to_string());
     }
-
-    let query = format!("SELECT _id, spectral_curve, d65_10deg_hex FROM {}", brand);
-    sqlx::query_as(&query)
-        .fetch_all(db)
-        .await
-        .unwrap_or_default()
 }
 
-pub async fn get_spectral_data(db: &Db, brand: &str, color: &str) -> Option<Vec<u8>> {
-    let valid_brands = get_paint_brands(db).await;
-    if !valid_brands.contains(&brand.to_string()) {
-        return None;
+/// Paints and retired paints per brand id
+pub async fn count_brand_paints(db: &Db, overlay: &CatalogueOverlay) -> Vec<(String, i64, i64)> {
+    let mut counts = Vec::new();
+    for brand in get_paint_brands(db, overlay).await.unwrap_or_default() {
+        let paints = list_brand_paints(db, overlay, &brand).await;
+        let retired = paints.iter().filter(|p| p.retired_at.is_some()).count();
+        counts.push((brand, paints.len() as i64, retired as i64));
     }
+    counts
+}
 
-    let query = format!("SELECT spectral_curve FROM {} WHERE _id = ?", brand);
-    let result: Option<(Vec<u8>,)> = sqlx::query_as(&query)
-        .bind(color)
-        .fetch_optional(db)
+async fn read_brand_paints(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+    brand: &str,
+) -> Result<Vec<BrandPaintRow>, sqlx::Error> {
+    let paints = sqlx::query_as(
+        r#"
+        SELECT brand_id, name AS base_name, FALSE AS added, name AS _id, spectral_curve,
+               d65_10deg_hex, opacity, pigments, lightfastness, series, drying_speed, role,
+               retired_at
+        FROM paints
+        WHERE brand_id = ?
+        ORDER BY rowid
+        "#,
+    )
+    .bind(brand)
+    .fetch_all(db)
+    .await?;
+    Ok(overlay.apply_to_paints(brand, paints))
+}
+
+/// Every paint of a brand, retired ones included
+pub async fn list_brand_paints(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+    brand: &str,
+) -> Vec<BrandPaintRow> {
+    read_brand_paints(db, overlay, brand).await.unwrap_or_default()
+}
+
+pub async fn get_brand_paint(
+    db: &Db,
+    overlay: &CatalogueOverlay,
+    brand: &str,
+    name: &str,
+) -> Option<BrandPaintRow> {
+    list_brand_paints(db, overlay, brand)
         .await
-        .ok()
-        .flatten();
+        .into_iter()
+        .find(|paint| paint.color._id == name)
+}
 
-    result.map(|(data,)| data)
+// Audit log queries
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditLogRow {
+    pub id: i64,
+    pub user_id: String,
+    pub user_email: String,
+    pub action: String,
+    pub brand_id: Option<String>,
+    pub paint_name: Option<String>,
+    /// JSON describing the change, e.g. the values before and after
+    pub details: Option<String>,
+    pub created_at: String,
+}
+
+/// A catalogue change to record in the audit log
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub user_id: String,
+    pub user_email: String,
+    pub action: String,
+    pub brand_id: Option<String>,
+    pub paint_name: Option<String>,
+    pub details: Option<String>,
+}
+
+// User paints
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserPaintRow {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub spectral_curve: Vec<u8>,
+    pub hex: String,
+    pub source: String,
+    pub opacity: Option<String>,
+    pub pigments: Option<String>,
+    pub lightfastness: Option<String>,
+    pub series: Option<i64>,
+    pub drying_speed: Option<String>,
+    pub role: Option<String>,
+    pub created_at: String,
+}
+
+// Batch jobs
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BatchJobRow {
+    pub id: String,
+    pub user_id: String,
+    pub total: i64,
+    pub completed: i64,
+    pub finished: bool,
+    /// `Vec<BatchMixResult>` as JSON, once finished
+    pub results: Option<String>,
+    pub error: Option<String>,
+    /// Unix seconds; the server running the job moves it on while it runs
+    pub updated_at: i64,
 }
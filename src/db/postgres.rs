@@ -0,0 +1,1284 @@
+//! User data in Postgres, for deployments running several server instances
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use sqlx::postgres::{PgConnection, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{PgPool, Row};
+
+use super::migrations::{BoxFuture, USER_TABLES};
+use super::store::{renamed_palette_paint, UserStore};
+use super::{
+    AuditLogRow, BatchJobRow, BrandPaintRow, BrandRow, CatalogueOverlay, Db, Migration,
+    MigrationDb, MigrationError, MigrationSet, MigrationStep, MixHistoryRow, NewAuditEntry,
+    PaintColor, PaletteRow, ProjectListRow, ProjectRow, ProjectTargetRow, User, UserPaintRow,
+    UserSettings,
+};
+
+/// Migrations of a Postgres user database, oldest first. It starts from the
+/// schema the SQLite user database had reached; existing SQLite accounts are
+/// copied in with `aop import-users`.
+pub static POSTGRES_USER_MIGRATIONS: &[Migration<PgConnection>] = &[
+    Migration {
+        version: "m20261018_100001_create_user_tables",
+        description: "accounts, settings, palettes, mix history, projects and user paints",
+        up: MigrationStep::Sql(
+            r#"
+            -- The same text timestamps as SQLite's datetime('now'), so both sort alike
+            CREATE OR REPLACE FUNCTION datetime_now() RETURNS TEXT AS $$
+                SELECT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+            $$ LANGUAGE SQL STABLE;
+            CREATE TABLE users (
+                id TEXT PRIMARY KEY,
+                email TEXT UNIQUE NOT NULL,
+                email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+                password_hash TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                failed_attempts INTEGER NOT NULL DEFAULT 0,
+                locked_until TEXT,
+                is_admin BOOLEAN NOT NULL DEFAULT FALSE
+            );
+            CREATE TABLE tokens (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            );
+            CREATE TABLE user_settings (
+                _id TEXT PRIMARY KEY,
+                email TEXT,
+                colour_mix_choice TEXT,
+                selected_colors TEXT,
+                active_palette_id TEXT
+            );
+            CREATE TABLE palettes (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                mix_choice TEXT NOT NULL,
+                paints TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                white TEXT,
+                black TEXT,
+                UNIQUE (user_id, name)
+            );
+            CREATE TABLE mix_history (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                target_hex TEXT NOT NULL,
+                palette_id TEXT,
+                palette_name TEXT,
+                mix_choice TEXT,
+                filters TEXT,
+                results TEXT NOT NULL,
+                starred BOOLEAN NOT NULL DEFAULT FALSE,
+                name TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_mix_history_user ON mix_history(user_id, created_at);
+            CREATE TABLE projects (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                notes TEXT NOT NULL DEFAULT '',
+                reference_image TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE project_targets (
+                id TEXT PRIMARY KEY,
+                project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                label TEXT NOT NULL,
+                target_hex TEXT NOT NULL,
+                palette_name TEXT,
+                mix_choice TEXT,
+                results TEXT NOT NULL,
+                chosen BIGINT,
+                notes TEXT NOT NULL DEFAULT '',
+                created_at TEXT NOT NULL,
+                -- Insertion order, which SQLite keeps as the rowid
+                seq BIGSERIAL
+            );
+            CREATE TABLE user_paints (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                spectral_curve BYTEA NOT NULL,
+                hex TEXT NOT NULL,
+                source TEXT NOT NULL,
+                opacity TEXT,
+                pigments TEXT,
+                lightfastness TEXT,
+                series BIGINT,
+                drying_speed TEXT,
+                role TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE (user_id, name)
+            );
+            "#,
+        ),
+        down: Some(MigrationStep::Sql(
+            r#"
+            DROP TABLE user_paints, project_targets, projects, mix_history, palettes,
+                       user_settings, tokens, users;
+            DROP FUNCTION datetime_now();
+            "#,
+        )),
+    },
+    Migration {
+        version: "m20261018_100002_create_sessions",
+        description: "sign-in sessions that survive a restart",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                created_at BIGINT NOT NULL,
+                expires_at BIGINT NOT NULL
+            );
+            CREATE INDEX idx_sessions_expires_at ON sessions(expires_at);
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE sessions;")),
+    },
+    Migration {
+        version: "m20261018_100003_create_batch_jobs",
+        description: "batch mix jobs, so any server can report their progress",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE batch_jobs (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                total BIGINT NOT NULL,
+                completed BIGINT NOT NULL DEFAULT 0,
+                finished BOOLEAN NOT NULL DEFAULT FALSE,
+                results TEXT,
+                error TEXT,
+                created_at BIGINT NOT NULL,
+                updated_at BIGINT NOT NULL
+            );
+            CREATE INDEX idx_batch_jobs_user_id ON batch_jobs(user_id);
+            "#,
+        ),
+        down: Some(MigrationStep::Sql("DROP TABLE batch_jobs;")),
+    },
+    Migration {
+        version: "m20261018_100004_catalogue_overlay",
+        description: "admins' catalogue edits and their audit log, out of the reference database",
+        up: MigrationStep::Sql(
+            r#"
+            CREATE TABLE catalogue_brands (
+                id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                manufacturer TEXT,
+                medium TEXT,
+                measurement_source TEXT,
+                data_version TEXT
+            );
+            CREATE TABLE catalogue_paints (
+                brand_id TEXT NOT NULL,
+                base_name TEXT NOT NULL,
+                added BOOLEAN NOT NULL DEFAULT FALSE,
+                name TEXT NOT NULL,
+                spectral_curve BYTEA,
+                d65_10deg_hex TEXT,
+                opacity TEXT,
+                pigments TEXT,
+                lightfastness TEXT,
+                series BIGINT,
+                drying_speed TEXT,
+                role TEXT,
+                retired_at TEXT,
+                PRIMARY KEY (brand_id, base_name),
+                UNIQUE (brand_id, name)
+            );
+            CREATE TABLE audit_log (
+                id BIGSERIAL PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                user_email TEXT NOT NULL,
+                action TEXT NOT NULL,
+                brand_id TEXT,
+                paint_name TEXT,
+                details TEXT,
+                created_at TEXT NOT NULL
+            );
+            "#,
+        ),
+        down: Some(MigrationStep::Sql(
+            "DROP TABLE audit_log; DROP TABLE catalogue_paints; DROP TABLE catalogue_brands;",
+        )),
+    },
+];
+
+/// Admins' catalogue edits, copied by `aop import-users` along with the accounts
+const CATALOGUE_TABLES: [&str; 3] = ["catalogue_brands", "catalogue_paints", "audit_log"];
+
+/// The user database on Postgres
+pub static POSTGRES_USER_DATABASE: MigrationSet<PgConnection> = MigrationSet {
+    label: "user",
+    migrations: POSTGRES_USER_MIGRATIONS,
+    shared_with: &[],
+};
+
+impl MigrationDb for PgPool {
+    type Connection = PgConnection;
+
+    fn applied_migrations(&self) -> BoxFuture<'_, Result<Vec<(String, i64)>, sqlx::Error>> {
+        Box::pin(async move {
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS "seaql_migrations" (
+                    "version" text NOT NULL PRIMARY KEY,
+                    "applied_at" bigint NOT NULL
+                )
+                "#,
+            )
+            .execute(self)
+            .await?;
+            sqlx::query_as(r#"SELECT "version", "applied_at" FROM "seaql_migrations""#)
+                .fetch_all(self)
+                .await
+        })
+    }
+
+    fn run_migration<'a>(
+        &'a self,
+        version: &'static str,
+        step: &'a MigrationStep<PgConnection>,
+        apply: bool,
+    ) -> BoxFuture<'a, Result<(), MigrationError>> {
+        Box::pin(async move {
+            let mut tx = self.begin().await?;
+            let result = match step {
+                MigrationStep::Sql(sql) => sqlx::raw_sql(sql).execute(&mut *tx).await.map(|_| ()),
+                MigrationStep::Code(f) => f(&mut tx).await,
+            };
+            result.map_err(|source| MigrationError::Failed { version, source })?;
+            if apply {
+                sqlx::query(r#"INSERT INTO "seaql_migrations" ("version", "applied_at") VALUES ($1, $2)"#)
+                    .bind(version)
+                    .bind(chrono::Utc::now().timestamp())
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query(r#"DELETE FROM "seaql_migrations" WHERE "version" = $1"#)
+                    .bind(version)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+}
+
+/// User data in Postgres
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str) -> Self {
+        let pool = PgPoolOptions::new()
+            .max_connections(20)
+            .connect(url)
+            .await
+            .expect("Failed to connect to the user database");
+        PostgresStore { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Whether any account exists yet
+    pub async fn has_users(&self) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users)")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Copy the user tables and catalogue edits of a SQLite database into this
+    /// one, in one transaction; returns the rows copied per table
+    pub async fn import_sqlite(&self, source_url: &str) -> Result<Vec<(&'static str, u64)>, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(source_url)?.read_only(true);
+        let source: Db = SqlitePoolOptions::new().connect_with(options).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let mut copied = Vec::new();
+        // Parents come before the tables referencing them
+        for table in USER_TABLES.into_iter().chain(CATALOGUE_TABLES) {
+            let source_columns: Vec<String> =
+                sqlx::query_scalar("SELECT name FROM pragma_table_info(?, 'main')")
+                    .bind(table)
+                    .fetch_all(&source)
+                    .await?;
+            if source_columns.is_empty() {
+                continue;
+            }
+            let columns: Vec<(String, String)> = sqlx::query_as(
+                r#"
+                SELECT column_name::TEXT, data_type::TEXT FROM information_schema.columns
+                WHERE table_schema = current_schema() AND table_name = $1
+                ORDER BY ordinal_position
+                "#,
+            )
+            .bind(table)
+            .fetch_all(&mut *tx)
+            .await?;
+            let columns: Vec<(String, String)> = columns
+                .into_iter()
+                .filter(|(name, _)| source_columns.contains(name))
+                .collect();
+
+            let names = columns.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ");
+            let params = (1..=columns.len()).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ");
+            let select = format!("SELECT {} FROM {} ORDER BY rowid", names, table);
+            let insert = format!("INSERT INTO {} ({}) VALUES ({})", table, names, params);
+
+            let rows = sqlx::query(&select).fetch_all(&source).await?;
+            for row in &rows {
+                let mut query = sqlx::query(&insert);
+                for (i, (_, data_type)) in columns.iter().enumerate() {
+                    query = match data_type.as_str() {
+                        "boolean" => query.bind(row.try_get::<Option<bool>, _>(i)?),
+                        "integer" => query.bind(row.try_get::<Option<i32>, _>(i)?),
+                        "bigint" => query.bind(row.try_get::<Option<i64>, _>(i)?),
+                        "bytea" => query.bind(row.try_get::<Option<Vec<u8>>, _>(i)?),
+                        _ => query.bind(row.try_get::<Option<String>, _>(i)?),
+                    };
+                }
+                query.execute(&mut *tx).await?;
+            }
+            copied.push((table, rows.len() as u64));
+        }
+        // Log entries keep their ids, so new ones are numbered after them
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('audit_log', 'id'), MAX(id)) FROM audit_log",
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(copied)
+    }
+
+    async fn touch_project(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE projects SET updated_at = datetime_now() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for PostgresStore {
+    async fn get_user_by_email(&self, email: &str) -> Option<User> {
+        sqlx::query_as("SELECT * FROM users WHERE email = $1")
+            .bind(email.to_lowercase())
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn get_user_by_id(&self, id: &str) -> Option<User> {
+        sqlx::query_as("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_user(
+        &self,
+        id: &str,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash, created_at) VALUES ($1, $2, $3, datetime_now())",
+        )
+        .bind(id)
+        .bind(email.to_lowercase())
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn verify_user_email(&self, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET email_verified = TRUE WHERE id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: &str, hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(hash)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_user_admin(&self, email: &str, is_admin: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET is_admin = $1 WHERE email = $2")
+            .bind(is_admin)
+            .bind(email.to_lowercase())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_failed_attempts(
+        &self,
+        user_id: &str,
+        count: i32,
+        locked_until: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET failed_attempts = $1, locked_until = $2 WHERE id = $3")
+            .bind(count)
+            .bind(locked_until)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        hash: &str,
+        expires_at: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO tokens (id, user_id, kind, hash, expires_at) VALUES ($1, $2, $3, $4, $5)")
+            .bind(id)
+            .bind(user_id)
+            .bind(kind)
+            .bind(hash)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_token(&self, hash: &str, kind: &str) -> Option<(String, String, String)> {
+        sqlx::query_as("SELECT id, user_id, expires_at FROM tokens WHERE hash = $1 AND kind = $2")
+            .bind(hash)
+            .bind(kind)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn delete_token(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM tokens WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user_settings(&self, user_id: &str) -> Option<UserSettings> {
+        sqlx::query_as("SELECT * FROM user_settings WHERE _id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn set_active_palette(
+        &self,
+        user_id: &str,
+        email: &str,
+        palette_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_settings (_id, email, active_palette_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT(_id) DO UPDATE SET
+                email = excluded.email,
+                active_palette_id = excluded.active_palette_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(palette_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_palettes(&self, user_id: &str) -> Vec<PaletteRow> {
+        sqlx::query_as("SELECT * FROM palettes WHERE user_id = $1 ORDER BY created_at, name")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_palette(&self, user_id: &str, id: &str) -> Option<PaletteRow> {
+        sqlx::query_as("SELECT * FROM palettes WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO palettes (id, user_id, name, mix_choice, paints, white, black, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, datetime_now(), datetime_now())
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .bind(mix_choice)
+        .bind(paints)
+        .bind(white)
+        .bind(black)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE palettes SET name = $1, mix_choice = $2, paints = $3, white = $4, black = $5,
+                                updated_at = datetime_now()
+            WHERE id = $6 AND user_id = $7
+            "#,
+        )
+        .bind(name)
+        .bind(mix_choice)
+        .bind(paints)
+        .bind(white)
+        .bind(black)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_palette(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM palettes WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn rename_palette_paint(&self, brand: &str, name: &str, new_name: &str) -> Result<(), sqlx::Error> {
+        let palettes: Vec<PaletteRow> = sqlx::query_as("SELECT * FROM palettes")
+            .fetch_all(&self.pool)
+            .await?;
+        for palette in palettes {
+            let Some((paints, white, black)) = renamed_palette_paint(&palette, brand, name, new_name) else {
+                continue;
+            };
+            sqlx::query("UPDATE palettes SET paints = $1, white = $2, black = $3 WHERE id = $4")
+                .bind(paints)
+                .bind(white)
+                .bind(black)
+                .bind(&palette.id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_mix_history(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        target_hex: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        filters: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO mix_history (id, user_id, kind, target_hex, palette_id, palette_name,
+                                     mix_choice, filters, results, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, datetime_now(), datetime_now())
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(kind)
+        .bind(target_hex)
+        .bind(palette_id)
+        .bind(palette_name)
+        .bind(mix_choice)
+        .bind(filters)
+        .bind(results)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_mix_history(&self, user_id: &str, starred_only: bool) -> Vec<MixHistoryRow> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM mix_history
+            WHERE user_id = $1 AND (starred OR NOT $2)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(starred_only)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn get_mix_history(&self, user_id: &str, id: &str) -> Option<MixHistoryRow> {
+        sqlx::query_as("SELECT * FROM mix_history WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn update_mix_history_recipe(
+        &self,
+        id: &str,
+        user_id: &str,
+        starred: bool,
+        name: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE mix_history SET starred = $1, name = $2, updated_at = datetime_now()
+            WHERE id = $3 AND user_id = $4
+            "#,
+        )
+        .bind(starred)
+        .bind(name)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_mix_history_results(
+        &self,
+        id: &str,
+        user_id: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE mix_history SET palette_id = $1, palette_name = $2, results = $3,
+                                   updated_at = datetime_now()
+            WHERE id = $4 AND user_id = $5
+            "#,
+        )
+        .bind(palette_id)
+        .bind(palette_name)
+        .bind(results)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_mix_history(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM mix_history WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_projects(&self, user_id: &str) -> Vec<ProjectListRow> {
+        sqlx::query_as(
+            r#"
+            SELECT p.id, p.name, p.updated_at,
+                   (SELECT COUNT(*) FROM project_targets t WHERE t.project_id = p.id) AS target_count
+            FROM projects p
+            WHERE p.user_id = $1
+            ORDER BY p.updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn get_project(&self, user_id: &str, id: &str) -> Option<ProjectRow> {
+        sqlx::query_as("SELECT * FROM projects WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_project(&self, id: &str, user_id: &str, name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO projects (id, user_id, name, created_at, updated_at)
+            VALUES ($1, $2, $3, datetime_now(), datetime_now())
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_project(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        notes: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE projects SET name = $1, notes = $2, updated_at = datetime_now()
+            WHERE id = $3 AND user_id = $4
+            "#,
+        )
+        .bind(name)
+        .bind(notes)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_project_image(
+        &self,
+        id: &str,
+        user_id: &str,
+        reference_image: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE projects SET reference_image = $1, updated_at = datetime_now()
+            WHERE id = $2 AND user_id = $3
+            "#,
+        )
+        .bind(reference_image)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_project(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM projects WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_project_targets(&self, project_id: &str) -> Vec<ProjectTargetRow> {
+        sqlx::query_as("SELECT * FROM project_targets WHERE project_id = $1 ORDER BY created_at, seq")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_project_target(&self, user_id: &str, id: &str) -> Option<ProjectTargetRow> {
+        sqlx::query_as(
+            r#"
+            SELECT t.* FROM project_targets t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.id = $1 AND p.user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()
+        .flatten()
+    }
+
+    async fn insert_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        target_hex: &str,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        results: &str,
+        chosen: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO project_targets (id, project_id, label, target_hex, palette_name,
+                                         mix_choice, results, chosen, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, datetime_now())
+            "#,
+        )
+        .bind(id)
+        .bind(project_id)
+        .bind(label)
+        .bind(target_hex)
+        .bind(palette_name)
+        .bind(mix_choice)
+        .bind(results)
+        .bind(chosen)
+        .execute(&self.pool)
+        .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn update_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        chosen: Option<i64>,
+        notes: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE project_targets SET label = $1, chosen = $2, notes = $3 WHERE id = $4")
+            .bind(label)
+            .bind(chosen)
+            .bind(notes)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn delete_project_target(&self, id: &str, project_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM project_targets WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn list_user_paints(&self, user_id: &str) -> Vec<UserPaintRow> {
+        sqlx::query_as("SELECT * FROM user_paints WHERE user_id = $1 ORDER BY lower(name)")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_user_paint_colors(&self, user_id: &str) -> Vec<PaintColor> {
+        sqlx::query_as(
+            r#"
+            SELECT name AS _id, spectral_curve, hex AS d65_10deg_hex,
+                   opacity, pigments, lightfastness, series, drying_speed, role
+            FROM user_paints
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn insert_user_paint(&self, paint: &UserPaintRow) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_paints (id, user_id, name, spectral_curve, hex, source, opacity,
+                                     pigments, lightfastness, series, drying_speed, role, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, datetime_now())
+            "#,
+        )
+        .bind(&paint.id)
+        .bind(&paint.user_id)
+        .bind(&paint.name)
+        .bind(&paint.spectral_curve)
+        .bind(&paint.hex)
+        .bind(&paint.source)
+        .bind(&paint.opacity)
+        .bind(&paint.pigments)
+        .bind(&paint.lightfastness)
+        .bind(paint.series)
+        .bind(&paint.drying_speed)
+        .bind(&paint.role)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_user_paint(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM user_paints WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn create_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO sessions (id, data, created_at, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(id)
+        .bind(data)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn save_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO sessions (id, data, created_at, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (id) DO UPDATE SET data = excluded.data, expires_at = excluded.expires_at",
+        )
+        .bind(id)
+        .bind(data)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_session(
+        &self,
+        id: &str,
+        now: i64,
+        created_after: i64,
+    ) -> Result<Option<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT data, expires_at FROM sessions WHERE id = $1 AND expires_at > $2 AND created_at > $3",
+        )
+        .bind(id)
+        .bind(now)
+        .bind(created_after)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_sessions(
+        &self,
+        now: i64,
+        created_before: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= $1 OR created_at < $2")
+            .bind(now)
+            .bind(created_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn create_batch_job(
+        &self,
+        id: &str,
+        user_id: &str,
+        total: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO batch_jobs (id, user_id, total, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(total)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_batch_progress(
+        &self,
+        id: &str,
+        completed: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE batch_jobs SET completed = $1, updated_at = $2 WHERE id = $3 AND NOT finished",
+        )
+        .bind(completed)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_batch_job(
+        &self,
+        id: &str,
+        results: Option<&str>,
+        error: Option<&str>,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE batch_jobs
+            SET finished = TRUE, completed = total, results = $1, error = $2, updated_at = $3
+            WHERE id = $4
+            "#,
+        )
+        .bind(results)
+        .bind(error)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_batch_job(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<BatchJobRow>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, user_id, total, completed, finished, results, error, updated_at
+            FROM batch_jobs WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn count_running_batch_jobs(
+        &self,
+        user_id: &str,
+        updated_after: i64,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM batch_jobs
+            WHERE user_id = $1 AND NOT finished AND updated_at > $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(updated_after)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete_old_batch_jobs(&self, updated_before: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM batch_jobs WHERE updated_at < $1")
+            .bind(updated_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn catalogue_overlay(&self) -> Result<CatalogueOverlay, sqlx::Error> {
+        Ok(CatalogueOverlay {
+            brands: sqlx::query_as("SELECT * FROM catalogue_brands")
+                .fetch_all(&self.pool)
+                .await?,
+            paints: sqlx::query_as(
+                r#"
+                SELECT brand_id, base_name, added, name AS _id, spectral_curve, d65_10deg_hex,
+                       opacity, pigments, lightfastness, series, drying_speed, role, retired_at
+                FROM catalogue_paints
+                ORDER BY brand_id, base_name
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?,
+        })
+    }
+
+    async fn save_catalogue_brand(
+        &self,
+        brand: &BrandRow,
+        entry: &NewAuditEntry,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO catalogue_brands
+                (id, display_name, manufacturer, medium, measurement_source, data_version)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET
+                display_name = EXCLUDED.display_name,
+                manufacturer = EXCLUDED.manufacturer,
+                medium = EXCLUDED.medium,
+                measurement_source = EXCLUDED.measurement_source,
+                data_version = EXCLUDED.data_version
+            "#,
+        )
+        .bind(&brand.id)
+        .bind(&brand.display_name)
+        .bind(&brand.manufacturer)
+        .bind(&brand.medium)
+        .bind(&brand.measurement_source)
+        .bind(&brand.data_version)
+        .execute(&mut *tx)
+        .await?;
+        insert_audit_entry(&mut tx, entry).await?;
+        tx.commit().await
+    }
+
+    async fn save_catalogue_paints(
+        &self,
+        paints: &[BrandPaintRow],
+        entries: &[NewAuditEntry],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for paint in paints {
+            sqlx::query("DELETE FROM catalogue_paints WHERE brand_id = $1 AND base_name = $2")
+                .bind(&paint.brand_id)
+                .bind(&paint.base_name)
+                .execute(&mut *tx)
+                .await?;
+            let color = &paint.color;
+            sqlx::query(
+                r#"
+                INSERT INTO catalogue_paints
+                    (brand_id, base_name, added, name, spectral_curve, d65_10deg_hex, opacity,
+                     pigments, lightfastness, series, drying_speed, role, retired_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#,
+            )
+            .bind(&paint.brand_id)
+            .bind(&paint.base_name)
+            .bind(paint.added)
+            .bind(&color._id)
+            .bind(&color.spectral_curve)
+            .bind(&color.d65_10deg_hex)
+            .bind(&color.opacity)
+            .bind(&color.pigments)
+            .bind(&color.lightfastness)
+            .bind(color.series)
+            .bind(&color.drying_speed)
+            .bind(&color.role)
+            .bind(&paint.retired_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for entry in entries {
+            insert_audit_entry(&mut tx, entry).await?;
+        }
+        tx.commit().await
+    }
+
+    async fn list_audit_log(&self, limit: i64) -> Result<Vec<AuditLogRow>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM audit_log ORDER BY id DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn catalogue_revision(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM audit_log")
+            .fetch_one(&self.pool)
+            .await
+    }
+}
+
+async fn insert_audit_entry(
+    conn: &mut PgConnection,
+    entry: &NewAuditEntry,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (user_id, user_email, action, brand_id, paint_name, details, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, datetime_now())
+        "#,
+    )
+    .bind(&entry.user_id)
+    .bind(&entry.user_email)
+    .bind(&entry.action)
+    .bind(&entry.brand_id)
+    .bind(&entry.paint_name)
+    .bind(&entry.details)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
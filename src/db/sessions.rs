@@ -0,0 +1,145 @@
+//! Sign-in sessions kept in the user database, so they survive restarts and
+//! are shared by every server using that database
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::{self, ExpiredDeletion, SessionStore};
+
+use super::UserStore;
+
+/// How long sessions last, from the environment
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    /// Longest a session lasts after sign-in, however active (SESSION_LIFETIME_HOURS)
+    pub lifetime: Duration,
+    /// Inactivity after which a session ends (SESSION_IDLE_TIMEOUT_HOURS)
+    pub idle_timeout: Duration,
+    /// Name of the session cookie (SESSION_COOKIE_NAME)
+    pub cookie_name: String,
+}
+
+impl SessionConfig {
+    pub fn from_env() -> Self {
+        let hours = |var: &str, default: u32| {
+            let hours = match std::env::var(var) {
+                Ok(value) => match value.trim().parse::<u32>() {
+                    Ok(hours) if hours > 0 => hours,
+                    _ => panic!("{} must be a whole number of hours above 0, not {:?}", var, value),
+                },
+                Err(_) => default,
+            };
+            Duration::hours(hours.into())
+        };
+        SessionConfig {
+            lifetime: hours("SESSION_LIFETIME_HOURS", 24 * 30),
+            idle_timeout: hours("SESSION_IDLE_TIMEOUT_HOURS", 24 * 7),
+            cookie_name: std::env::var("SESSION_COOKIE_NAME")
+                .unwrap_or_else(|_| "aop_session".into()),
+        }
+    }
+}
+
+/// A [`SessionStore`] over the user database. Each session's expiry moves
+/// with activity, but no session outlives `lifetime` from when it was created.
+#[derive(Clone)]
+pub struct UserSessionStore {
+    users: Arc<dyn UserStore>,
+    lifetime: Duration,
+}
+
+impl UserSessionStore {
+    pub fn new(users: Arc<dyn UserStore>, lifetime: Duration) -> Self {
+        UserSessionStore { users, lifetime }
+    }
+
+    fn row(record: &Record) -> session_store::Result<(String, String, i64, i64)> {
+        let data = serde_json::to_string(&record.data)
+            .map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Ok((record.id.to_string(), data, now, record.expiry_date.unix_timestamp()))
+    }
+}
+
+impl std::fmt::Debug for UserSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserSessionStore")
+            .field("lifetime", &self.lifetime)
+            .finish_non_exhaustive()
+    }
+}
+
+fn backend_error(e: sqlx::Error) -> session_store::Error {
+    session_store::Error::Backend(e.to_string())
+}
+
+#[async_trait]
+impl SessionStore for UserSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        loop {
+            let (id, data, now, expires_at) = Self::row(record)?;
+            if self
+                .users
+                .create_session(&id, &data, now, expires_at)
+                .await
+                .map_err(backend_error)?
+            {
+                return Ok(());
+            }
+            // Ids are random, so a collision is vanishingly rare; pick another
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let (id, data, now, expires_at) = Self::row(record)?;
+        self.users
+            .save_session(&id, &data, now, expires_at)
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn load(&self, id: &Id) -> session_store::Result<Option<Record>> {
+        let now = OffsetDateTime::now_utc();
+        let Some((data, expires_at)) = self
+            .users
+            .load_session(
+                &id.to_string(),
+                now.unix_timestamp(),
+                (now - self.lifetime).unix_timestamp(),
+            )
+            .await
+            .map_err(backend_error)?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(Record {
+            id: *id,
+            data: serde_json::from_str(&data)
+                .map_err(|e| session_store::Error::Decode(e.to_string()))?,
+            expiry_date: OffsetDateTime::from_unix_timestamp(expires_at)
+                .map_err(|e| session_store::Error::Decode(e.to_string()))?,
+        }))
+    }
+
+    async fn delete(&self, id: &Id) -> session_store::Result<()> {
+        self.users
+            .delete_session(&id.to_string())
+            .await
+            .map_err(backend_error)
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for UserSessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        self.users
+            .delete_expired_sessions(now.unix_timestamp(), (now - self.lifetime).unix_timestamp())
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! Stored encoding of spectral reflectance curves
+//!
+//! A curve blob is the magic bytes `SPCV`, a format version byte and, for
+//! version 1, a JSON document holding the wavelength grid, the values and
+//! measurement metadata. Older databases stored bare bincode `Vec<f64>`
+//! blobs on the 400–700 nm grid; `from_legacy` reads those for migration.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::SpectralGrid;
+
+const MAGIC: &[u8; 4] = b"SPCV";
+const VERSION: u8 = 1;
+
+/// How a curve was obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurveOrigin {
+    /// Converted from the unversioned format, so nothing more is known
+    #[default]
+    Unknown,
+    /// Measured with a spectrophotometer
+    Measured,
+    /// Reconstructed from a colour with LHTSS
+    Reconstructed,
+}
+
+/// Where a curve came from and how it was measured, where known
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CurveMetadata {
+    pub origin: CurveOrigin,
+    /// Spectrophotometer make and model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument: Option<String>,
+    /// Measurement geometry, e.g. "45/0" or "d/8 SCI"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A reflectance curve with the grid it is sampled on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectralCurve {
+    pub grid: SpectralGrid,
+    /// Reflectance in 0..=1, one value per grid wavelength
+    pub values: Vec<f64>,
+    #[serde(default)]
+    pub metadata: CurveMetadata,
+}
+
+/// Why a stored curve could not be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum CurveDecodeError {
+    Empty,
+    /// A bare bincode blob that has not been migrated yet
+    Unversioned,
+    UnsupportedVersion(u8),
+    Malformed(String),
+    /// The grid's wavelengths do not increase (a zero step)
+    NonIncreasingGrid,
+    /// The values do not match the grid they claim to be on
+    WrongLength { expected: usize, found: usize },
+    OutOfRange,
+}
+
+impl fmt::Display for CurveDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurveDecodeError::Empty => write!(f, "no spectral data"),
+            CurveDecodeError::Unversioned => {
+                write!(f, "spectral data is in the old unversioned format; run the migrations")
+            }
+            CurveDecodeError::UnsupportedVersion(v) => {
+                write!(f, "spectral data format version {} is not supported", v)
+            }
+            CurveDecodeError::Malformed(e) => write!(f, "spectral data is malformed: {}", e),
+            CurveDecodeError::NonIncreasingGrid => {
+                write!(f, "spectral data wavelengths do not increase")
+            }
+            CurveDecodeError::WrongLength { expected, found } => write!(
+                f,
+                "spectral data has {} values but its grid has {}",
+                found, expected
+            ),
+            CurveDecodeError::OutOfRange => {
+                write!(f, "spectral data has values outside 0..1")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CurveDecodeError {}
+
+impl SpectralCurve {
+    pub fn new(grid: SpectralGrid, values: Vec<f64>, metadata: CurveMetadata) -> Self {
+        SpectralCurve { grid, values, metadata }
+    }
+
+    /// Encode for storage in the current format version
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(serde_json::to_vec(self).expect("spectral curves always serialize"));
+        bytes
+    }
+
+    /// Decode a stored curve, checking the values fit its grid
+    pub fn decode(bytes: &[u8]) -> Result<Self, CurveDecodeError> {
+        if bytes.is_empty() {
+            return Err(CurveDecodeError::Empty);
+        }
+        let Some(rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+            return Err(CurveDecodeError::Unversioned);
+        };
+        let curve: SpectralCurve = match rest.split_first() {
+            Some((&VERSION, body)) => {
+                serde_json::from_slice(body).map_err(|e| CurveDecodeError::Malformed(e.to_string()))?
+            }
+            Some((&version, _)) => return Err(CurveDecodeError::UnsupportedVersion(version)),
+            None => return Err(CurveDecodeError::Malformed("missing version".to_string())),
+        };
+        curve.validate()?;
+        Ok(curve)
+    }
+
+    /// Read a bare bincode `Vec<f64>` on the 400–700 nm paint data grid, as
+    /// stored before curves were versioned
+    pub fn from_legacy(bytes: &[u8]) -> Result<Self, CurveDecodeError> {
+        let values: Vec<f64> =
+            bincode::deserialize(bytes).map_err(|e| CurveDecodeError::Malformed(e.to_string()))?;
+        let curve = SpectralCurve::new(SpectralGrid::PAINT_DATA, values, CurveMetadata::default());
+        curve.validate()?;
+        Ok(curve)
+    }
+
+    /// Whether a blob is already in a versioned format
+    pub fn is_versioned(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    fn validate(&self) -> Result<(), CurveDecodeError> {
+        if self.grid.step == 0 {
+            return Err(CurveDecodeError::NonIncreasingGrid);
+        }
+        if self.values.len() != self.grid.count || self.values.is_empty() {
+            return Err(CurveDecodeError::WrongLength {
+                expected: self.grid.count,
+                found: self.values.len(),
+            });
+        }
+        if self.values.iter().any(|v| !v.is_finite() || *v < 0.0 || *v > 1.0) {
+            return Err(CurveDecodeError::OutOfRange);
+        }
+        Ok(())
+    }
+
+    /// The values resampled onto another grid
+    pub fn resampled(&self, grid: SpectralGrid) -> Vec<f64> {
+        grid.resample(&self.grid, &self.values)
+            .expect("validated curves match their grid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(count: usize) -> Vec<f64> {
+        (0..count).map(|i| 0.05 + 0.03 * i as f64).collect()
+    }
+
+    fn curve(grid: SpectralGrid, values: Vec<f64>) -> SpectralCurve {
+        SpectralCurve::new(grid, values, CurveMetadata::default())
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let curve = SpectralCurve::new(
+            SpectralGrid::new(380, 5, 81),
+            (0..81).map(|i| i as f64 / 80.0).collect(),
+            CurveMetadata {
+                origin: CurveOrigin::Measured,
+                instrument: Some("X-Rite i1Pro 3".to_string()),
+                geometry: Some("45/0".to_string()),
+                ..Default::default()
+            },
+        );
+        let bytes = curve.encode();
+        assert!(bytes.starts_with(b"SPCV\x01"));
+        assert!(SpectralCurve::is_versioned(&bytes));
+        assert_eq!(SpectralCurve::decode(&bytes), Ok(curve));
+    }
+
+    #[test]
+    fn legacy_blobs() {
+        let bytes = bincode::serialize(&ramp(31)).unwrap();
+        assert!(!SpectralCurve::is_versioned(&bytes));
+        assert_eq!(SpectralCurve::decode(&bytes), Err(CurveDecodeError::Unversioned));
+
+        let legacy = SpectralCurve::from_legacy(&bytes).unwrap();
+        assert_eq!(legacy, curve(SpectralGrid::PAINT_DATA, ramp(31)));
+
+        // Legacy blobs are always on the paint data grid
+        let short = bincode::serialize(&ramp(30)).unwrap();
+        assert_eq!(
+            SpectralCurve::from_legacy(&short),
+            Err(CurveDecodeError::WrongLength { expected: 31, found: 30 })
+        );
+    }
+
+    #[test]
+    fn invalid_curves_are_rejected() {
+        let mut bytes = curve(SpectralGrid::PAINT_DATA, ramp(31)).encode();
+        bytes[4] = 2;
+        assert_eq!(SpectralCurve::decode(&bytes), Err(CurveDecodeError::UnsupportedVersion(2)));
+        assert_eq!(SpectralCurve::decode(b""), Err(CurveDecodeError::Empty));
+
+        let wrong_length = curve(SpectralGrid::PAINT_DATA, ramp(16));
+        let expected = CurveDecodeError::WrongLength { expected: 31, found: 16 };
+        assert_eq!(wrong_length.validate(), Err(expected.clone()));
+        assert_eq!(SpectralCurve::decode(&wrong_length.encode()), Err(expected));
+
+        let zero_step = curve(SpectralGrid::new(400, 0, 31), ramp(31));
+        assert_eq!(zero_step.validate(), Err(CurveDecodeError::NonIncreasingGrid));
+
+        let mut values = ramp(31);
+        values[3] = 1.2;
+        let out_of_range = curve(SpectralGrid::PAINT_DATA, values);
+        assert_eq!(out_of_range.validate(), Err(CurveDecodeError::OutOfRange));
+    }
+
+    #[test]
+    fn resample_onto_another_grid() {
+        let coarse =
+            curve(SpectralGrid::PAINT_DATA, ramp(31)).resampled(SpectralGrid::new(400, 20, 16));
+        assert_eq!(coarse.len(), 16);
+        for (i, value) in coarse.iter().enumerate() {
+            assert!((value - ramp(31)[2 * i]).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,935 @@
+//! User data in SQLite
+
+use async_trait::async_trait;
+
+use super::store::{renamed_palette_paint, UserStore};
+use super::{
+    AuditLogRow, BatchJobRow, BrandPaintRow, BrandRow, CatalogueOverlay, Db, MixHistoryRow,
+    NewAuditEntry, PaintColor, PaletteRow, ProjectListRow, ProjectRow, ProjectTargetRow, User,
+    UserPaintRow, UserSettings,
+};
+
+/// User data in a SQLite database, which has the reference database attached
+/// so migrations can copy accounts out of an old combined database
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: Db,
+}
+
+impl SqliteStore {
+    pub fn new(pool: Db) -> Self {
+        SqliteStore { pool }
+    }
+
+    pub fn pool(&self) -> &Db {
+        &self.pool
+    }
+
+    async fn touch_project(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE projects SET updated_at = datetime('now') WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for SqliteStore {
+    async fn get_user_by_email(&self, email: &str) -> Option<User> {
+        sqlx::query_as("SELECT * FROM users WHERE email = ?")
+            .bind(email.to_lowercase())
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn get_user_by_id(&self, id: &str) -> Option<User> {
+        sqlx::query_as("SELECT * FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_user(
+        &self,
+        id: &str,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash, created_at) VALUES (?, ?, ?, datetime('now'))",
+        )
+        .bind(id)
+        .bind(email.to_lowercase())
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn verify_user_email(&self, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET email_verified = 1 WHERE id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: &str, hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(hash)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_user_admin(&self, email: &str, is_admin: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET is_admin = ? WHERE email = ?")
+            .bind(is_admin)
+            .bind(email.to_lowercase())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_failed_attempts(
+        &self,
+        user_id: &str,
+        count: i32,
+        locked_until: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET failed_attempts = ?, locked_until = ? WHERE id = ?")
+            .bind(count)
+            .bind(locked_until)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        hash: &str,
+        expires_at: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO tokens (id, user_id, kind, hash, expires_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(id)
+            .bind(user_id)
+            .bind(kind)
+            .bind(hash)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_token(&self, hash: &str, kind: &str) -> Option<(String, String, String)> {
+        sqlx::query_as("SELECT id, user_id, expires_at FROM tokens WHERE hash = ? AND kind = ?")
+            .bind(hash)
+            .bind(kind)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn delete_token(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM tokens WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user_settings(&self, user_id: &str) -> Option<UserSettings> {
+        sqlx::query_as("SELECT * FROM user_settings WHERE _id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn set_active_palette(
+        &self,
+        user_id: &str,
+        email: &str,
+        palette_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_settings (_id, email, active_palette_id)
+            VALUES (?, ?, ?)
+            ON CONFLICT(_id) DO UPDATE SET
+                email = excluded.email,
+                active_palette_id = excluded.active_palette_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(palette_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_palettes(&self, user_id: &str) -> Vec<PaletteRow> {
+        sqlx::query_as("SELECT * FROM palettes WHERE user_id = ? ORDER BY created_at, name")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_palette(&self, user_id: &str, id: &str) -> Option<PaletteRow> {
+        sqlx::query_as("SELECT * FROM palettes WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO palettes (id, user_id, name, mix_choice, paints, white, black, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .bind(mix_choice)
+        .bind(paints)
+        .bind(white)
+        .bind(black)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE palettes SET name = ?, mix_choice = ?, paints = ?, white = ?, black = ?,
+                                updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(name)
+        .bind(mix_choice)
+        .bind(paints)
+        .bind(white)
+        .bind(black)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_palette(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM palettes WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn rename_palette_paint(&self, brand: &str, name: &str, new_name: &str) -> Result<(), sqlx::Error> {
+        let palettes: Vec<PaletteRow> = sqlx::query_as("SELECT * FROM palettes")
+            .fetch_all(&self.pool)
+            .await?;
+        for palette in palettes {
+            let Some((paints, white, black)) = renamed_palette_paint(&palette, brand, name, new_name) else {
+                continue;
+            };
+            sqlx::query("UPDATE palettes SET paints = ?, white = ?, black = ? WHERE id = ?")
+                .bind(paints)
+                .bind(white)
+                .bind(black)
+                .bind(&palette.id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_mix_history(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        target_hex: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        filters: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO mix_history (id, user_id, kind, target_hex, palette_id, palette_name,
+                                     mix_choice, filters, results, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(kind)
+        .bind(target_hex)
+        .bind(palette_id)
+        .bind(palette_name)
+        .bind(mix_choice)
+        .bind(filters)
+        .bind(results)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_mix_history(&self, user_id: &str, starred_only: bool) -> Vec<MixHistoryRow> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM mix_history
+            WHERE user_id = ? AND (starred = 1 OR ? = 0)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(starred_only)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn get_mix_history(&self, user_id: &str, id: &str) -> Option<MixHistoryRow> {
+        sqlx::query_as("SELECT * FROM mix_history WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn update_mix_history_recipe(
+        &self,
+        id: &str,
+        user_id: &str,
+        starred: bool,
+        name: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE mix_history SET starred = ?, name = ?, updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(starred)
+        .bind(name)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_mix_history_results(
+        &self,
+        id: &str,
+        user_id: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE mix_history SET palette_id = ?, palette_name = ?, results = ?,
+                                   updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(palette_id)
+        .bind(palette_name)
+        .bind(results)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_mix_history(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM mix_history WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_projects(&self, user_id: &str) -> Vec<ProjectListRow> {
+        sqlx::query_as(
+            r#"
+            SELECT p.id, p.name, p.updated_at,
+                   (SELECT COUNT(*) FROM project_targets t WHERE t.project_id = p.id) AS target_count
+            FROM projects p
+            WHERE p.user_id = ?
+            ORDER BY p.updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn get_project(&self, user_id: &str, id: &str) -> Option<ProjectRow> {
+        sqlx::query_as("SELECT * FROM projects WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn create_project(&self, id: &str, user_id: &str, name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO projects (id, user_id, name, created_at, updated_at)
+            VALUES (?, ?, ?, datetime('now'), datetime('now'))
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_project(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        notes: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE projects SET name = ?, notes = ?, updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(name)
+        .bind(notes)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_project_image(
+        &self,
+        id: &str,
+        user_id: &str,
+        reference_image: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE projects SET reference_image = ?, updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(reference_image)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_project(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM projects WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_project_targets(&self, project_id: &str) -> Vec<ProjectTargetRow> {
+        sqlx::query_as("SELECT * FROM project_targets WHERE project_id = ? ORDER BY created_at, rowid")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_project_target(&self, user_id: &str, id: &str) -> Option<ProjectTargetRow> {
+        sqlx::query_as(
+            r#"
+            SELECT t.* FROM project_targets t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.id = ? AND p.user_id = ?
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()
+        .flatten()
+    }
+
+    async fn insert_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        target_hex: &str,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        results: &str,
+        chosen: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO project_targets (id, project_id, label, target_hex, palette_name,
+                                         mix_choice, results, chosen, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(id)
+        .bind(project_id)
+        .bind(label)
+        .bind(target_hex)
+        .bind(palette_name)
+        .bind(mix_choice)
+        .bind(results)
+        .bind(chosen)
+        .execute(&self.pool)
+        .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn update_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        chosen: Option<i64>,
+        notes: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE project_targets SET label = ?, chosen = ?, notes = ? WHERE id = ?")
+            .bind(label)
+            .bind(chosen)
+            .bind(notes)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn delete_project_target(&self, id: &str, project_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM project_targets WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.touch_project(project_id).await
+    }
+
+    async fn list_user_paints(&self, user_id: &str) -> Vec<UserPaintRow> {
+        sqlx::query_as("SELECT * FROM user_paints WHERE user_id = ? ORDER BY name COLLATE NOCASE")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn get_user_paint_colors(&self, user_id: &str) -> Vec<PaintColor> {
+        sqlx::query_as(
+            r#"
+            SELECT name AS _id, spectral_curve, hex AS d65_10deg_hex,
+                   opacity, pigments, lightfastness, series, drying_speed, role
+            FROM user_paints
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn insert_user_paint(&self, paint: &UserPaintRow) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_paints (id, user_id, name, spectral_curve, hex, source, opacity,
+                                     pigments, lightfastness, series, drying_speed, role, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(&paint.id)
+        .bind(&paint.user_id)
+        .bind(&paint.name)
+        .bind(&paint.spectral_curve)
+        .bind(&paint.hex)
+        .bind(&paint.source)
+        .bind(&paint.opacity)
+        .bind(&paint.pigments)
+        .bind(&paint.lightfastness)
+        .bind(paint.series)
+        .bind(&paint.drying_speed)
+        .bind(&paint.role)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_user_paint(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM user_paints WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn create_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO sessions (id, data, created_at, expires_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(id)
+        .bind(data)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn save_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO sessions (id, data, created_at, expires_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET data = excluded.data, expires_at = excluded.expires_at",
+        )
+        .bind(id)
+        .bind(data)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_session(
+        &self,
+        id: &str,
+        now: i64,
+        created_after: i64,
+    ) -> Result<Option<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT data, expires_at FROM sessions WHERE id = ? AND expires_at > ? AND created_at > ?",
+        )
+        .bind(id)
+        .bind(now)
+        .bind(created_after)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM sessions WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_sessions(
+        &self,
+        now: i64,
+        created_before: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= ? OR created_at < ?")
+            .bind(now)
+            .bind(created_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn create_batch_job(
+        &self,
+        id: &str,
+        user_id: &str,
+        total: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO batch_jobs (id, user_id, total, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(total)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_batch_progress(
+        &self,
+        id: &str,
+        completed: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE batch_jobs SET completed = ?, updated_at = ? WHERE id = ? AND NOT finished",
+        )
+        .bind(completed)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_batch_job(
+        &self,
+        id: &str,
+        results: Option<&str>,
+        error: Option<&str>,
+        now: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE batch_jobs
+            SET finished = 1, completed = total, results = ?, error = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(results)
+        .bind(error)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_batch_job(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<BatchJobRow>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, user_id, total, completed, finished, results, error, updated_at
+            FROM batch_jobs WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn count_running_batch_jobs(
+        &self,
+        user_id: &str,
+        updated_after: i64,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM batch_jobs
+            WHERE user_id = ? AND NOT finished AND updated_at > ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(updated_after)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete_old_batch_jobs(&self, updated_before: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM batch_jobs WHERE updated_at < ?")
+            .bind(updated_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn catalogue_overlay(&self) -> Result<CatalogueOverlay, sqlx::Error> {
+        Ok(CatalogueOverlay {
+            brands: sqlx::query_as("SELECT * FROM catalogue_brands")
+                .fetch_all(&self.pool)
+                .await?,
+            paints: sqlx::query_as(
+                r#"
+                SELECT brand_id, base_name, added, name AS _id, spectral_curve, d65_10deg_hex,
+                       opacity, pigments, lightfastness, series, drying_speed, role, retired_at
+                FROM catalogue_paints
+                ORDER BY brand_id, base_name
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?,
+        })
+    }
+
+    async fn save_catalogue_brand(
+        &self,
+        brand: &BrandRow,
+        entry: &NewAuditEntry,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO catalogue_brands
+                (id, display_name, manufacturer, medium, measurement_source, data_version)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&brand.id)
+        .bind(&brand.display_name)
+        .bind(&brand.manufacturer)
+        .bind(&brand.medium)
+        .bind(&brand.measurement_source)
+        .bind(&brand.data_version)
+        .execute(&mut *tx)
+        .await?;
+        insert_audit_entry(&mut tx, entry).await?;
+        tx.commit().await
+    }
+
+    async fn save_catalogue_paints(
+        &self,
+        paints: &[BrandPaintRow],
+        entries: &[NewAuditEntry],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for paint in paints {
+            sqlx::query("DELETE FROM catalogue_paints WHERE brand_id = ? AND base_name = ?")
+                .bind(&paint.brand_id)
+                .bind(&paint.base_name)
+                .execute(&mut *tx)
+                .await?;
+            let color = &paint.color;
+            sqlx::query(
+                r#"
+                INSERT INTO catalogue_paints
+                    (brand_id, base_name, added, name, spectral_curve, d65_10deg_hex, opacity,
+                     pigments, lightfastness, series, drying_speed, role, retired_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&paint.brand_id)
+            .bind(&paint.base_name)
+            .bind(paint.added)
+            .bind(&color._id)
+            .bind(&color.spectral_curve)
+            .bind(&color.d65_10deg_hex)
+            .bind(&color.opacity)
+            .bind(&color.pigments)
+            .bind(&color.lightfastness)
+            .bind(color.series)
+            .bind(&color.drying_speed)
+            .bind(&color.role)
+            .bind(&paint.retired_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for entry in entries {
+            insert_audit_entry(&mut tx, entry).await?;
+        }
+        tx.commit().await
+    }
+
+    async fn list_audit_log(&self, limit: i64) -> Result<Vec<AuditLogRow>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM audit_log ORDER BY id DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn catalogue_revision(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM audit_log")
+            .fetch_one(&self.pool)
+            .await
+    }
+}
+
+async fn insert_audit_entry(
+    conn: &mut sqlx::SqliteConnection,
+    entry: &NewAuditEntry,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (user_id, user_email, action, brand_id, paint_name, details, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+        "#,
+    )
+    .bind(&entry.user_id)
+    .bind(&entry.user_email)
+    .bind(&entry.action)
+    .bind(&entry.brand_id)
+    .bind(&entry.paint_name)
+    .bind(&entry.details)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! Storage of user data: accounts, settings, palettes, history, projects,
+//! users' own paints and admins' catalogue edits. SQLite and Postgres each
+//! implement [`UserStore`]; the paint reference data stays in SQLite either way.
+
+use async_trait::async_trait;
+
+use super::{
+    AuditLogRow, BatchJobRow, BrandPaintRow, BrandRow, CatalogueOverlay, MixHistoryRow,
+    NewAuditEntry, PaintColor, PaletteRow, ProjectListRow, ProjectRow, ProjectTargetRow, User,
+    UserPaintRow, UserSettings,
+};
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    // Users
+    async fn get_user_by_email(&self, email: &str) -> Option<User>;
+    async fn get_user_by_id(&self, id: &str) -> Option<User>;
+    async fn create_user(&self, id: &str, email: &str, password_hash: &str) -> Result<(), sqlx::Error>;
+    async fn verify_user_email(&self, user_id: &str) -> Result<(), sqlx::Error>;
+    async fn update_password(&self, user_id: &str, hash: &str) -> Result<(), sqlx::Error>;
+    /// Grant or revoke admin rights; returns whether a user with that email exists
+    async fn set_user_admin(&self, email: &str, is_admin: bool) -> Result<bool, sqlx::Error>;
+    async fn update_failed_attempts(
+        &self,
+        user_id: &str,
+        count: i32,
+        locked_until: Option<&str>,
+    ) -> Result<(), sqlx::Error>;
+
+    // Tokens
+    async fn create_token(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        hash: &str,
+        expires_at: &str,
+    ) -> Result<(), sqlx::Error>;
+    /// `(id, user_id, expires_at)` of the token with that hash
+    async fn get_token(&self, hash: &str, kind: &str) -> Option<(String, String, String)>;
+    async fn delete_token(&self, id: &str) -> Result<(), sqlx::Error>;
+
+    // User settings
+    async fn get_user_settings(&self, user_id: &str) -> Option<UserSettings>;
+    async fn set_active_palette(&self, user_id: &str, email: &str, palette_id: &str) -> Result<(), sqlx::Error>;
+
+    // Palettes
+    async fn list_palettes(&self, user_id: &str) -> Vec<PaletteRow>;
+    async fn get_palette(&self, user_id: &str, id: &str) -> Option<PaletteRow>;
+
+    /// The palette marked active in the user's settings, falling back to their first palette
+    async fn get_active_palette(&self, user_id: &str) -> Option<PaletteRow> {
+        if let Some(active_id) = self
+            .get_user_settings(user_id)
+            .await
+            .and_then(|s| s.active_palette_id)
+        {
+            if let Some(palette) = self.get_palette(user_id, &active_id).await {
+                return Some(palette);
+            }
+        }
+        self.list_palettes(user_id).await.into_iter().next()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<(), sqlx::Error>;
+    #[allow(clippy::too_many_arguments)]
+    async fn update_palette(
+        &self,
+        id: &str,
+        user_id: &str,
+        name: &str,
+        mix_choice: &str,
+        paints: &str,
+        white: Option<&str>,
+        black: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn delete_palette(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error>;
+    /// Follow a catalogue paint rename in every palette that selects it or
+    /// mixes with it as white or black
+    async fn rename_palette_paint(&self, brand: &str, name: &str, new_name: &str) -> Result<(), sqlx::Error>;
+
+    // Mix history
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_mix_history(
+        &self,
+        id: &str,
+        user_id: &str,
+        kind: &str,
+        target_hex: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        filters: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn list_mix_history(&self, user_id: &str, starred_only: bool) -> Vec<MixHistoryRow>;
+    async fn get_mix_history(&self, user_id: &str, id: &str) -> Option<MixHistoryRow>;
+    async fn update_mix_history_recipe(
+        &self,
+        id: &str,
+        user_id: &str,
+        starred: bool,
+        name: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_mix_history_results(
+        &self,
+        id: &str,
+        user_id: &str,
+        palette_id: Option<&str>,
+        palette_name: Option<&str>,
+        results: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn delete_mix_history(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error>;
+
+    // Projects
+    async fn list_projects(&self, user_id: &str) -> Vec<ProjectListRow>;
+    async fn get_project(&self, user_id: &str, id: &str) -> Option<ProjectRow>;
+    async fn create_project(&self, id: &str, user_id: &str, name: &str) -> Result<(), sqlx::Error>;
+    async fn update_project(&self, id: &str, user_id: &str, name: &str, notes: &str) -> Result<bool, sqlx::Error>;
+    async fn set_project_image(
+        &self,
+        id: &str,
+        user_id: &str,
+        reference_image: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn delete_project(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error>;
+    /// A project's targets in the order they were added
+    async fn list_project_targets(&self, project_id: &str) -> Vec<ProjectTargetRow>;
+    /// Look up a target, provided its project belongs to the user
+    async fn get_project_target(&self, user_id: &str, id: &str) -> Option<ProjectTargetRow>;
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        target_hex: &str,
+        palette_name: Option<&str>,
+        mix_choice: Option<&str>,
+        results: &str,
+        chosen: Option<i64>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_project_target(
+        &self,
+        id: &str,
+        project_id: &str,
+        label: &str,
+        chosen: Option<i64>,
+        notes: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn delete_project_target(&self, id: &str, project_id: &str) -> Result<(), sqlx::Error>;
+
+    // User paints
+    async fn list_user_paints(&self, user_id: &str) -> Vec<UserPaintRow>;
+    /// A user's own paints in the same shape as a brand's paints, keyed by name
+    async fn get_user_paint_colors(&self, user_id: &str) -> Vec<PaintColor>;
+    async fn insert_user_paint(&self, paint: &UserPaintRow) -> Result<(), sqlx::Error>;
+    async fn delete_user_paint(&self, id: &str, user_id: &str) -> Result<bool, sqlx::Error>;
+
+    // Sessions, with times in Unix seconds
+    /// Store a new session; returns false if one with that id already exists
+    async fn create_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<bool, sqlx::Error>;
+    async fn save_session(
+        &self,
+        id: &str,
+        data: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> Result<(), sqlx::Error>;
+    /// `(data, expires_at)` of a session that has not expired by `now` and
+    /// was created after `created_after`
+    async fn load_session(
+        &self,
+        id: &str,
+        now: i64,
+        created_after: i64,
+    ) -> Result<Option<(String, i64)>, sqlx::Error>;
+    async fn delete_session(&self, id: &str) -> Result<(), sqlx::Error>;
+    /// Remove sessions that have expired by `now` or were created before
+    /// `created_before`, returning how many were removed
+    async fn delete_expired_sessions(
+        &self,
+        now: i64,
+        created_before: i64,
+    ) -> Result<u64, sqlx::Error>;
+
+    // Batch jobs, with times in Unix seconds
+    async fn create_batch_job(
+        &self,
+        id: &str,
+        user_id: &str,
+        total: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_batch_progress(
+        &self,
+        id: &str,
+        completed: i64,
+        now: i64,
+    ) -> Result<(), sqlx::Error>;
+    async fn finish_batch_job(
+        &self,
+        id: &str,
+        results: Option<&str>,
+        error: Option<&str>,
+        now: i64,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_batch_job(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<BatchJobRow>, sqlx::Error>;
+    /// Unfinished jobs of the user updated after `updated_after`
+    async fn count_running_batch_jobs(
+        &self,
+        user_id: &str,
+        updated_after: i64,
+    ) -> Result<i64, sqlx::Error>;
+    /// Remove jobs last updated before `updated_before`
+    async fn delete_old_batch_jobs(&self, updated_before: i64) -> Result<u64, sqlx::Error>;
+
+    // Catalogue edits
+    async fn catalogue_overlay(&self) -> Result<CatalogueOverlay, sqlx::Error>;
+    /// Add or replace an edited brand, logging the change in the same transaction
+    async fn save_catalogue_brand(
+        &self,
+        brand: &BrandRow,
+        entry: &NewAuditEntry,
+    ) -> Result<(), sqlx::Error>;
+    /// Add or replace edited paints, keyed by brand and `base_name`, logging
+    /// the changes in the same transaction
+    async fn save_catalogue_paints(
+        &self,
+        paints: &[BrandPaintRow],
+        entries: &[NewAuditEntry],
+    ) -> Result<(), sqlx::Error>;
+    /// Most recent audit log entries first
+    async fn list_audit_log(&self, limit: i64) -> Result<Vec<AuditLogRow>, sqlx::Error>;
+    /// Changes with every catalogue edit, as each one is logged: the id of
+    /// the latest audit log entry, or 0 before the first
+    async fn catalogue_revision(&self) -> Result<i64, sqlx::Error>;
+}
+
+/// A palette's `(paints, white, black)` after renaming a brand's paint, or
+/// `None` when the palette does not use it
+pub(crate) fn renamed_palette_paint(
+    palette: &PaletteRow,
+    brand: &str,
+    name: &str,
+    new_name: &str,
+) -> Option<(String, Option<String>, Option<String>)> {
+    let renamed_choice = |stored: &Option<String>| -> Option<String> {
+        let mut choice: serde_json::Value = serde_json::from_str(stored.as_deref()?).ok()?;
+        if choice["brand"] != brand || choice["name"] != name {
+            return None;
+        }
+        choice["name"] = new_name.into();
+        Some(choice.to_string())
+    };
+
+    let mut by_brand = palette.selected_by_brand();
+    let mut changed = false;
+    for paint in by_brand.get_mut(brand).into_iter().flatten() {
+        if paint == name {
+            *paint = new_name.to_string();
+            changed = true;
+        }
+    }
+    let white = renamed_choice(&palette.white);
+    let black = renamed_choice(&palette.black);
+    if !changed && white.is_none() && black.is_none() {
+        return None;
+    }
+    Some((
+        serde_json::to_string(&by_brand).unwrap_or_else(|_| palette.paints.clone()),
+        white.or_else(|| palette.white.clone()),
+        black.or_else(|| palette.black.clone()),
+    ))
+}
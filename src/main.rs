@@ -6,21 +6,104 @@ async fn main() {
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
     use std::sync::Arc;
-    use tower_sessions::{MemoryStore, SessionManagerLayer};
+    use tower_sessions::session_store::ExpiredDeletion;
+    use tower_sessions::{Expiry, SessionManagerLayer};
 
     // Load env vars
     dotenvy::dotenv().ok();
 
-    // Initialize database // in oxyde.cloud for production use the oxyde.cloud.toml file
-    let db_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data.db".into());
-    let db = aop::db::create_pool(&db_url).await;
+    // User data lives in DATABASE_URL, on SQLite or Postgres; the paint
+    // reference data in REFERENCE_DATABASE_URL is always SQLite
+    let db_config = aop::db::DbConfig::from_env();
+    let users = aop::db::UserDatabase::connect(&db_config).await;
 
-    // Run migrations
-    aop::db::run_migrations(&db).await;
+    // `aop migrate [status | up | down [steps] [--reference]]` manages the schema and exits
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        let reference = aop::db::create_reference_pool(&db_config, true).await;
+        std::process::exit(migrate_command(&users, &reference, &args).await);
+    }
+
+    // `aop import-users <sqlite-url>` copies accounts from SQLite into Postgres
+    if std::env::args().nth(1).as_deref() == Some("import-users") {
+        std::process::exit(import_users_command(&users, std::env::args().nth(2)).await);
+    }
+
+    let db = aop::db::create_reference_pool(&db_config, false).await;
+
+    // `aop validate` prints the paint data integrity report and exits,
+    // failing if any paint is unusable
+    if std::env::args().nth(1).as_deref() == Some("validate") {
+        let report =
+            aop::services::paint_validation::validate_paint_data(&db, users.store().as_ref()).await;
+        print!("{}", report);
+        std::process::exit(if report.has_errors() { 1 } else { 0 });
+    }
+
+    // `aop make-admin <email>` lets an existing user edit the paint catalogue
+    if std::env::args().nth(1).as_deref() == Some("make-admin") {
+        let Some(email) = std::env::args().nth(2) else {
+            eprintln!("Usage: aop make-admin <email>");
+            std::process::exit(2);
+        };
+        match users.store().set_user_admin(&email, true).await {
+            Ok(true) => {
+                println!("{} is now an admin; they need to sign in again to see the admin page", email);
+                std::process::exit(0);
+            }
+            Ok(false) => eprintln!("No user with email {}", email),
+            Err(e) => eprintln!("Failed to update {}: {}", email, e),
+        }
+        std::process::exit(1);
+    }
+
+    // Deployments apply migrations with `aop migrate`; pending ones are only
+    // applied at startup outside production or with MIGRATE_ON_BOOT set
+    let pending = users
+        .pending_migrations()
+        .await
+        .unwrap_or_else(|e| panic!("Failed to read the user migration status: {}", e))
+        + aop::db::pending_migrations(&db, &aop::db::REFERENCE_DATABASE)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to read the reference migration status: {}", e))
+            .len();
+    if pending > 0 {
+        if std::env::var("PRODUCTION").is_err() || std::env::var("MIGRATE_ON_BOOT").is_ok() {
+            // The reference database is writable only while migrations run
+            let reference = aop::db::create_reference_pool(&db_config, true).await;
+            aop::db::run_migrations(&users, &reference).await;
+            reference.close().await;
+        } else {
+            eprintln!(
+                "{} pending database migrations; run `aop migrate` before starting the server",
+                pending
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // Check the paint data, reporting unusable paints without refusing to start
+    let report =
+        aop::services::paint_validation::validate_paint_data(&db, users.store().as_ref()).await;
+    for issue in report
+        .issues
+        .iter()
+        .filter(|i| i.severity == aop::services::paint_validation::Severity::Error)
+    {
+        eprintln!("{}", issue);
+    }
+    println!("Paint data: {} (run `aop validate` for details)", report.summary());
+
+    // Build the target reflectance lookup table before the first mix needs it
+    tokio::task::spawn_blocking(|| {
+        aop::services::target_reflectance::TargetReflectance::for_colorimetry(&Default::default())
+    });
 
     // Create app state
     let state = AppState {
         db: db.clone(),
+        paints: aop::services::paint_cache::PaintCache::load(&db, users.store().as_ref()).await,
+        users: users.store(),
         email: Arc::new(aop::services::email::Email {
             api_key: std::env::var("RESEND_API_KEY").unwrap_or_default(),
             from: std::env::var("EMAIL_FROM")
@@ -30,9 +113,42 @@ async fn main() {
         }),
     };
 
-    // Session store
-    let session_store = MemoryStore::default();
+    // Servers sharing the user database pick up each other's catalogue edits
+    tokio::spawn({
+        let db = db.clone();
+        let paints = state.paints.clone();
+        let users = state.users.clone();
+        async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                paints.refresh(&db, users.as_ref()).await;
+            }
+        }
+    });
+
+    // Sessions live in the user database; expired ones are swept hourly
+    let session_config = aop::db::SessionConfig::from_env();
+    let session_store =
+        aop::db::UserSessionStore::new(state.users.clone(), session_config.lifetime);
+    tokio::spawn({
+        let session_store = session_store.clone();
+        async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = session_store.delete_expired().await {
+                    eprintln!("Failed to delete expired sessions: {}", e);
+                }
+            }
+        }
+    });
     let session_layer = SessionManagerLayer::new(session_store)
+        .with_name(session_config.cookie_name)
+        .with_expiry(Expiry::OnInactivity(session_config.idle_timeout))
+        // Saving on every request is what pushes back the idle expiry
+        .with_always_save(true)
         .with_secure(std::env::var("PRODUCTION").is_ok())
         .with_same_site(tower_sessions::cookie::SameSite::Lax);
 
@@ -60,6 +176,144 @@ async fn main() {
         .unwrap();
 }
 
+/// Run `aop migrate`, returning the process exit code
+#[cfg(feature = "ssr")]
+async fn migrate_command(users: &aop::db::UserDatabase, reference: &aop::db::Db, args: &[String]) -> i32 {
+    use aop::db::{migrate_down, migrate_reference, migration_status, REFERENCE_DATABASE};
+
+    let reference_only = args.iter().any(|a| a == "--reference");
+    let args: Vec<&str> = args.iter().map(String::as_str).filter(|a| *a != "--reference").collect();
+
+    match args.first().copied().unwrap_or("up") {
+        "up" => {
+            let user_applied = match users.migrate_up().await {
+                Ok(applied) => applied,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            for version in &user_applied {
+                println!("Applied {} (user)", version);
+            }
+            let reference_applied = match migrate_reference(users, reference).await {
+                Ok(applied) => applied,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            for version in &reference_applied {
+                println!("Applied {} (reference)", version);
+            }
+            println!("{} migrations applied", user_applied.len() + reference_applied.len());
+            0
+        }
+        "down" => {
+            if std::env::var("PRODUCTION").is_ok() {
+                eprintln!("Refusing to revert migrations in production");
+                return 1;
+            }
+            let steps = match args.get(1).map(|s| s.parse::<usize>()) {
+                None => 1,
+                Some(Ok(steps)) => steps,
+                Some(Err(_)) => {
+                    eprintln!("Usage: aop migrate down [steps] [--reference]");
+                    return 2;
+                }
+            };
+            let (result, label) = if reference_only {
+                (migrate_down(reference, &REFERENCE_DATABASE, steps).await, "reference")
+            } else {
+                (users.migrate_down(steps).await, "user")
+            };
+            match result {
+                Ok(reverted) => {
+                    for version in &reverted {
+                        println!("Reverted {} ({})", version, label);
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            }
+        }
+        "status" => {
+            let databases = [
+                ("user", users.migration_status().await),
+                ("reference", migration_status(reference, &REFERENCE_DATABASE).await),
+            ];
+            for (label, status) in databases {
+                let status = match status {
+                    Ok(status) => status,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return 1;
+                    }
+                };
+                println!("{} database:", label);
+                for s in status {
+                    let state = match s.applied_at {
+                        Some(at) => chrono::DateTime::from_timestamp(at, 0)
+                            .map(|t| format!("applied {}", t.format("%Y-%m-%d %H:%M")))
+                            .unwrap_or_else(|| "applied".to_string()),
+                        None => "pending".to_string(),
+                    };
+                    let reversible = if s.reversible { "" } else { " (irreversible)" };
+                    println!("  {:<52} {}{}", s.version, state, reversible);
+                }
+            }
+            0
+        }
+        _ => {
+            eprintln!("Usage: aop migrate [status | up | down [steps] [--reference]]");
+            2
+        }
+    }
+}
+
+/// Run `aop import-users`, returning the process exit code
+#[cfg(feature = "ssr")]
+async fn import_users_command(users: &aop::db::UserDatabase, source: Option<String>) -> i32 {
+    let Some(source) = source else {
+        eprintln!("Usage: aop import-users <sqlite-url>");
+        return 2;
+    };
+    let aop::db::UserDatabase::Postgres(store) = users else {
+        eprintln!("DATABASE_URL must name the Postgres database to import into");
+        return 2;
+    };
+    if let Err(e) = users.migrate_up().await {
+        eprintln!("{}", e);
+        return 1;
+    }
+    match store.has_users().await {
+        Ok(false) => {}
+        Ok(true) => {
+            eprintln!("The Postgres user database already has accounts; import only into an empty one");
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+    match store.import_sqlite(&source).await {
+        Ok(copied) => {
+            for (table, rows) in copied {
+                println!("{:<16} {} rows", table, rows);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Import failed, nothing was copied: {}", e);
+            1
+        }
+    }
+}
+
 #[cfg(feature = "ssr")]
 fn shell(options: leptos::config::LeptosOptions) -> impl leptos::IntoView {
     use aop::App;
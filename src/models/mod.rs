@@ -1,3 +1,9 @@
+mod colour;
 mod paint;
+mod spectral_grid;
+mod target_colour;
 
+pub use colour::*;
 pub use paint::*;
+pub use spectral_grid::*;
+pub use target_colour::*;
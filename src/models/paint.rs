@@ -12,9 +12,108 @@ pub struct SpectralData {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct MixingResult {
     pub paints: Vec<String>,
+    /// Brand display name for each paint
+    #[serde(default)]
+    pub brands: Vec<String>,
     pub weights: Vec<f64>,
     pub error: f64,
     pub hex_colors: Vec<String>,
+    #[serde(default)]
+    pub warnings: Vec<MixWarning>,
+}
+
+/// Mixes found for one target of a batch run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchMixResult {
+    pub label: String,
+    pub target_hex: Option<String>,
+    pub results: Vec<MixingResult>,
+    pub error: Option<String>,
+}
+
+/// Largest total number of parts used when expressing a mix as a ratio
+const MAX_PARTS: u32 = 20;
+
+impl MixingResult {
+    /// Weights as whole-number parts (e.g. 3:2:1), the way paint is measured out
+    /// at the easel. Uses the smallest total that keeps every share within 2%.
+    pub fn parts(&self) -> Vec<u32> {
+        let total: f64 = self.weights.iter().sum();
+        if self.weights.is_empty() || total <= 0.0 {
+            return vec![];
+        }
+        let shares: Vec<f64> = self.weights.iter().map(|w| w / total).collect();
+
+        let mut best: Option<(f64, Vec<u32>)> = None;
+        for n in 1..=MAX_PARTS {
+            // Every paint in the mix gets at least one part
+            let parts: Vec<u32> = shares
+                .iter()
+                .map(|s| ((s * n as f64).round() as u32).max(1))
+                .collect();
+            let sum: u32 = parts.iter().sum();
+            let error = parts
+                .iter()
+                .zip(&shares)
+                .map(|(&p, s)| (p as f64 / sum as f64 - s).abs())
+                .fold(0.0, f64::max);
+
+            if best.as_ref().is_none_or(|(e, _)| error < *e) {
+                best = Some((error, parts));
+            }
+            if error <= 0.02 {
+                break;
+            }
+        }
+
+        let parts = best.map(|(_, p)| p).unwrap_or_default();
+        let divisor = parts.iter().copied().fold(0, gcd).max(1);
+        parts.into_iter().map(|p| p / divisor).collect()
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Pigment-related caveats attached to a recommended mix
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MixWarning {
+    /// Mixing many distinct pigments tends to produce dull, muddy colour
+    ManyPigments { count: usize },
+    /// Two pigments that are known to react with or degrade each other
+    IncompatiblePigments {
+        first: String,
+        second: String,
+        reason: String,
+    },
+    /// A fugitive paint makes up the largest share of the mix
+    FugitiveDominant { paint: String, share: f64 },
+}
+
+impl MixWarning {
+    pub fn message(&self) -> String {
+        match self {
+            MixWarning::ManyPigments { count } => format!(
+                "Combines {} distinct pigments (excluding white), which is prone to mud",
+                count
+            ),
+            MixWarning::IncompatiblePigments {
+                first,
+                second,
+                reason,
+            } => format!("{} with {}: {}", first, second, reason),
+            MixWarning::FugitiveDominant { paint, share } => format!(
+                "{} is fugitive and makes up {:.0}% of the mix",
+                paint,
+                share * 100.0
+            ),
+        }
+    }
 }
 
 /// Internal mixture representation during optimization
@@ -58,17 +157,6 @@ impl MixChoice {
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "black + white + 2 colours" => Some(MixChoice::BlackWhite2Colors),
-            "black + white + 3 colours" => Some(MixChoice::BlackWhite3Colors),
-            "all available colours" => Some(MixChoice::AllAvailableColors),
-            "neutral greys" => Some(MixChoice::NeutralGreys),
-            "no black" => Some(MixChoice::NoBlack),
-            _ => None,
-        }
-    }
-
     pub fn all() -> Vec<MixChoice> {
         vec![
             MixChoice::BlackWhite2Colors,
@@ -79,3 +167,315 @@ impl MixChoice {
         ]
     }
 }
+
+impl std::str::FromStr for MixChoice {
+    type Err = ColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "black + white + 2 colours" => Ok(MixChoice::BlackWhite2Colors),
+            "black + white + 3 colours" => Ok(MixChoice::BlackWhite3Colors),
+            "all available colours" => Ok(MixChoice::AllAvailableColors),
+            "neutral greys" => Ok(MixChoice::NeutralGreys),
+            "no black" => Ok(MixChoice::NoBlack),
+            _ => Err(ColorError::OptimizationError("Invalid mix choice".into())),
+        }
+    }
+}
+
+/// Opacity class as printed on the tube
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Opacity {
+    Transparent,
+    SemiTransparent,
+    SemiOpaque,
+    Opaque,
+}
+
+impl Opacity {
+    /// Short code used in the database and on manufacturer charts
+    pub fn code(&self) -> &'static str {
+        match self {
+            Opacity::Transparent => "T",
+            Opacity::SemiTransparent => "ST",
+            Opacity::SemiOpaque => "SO",
+            Opacity::Opaque => "O",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "T" => Some(Opacity::Transparent),
+            "ST" => Some(Opacity::SemiTransparent),
+            "SO" => Some(Opacity::SemiOpaque),
+            "O" => Some(Opacity::Opaque),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Opacity::Transparent => "transparent",
+            Opacity::SemiTransparent => "semi-transparent",
+            Opacity::SemiOpaque => "semi-opaque",
+            Opacity::Opaque => "opaque",
+        }
+    }
+
+    pub fn all() -> Vec<Opacity> {
+        vec![
+            Opacity::Transparent,
+            Opacity::SemiTransparent,
+            Opacity::SemiOpaque,
+            Opacity::Opaque,
+        ]
+    }
+}
+
+/// ASTM D4303 lightfastness category (I is best)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lightfastness {
+    I,
+    II,
+    III,
+    IV,
+    V,
+}
+
+impl Lightfastness {
+    /// The best rating that counts as fugitive
+    pub const FUGITIVE: Lightfastness = Lightfastness::IV;
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lightfastness::I => "I",
+            Lightfastness::II => "II",
+            Lightfastness::III => "III",
+            Lightfastness::IV => "IV",
+            Lightfastness::V => "V",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "I" => Some(Lightfastness::I),
+            "II" => Some(Lightfastness::II),
+            "III" => Some(Lightfastness::III),
+            "IV" => Some(Lightfastness::IV),
+            "V" => Some(Lightfastness::V),
+            _ => None,
+        }
+    }
+
+    /// ASTM IV (poor) and V (very poor) are fugitive; III is "fair" and
+    /// still accepted in artists' paint
+    pub fn is_fugitive(&self) -> bool {
+        *self >= Lightfastness::FUGITIVE
+    }
+
+    pub fn all() -> Vec<Lightfastness> {
+        vec![
+            Lightfastness::I,
+            Lightfastness::II,
+            Lightfastness::III,
+            Lightfastness::IV,
+            Lightfastness::V,
+        ]
+    }
+}
+
+/// Relative drying speed of a paint
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DryingSpeed {
+    Fast,
+    Medium,
+    Slow,
+}
+
+impl DryingSpeed {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DryingSpeed::Fast => "fast",
+            DryingSpeed::Medium => "medium",
+            DryingSpeed::Slow => "slow",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fast" => Some(DryingSpeed::Fast),
+            "medium" => Some(DryingSpeed::Medium),
+            "slow" => Some(DryingSpeed::Slow),
+            _ => None,
+        }
+    }
+
+    pub fn all() -> Vec<DryingSpeed> {
+        vec![DryingSpeed::Fast, DryingSpeed::Medium, DryingSpeed::Slow]
+    }
+}
+
+/// What a paint is used for in mixing, beyond its colour
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PaintRole {
+    /// Lightens mixes in the black + white strategies
+    White,
+    /// Darkens mixes in the black + white strategies; left out of "no black"
+    Black,
+    /// Iron oxide and umber browns, reds and yellows
+    Earth,
+    /// A spectrally flat mid-tone, used by the neutral greys strategy
+    NeutralGrey,
+}
+
+impl PaintRole {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaintRole::White => "white",
+            PaintRole::Black => "black",
+            PaintRole::Earth => "earth",
+            PaintRole::NeutralGrey => "neutral_grey",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "white" => Some(PaintRole::White),
+            "black" => Some(PaintRole::Black),
+            "earth" => Some(PaintRole::Earth),
+            "neutral_grey" => Some(PaintRole::NeutralGrey),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaintRole::White => "white",
+            PaintRole::Black => "black",
+            PaintRole::Earth => "earth",
+            PaintRole::NeutralGrey => "neutral grey",
+        }
+    }
+
+    pub fn all() -> Vec<PaintRole> {
+        vec![
+            PaintRole::White,
+            PaintRole::Black,
+            PaintRole::Earth,
+            PaintRole::NeutralGrey,
+        ]
+    }
+}
+
+/// Per-paint metadata used for layering, permanence and drying advice.
+/// Every field is optional because not all brands publish every attribute.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PaintAttributes {
+    pub opacity: Option<Opacity>,
+    /// Colour Index pigment codes, e.g. `["PW6", "PB29"]`
+    pub pigments: Vec<String>,
+    pub lightfastness: Option<Lightfastness>,
+    /// Manufacturer series number, which doubles as the price band
+    pub series: Option<u8>,
+    pub drying_speed: Option<DryingSpeed>,
+    #[serde(default)]
+    pub role: Option<PaintRole>,
+}
+
+impl PaintAttributes {
+    /// Whether the manufacturer's data is known: opacity, pigments,
+    /// lightfastness, series or drying speed. The role is left out, as it is
+    /// detected for every paint.
+    pub fn has_metadata(&self) -> bool {
+        self.opacity.is_some()
+            || !self.pigments.is_empty()
+            || self.lightfastness.is_some()
+            || self.series.is_some()
+            || self.drying_speed.is_some()
+    }
+
+    /// Parse a comma separated list of pigment codes ("PW6, PB29") into normalised codes
+    pub fn parse_pigments(s: &str) -> Vec<String> {
+        s.split([',', ';', '+'])
+            .map(|p| p.split_whitespace().collect::<String>().to_uppercase())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// Short human readable summary, e.g. "white · PW6 · opaque · LF I · series 2"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(role) = self.role {
+            parts.push(role.label().to_string());
+        }
+        if !self.pigments.is_empty() {
+            parts.push(self.pigments.join(", "));
+        }
+        if let Some(o) = self.opacity {
+            parts.push(o.label().to_string());
+        }
+        if let Some(lf) = self.lightfastness {
+            parts.push(format!("LF {}", lf.code()));
+        }
+        if let Some(series) = self.series {
+            parts.push(format!("series {}", series));
+        }
+        if let Some(d) = self.drying_speed {
+            parts.push(format!("{} drying", d.code()));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// Optional filters applied to the palette before searching for mixes.
+/// Paints with an unknown value for a filtered attribute are kept.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PaintFilters {
+    /// Drop fugitive paints, rated ASTM IV or worse
+    #[serde(default)]
+    pub exclude_fugitive: bool,
+    /// Only keep these opacity classes (empty means any)
+    #[serde(default)]
+    pub opacity: Vec<Opacity>,
+    /// Highest series (price band) to include
+    #[serde(default)]
+    pub max_series: Option<u8>,
+    /// Only keep these drying speeds (empty means any)
+    #[serde(default)]
+    pub drying_speed: Vec<DryingSpeed>,
+    /// Drop paints containing any of these pigment codes
+    #[serde(default)]
+    pub exclude_pigments: Vec<String>,
+}
+
+impl PaintFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == PaintFilters::default()
+    }
+
+    pub fn matches(&self, attrs: &PaintAttributes) -> bool {
+        if self.exclude_fugitive && attrs.lightfastness.is_some_and(|lf| lf.is_fugitive()) {
+            return false;
+        }
+        if let (false, Some(o)) = (self.opacity.is_empty(), attrs.opacity) {
+            if !self.opacity.contains(&o) {
+                return false;
+            }
+        }
+        if let (Some(max), Some(series)) = (self.max_series, attrs.series) {
+            if series > max {
+                return false;
+            }
+        }
+        if let (false, Some(d)) = (self.drying_speed.is_empty(), attrs.drying_speed) {
+            if !self.drying_speed.contains(&d) {
+                return false;
+            }
+        }
+        !attrs.pigments.iter().any(|p| {
+            self.exclude_pigments
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(p))
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Wavelength sampling of reflectance curves and colour matching functions
+//!
+//! Curves only make sense together with the wavelengths they were sampled at;
+//! a `SpectralGrid` carries them so data on different grids is resampled
+//! rather than silently misaligned.
+
+use serde::{Deserialize, Serialize};
+
+/// Evenly spaced wavelengths: `count` samples from `start` nm every `step` nm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpectralGrid {
+    pub start: u32,
+    pub step: u32,
+    pub count: usize,
+}
+
+impl SpectralGrid {
+    /// 400–700 nm in 10 nm steps, the grid the paint data and imports are stored on
+    pub const PAINT_DATA: SpectralGrid = SpectralGrid::new(400, 10, 31);
+
+    pub const fn new(start: u32, step: u32, count: usize) -> Self {
+        SpectralGrid { start, step, count }
+    }
+
+    /// Last wavelength on the grid, in nm
+    pub fn end(&self) -> f64 {
+        self.wavelength(self.count.saturating_sub(1))
+    }
+
+    /// Wavelength of the `i`th sample, in nm
+    pub fn wavelength(&self, i: usize) -> f64 {
+        self.start as f64 + self.step as f64 * i as f64
+    }
+
+    pub fn wavelengths(&self) -> impl Iterator<Item = f64> {
+        let grid = *self;
+        (0..grid.count).map(move |i| grid.wavelength(i))
+    }
+
+    /// The two samples either side of a wavelength and the interpolation
+    /// fraction between them. Wavelengths off the grid clamp to its ends.
+    pub fn bracket(&self, wavelength: f64) -> (usize, usize, f64) {
+        let last = self.count.saturating_sub(1);
+        let position = (wavelength - self.start as f64) / self.step as f64;
+        if position <= 0.0 {
+            return (0, 0, 0.0);
+        }
+        if position >= last as f64 {
+            return (last, last, 0.0);
+        }
+        let lower = position.floor() as usize;
+        (lower, lower + 1, position - lower as f64)
+    }
+
+    /// Value of a curve on this grid at any wavelength, by linear interpolation
+    /// and holding the end values beyond the grid
+    pub fn interpolate(&self, values: &[f64], wavelength: f64) -> f64 {
+        let (lower, upper, t) = self.bracket(wavelength);
+        values[lower] + (values[upper] - values[lower]) * t
+    }
+
+    /// Resample a curve from the `source` grid onto this one, or `None` if the
+    /// curve does not have one value per source wavelength
+    pub fn resample(&self, source: &SpectralGrid, values: &[f64]) -> Option<Vec<f64>> {
+        if values.len() != source.count || values.is_empty() {
+            return None;
+        }
+        if source == self {
+            return Some(values.to_vec());
+        }
+        Some(self.wavelengths().map(|wl| source.interpolate(values, wl)).collect())
+    }
+
+    /// Resample irregularly spaced `(wavelength, value)` points, sorted by
+    /// wavelength, onto this grid, holding the end values beyond them
+    pub fn sample_points(&self, points: &[(f64, f64)]) -> Vec<f64> {
+        self.wavelengths()
+            .map(|wl| match points.iter().position(|&(w, _)| w >= wl) {
+                None => points[points.len() - 1].1,
+                Some(0) => points[0].1,
+                Some(j) => {
+                    let (w0, v0) = points[j - 1];
+                    let (w1, v1) = points[j];
+                    v0 + (v1 - v0) * (wl - w0) / (w1 - w0)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for SpectralGrid {
+    fn default() -> Self {
+        SpectralGrid::PAINT_DATA
+    }
+}
+
+impl std::fmt::Display for SpectralGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}–{} nm every {} nm", self.start, self.end(), self.step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_points_holds_the_ends() {
+        let grid = SpectralGrid::new(400, 10, 4);
+        let points = [(405.0, 0.2), (425.0, 0.6)];
+        let sampled = grid.sample_points(&points);
+        for (value, expected) in sampled.iter().zip([0.2, 0.3, 0.5, 0.6]) {
+            assert!((value - expected).abs() < 1e-12, "{:?}", sampled);
+        }
+    }
+
+    #[test]
+    fn sample_points_on_the_grid_edges() {
+        let grid = SpectralGrid::new(400, 10, 3);
+        let points = [(400.0, 0.1), (410.0, 0.3), (420.0, 0.5)];
+        assert_eq!(grid.sample_points(&points), vec![0.1, 0.3, 0.5]);
+
+        // Points beyond both ends are interpolated, not held
+        let points = [(390.0, 0.0), (430.0, 0.8)];
+        let sampled = grid.sample_points(&points);
+        for (value, expected) in sampled.iter().zip([0.2, 0.4, 0.6]) {
+            assert!((value - expected).abs() < 1e-12, "{:?}", sampled);
+        }
+    }
+
+    #[test]
+    fn resample_between_grids() {
+        let coarse = SpectralGrid::new(400, 20, 2);
+        let fine = SpectralGrid::new(400, 10, 3);
+        assert_eq!(fine.resample(&coarse, &[0.2, 0.6]), Some(vec![0.2, 0.4, 0.6]));
+        assert_eq!(fine.resample(&coarse, &[0.2]), None);
+    }
+}
@@ -0,0 +1,356 @@
+//! Target colours written in any of the notations painters and colorimeters use
+//!
+//! Every notation converts to CIE XYZ (D65, Y = 1 for white) without clipping,
+//! so targets outside sRGB keep their full chroma through to the mixing search.
+
+use std::fmt;
+
+use crate::models::{
+    lab_to_xyz, linear_rgb_to_xyz, named_colour, parse_hex, srgb_to_linear, srgb_to_xyz,
+    to_hex, xyz_to_linear_rgb, xyz_to_srgb, D65_WHITE,
+};
+
+/// A target colour as entered by the user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetColour {
+    /// 8-bit sRGB, from hex, a CSS name or a picker
+    Srgb([u8; 3]),
+    /// CIELAB (D65)
+    Lab([f64; 3]),
+    /// CIE LCh(ab) (D65), hue in degrees
+    Lch([f64; 3]),
+    /// CIE XYZ (D65) with Y on a 0–100 scale, as colorimeters report it
+    Xyz([f64; 3]),
+    /// HSL over sRGB: hue in degrees, saturation and lightness in 0..=1
+    Hsl([f64; 3]),
+    /// OKLab, lightness in 0..=1
+    Oklab([f64; 3]),
+    /// OKLCH, lightness in 0..=1 and hue in degrees
+    Oklch([f64; 3]),
+    /// Munsell hue (0..100, where 5 is 5R), value and chroma
+    Munsell { hue: f64, value: f64, chroma: f64 },
+}
+
+/// Munsell hue families in order, each spanning ten hue steps
+const MUNSELL_HUES: [&str; 10] = ["R", "YR", "Y", "GY", "G", "BG", "B", "PB", "P", "RP"];
+
+/// Approximate CIELAB hue angle of each principal Munsell hue (5R, 5YR, ...)
+const MUNSELL_HUE_ANGLES: [f64; 10] = [
+    25.0, 62.0, 92.0, 118.0, 162.0, 196.0, 236.0, 278.0, 314.0, 348.0,
+];
+
+/// Approximate CIELAB chroma per step of Munsell chroma
+const MUNSELL_CHROMA_SCALE: f64 = 5.0;
+
+impl TargetColour {
+    /// Parse a colour written as `#rrggbb`, a CSS colour name, `lab(L a b)`,
+    /// `lch(L C h)`, `xyz(X Y Z)`, `hsl(h s% l%)`, `oklab(L a b)`,
+    /// `oklch(L C h)` or Munsell `5R 4/14` (`N 5/` for neutrals). Three bare
+    /// numbers are read as Lab.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some(rgb) = parse_hex(text).or_else(|| named_colour(text)) {
+            return Ok(TargetColour::Srgb(rgb));
+        }
+        if let Some(munsell) = parse_munsell(text) {
+            return Ok(munsell);
+        }
+
+        let lower = text.to_lowercase();
+        let (name, args) = match lower.find('(') {
+            Some(open) => {
+                let args = lower[open + 1..]
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Missing ')' in {}", text))?;
+                (lower[..open].trim(), args)
+            }
+            None => ("lab", lower.as_str()),
+        };
+        let [a, b, c] = parse_components(args).ok_or_else(|| format!("Unrecognised colour: {}", text))?;
+
+        let colour = match name {
+            "lab" => TargetColour::Lab([a.value, b.value, c.value]),
+            "lch" => TargetColour::Lch([a.value, b.value, c.value]),
+            "xyz" => TargetColour::Xyz([a.value, b.value, c.value]),
+            "hsl" => TargetColour::Hsl([degrees(a), b.unit(), c.unit()]),
+            "oklab" => TargetColour::Oklab([a.unit(), b.value, c.value]),
+            "oklch" => TargetColour::Oklch([a.unit(), b.value, degrees(c)]),
+            _ => return Err(format!("Unknown colour notation: {}", name)),
+        };
+        colour.validate()?;
+        Ok(colour)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let ok = match *self {
+            TargetColour::Srgb(_) => true,
+            TargetColour::Lab([l, ..]) | TargetColour::Lch([l, ..]) => (0.0..=100.0).contains(&l),
+            TargetColour::Xyz(xyz) => xyz.iter().all(|&v| v >= 0.0) && xyz[1] <= 100.0,
+            TargetColour::Hsl([_, s, l]) => (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&l),
+            TargetColour::Oklab([l, ..]) | TargetColour::Oklch([l, ..]) => (0.0..=1.0).contains(&l),
+            TargetColour::Munsell { value, chroma, .. } => {
+                (0.0..=10.0).contains(&value) && chroma >= 0.0
+            }
+        };
+        let chroma_ok = match *self {
+            TargetColour::Lch([_, c, _]) | TargetColour::Oklch([_, c, _]) => c >= 0.0,
+            _ => true,
+        };
+        if ok && chroma_ok {
+            Ok(())
+        } else {
+            Err(format!("{} is out of range", self))
+        }
+    }
+
+    /// CIE XYZ under D65 with Y = 1 for white, unclipped
+    pub fn to_xyz(&self) -> [f64; 3] {
+        match *self {
+            TargetColour::Srgb(rgb) => srgb_to_xyz(rgb),
+            TargetColour::Lab(lab) => lab_to_xyz(lab, D65_WHITE),
+            TargetColour::Lch(lch) => lab_to_xyz(lch_to_lab(lch), D65_WHITE),
+            TargetColour::Xyz(xyz) => xyz.map(|v| v / 100.0),
+            TargetColour::Hsl(hsl) => linear_rgb_to_xyz(hsl_to_rgb(hsl).map(srgb_to_linear)),
+            TargetColour::Oklab(lab) => linear_rgb_to_xyz(oklab_to_linear_rgb(lab)),
+            TargetColour::Oklch(lch) => linear_rgb_to_xyz(oklab_to_linear_rgb(lch_to_lab(lch))),
+            TargetColour::Munsell { hue, value, chroma } => {
+                lab_to_xyz(munsell_to_lab(hue, value, chroma), D65_WHITE)
+            }
+        }
+    }
+
+    /// Nearest 8-bit sRGB, clipping colours outside the gamut
+    pub fn to_srgb(&self) -> [u8; 3] {
+        match *self {
+            TargetColour::Srgb(rgb) => rgb,
+            _ => xyz_to_srgb(self.to_xyz()),
+        }
+    }
+
+    /// Whether `to_xyz` is only an estimate: Munsell hue and chroma are
+    /// interpolated from the principal hues rather than the renotation data
+    pub fn is_approximate(&self) -> bool {
+        matches!(*self, TargetColour::Munsell { chroma, .. } if chroma > 0.0)
+    }
+
+    /// Whether the colour can be shown on an sRGB display without clipping
+    pub fn in_srgb_gamut(&self) -> bool {
+        xyz_to_linear_rgb(self.to_xyz())
+            .iter()
+            .all(|&c| (-0.001..=1.001).contains(&c))
+    }
+}
+
+impl From<[u8; 3]> for TargetColour {
+    fn from(rgb: [u8; 3]) -> Self {
+        TargetColour::Srgb(rgb)
+    }
+}
+
+impl fmt::Display for TargetColour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TargetColour::Srgb(rgb) => write!(f, "{}", to_hex(rgb)),
+            TargetColour::Lab([l, a, b]) => write!(f, "lab({:.1} {:.1} {:.1})", l, a, b),
+            TargetColour::Lch([l, c, h]) => write!(f, "lch({:.1} {:.1} {:.1})", l, c, h),
+            TargetColour::Xyz([x, y, z]) => write!(f, "xyz({:.2} {:.2} {:.2})", x, y, z),
+            TargetColour::Hsl([h, s, l]) => {
+                write!(f, "hsl({:.0} {:.0}% {:.0}%)", h, s * 100.0, l * 100.0)
+            }
+            TargetColour::Oklab([l, a, b]) => write!(f, "oklab({:.3} {:.3} {:.3})", l, a, b),
+            TargetColour::Oklch([l, c, h]) => write!(f, "oklch({:.3} {:.3} {:.1})", l, c, h),
+            TargetColour::Munsell { value, chroma: 0.0, .. } => {
+                write!(f, "N {}/", value)
+            }
+            TargetColour::Munsell { hue, value, chroma } => {
+                // Hue 0 is 10RP, the end of the last family
+                let hue = if hue <= 0.0 { 100.0 } else { hue };
+                let family = ((hue - 1e-9) / 10.0).floor() as usize;
+                let step = hue - family as f64 * 10.0;
+                write!(f, "{}{} {}/{}", step, MUNSELL_HUES[family.min(9)], value, chroma)
+            }
+        }
+    }
+}
+
+/// A number from a colour function, remembering whether it was a percentage
+#[derive(Clone, Copy)]
+struct Component {
+    value: f64,
+    percent: bool,
+}
+
+impl Component {
+    /// Value on a 0..=1 scale, reading percentages and numbers above 1 as percent
+    fn unit(self) -> f64 {
+        if self.percent || self.value > 1.0 {
+            self.value / 100.0
+        } else {
+            self.value
+        }
+    }
+}
+
+/// Hue angle wrapped into 0..360
+fn degrees(c: Component) -> f64 {
+    c.value.rem_euclid(360.0)
+}
+
+/// Three numbers separated by commas, spaces or `/`
+fn parse_components(args: &str) -> Option<[Component; 3]> {
+    let parts: Vec<Component> = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            let percent = v.ends_with('%');
+            let value = v.trim_end_matches('%').trim_end_matches("deg").parse().ok()?;
+            Some(Component { value, percent })
+        })
+        .collect::<Option<_>>()?;
+    parts.try_into().ok()
+}
+
+/// Munsell notation: `5R 4/14`, `2.5YR6/8`, `N 5/`, `N 5/0` or `N5`
+fn parse_munsell(text: &str) -> Option<TargetColour> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let upper = compact.to_uppercase();
+
+    if let Some(rest) = upper.strip_prefix('N') {
+        let (value, chroma) = rest.split_once('/').unwrap_or((rest, ""));
+        if !chroma.is_empty() && chroma.parse::<f64>().ok()? != 0.0 {
+            return None;
+        }
+        let value: f64 = value.parse().ok()?;
+        return (0.0..=10.0).contains(&value).then_some(TargetColour::Munsell {
+            hue: 0.0,
+            value,
+            chroma: 0.0,
+        });
+    }
+
+    let (hue_part, rest) = upper.split_at(upper.find(|c: char| c.is_ascii_alphabetic())?);
+    let family_len = rest.find(|c: char| !c.is_ascii_alphabetic())?;
+    let (family, value_chroma) = rest.split_at(family_len);
+    let (value, chroma) = value_chroma.split_once('/')?;
+
+    let step: f64 = hue_part.parse().ok()?;
+    let family = MUNSELL_HUES.iter().position(|h| *h == family)?;
+    if !(0.0..=10.0).contains(&step) {
+        return None;
+    }
+    let colour = TargetColour::Munsell {
+        hue: (family as f64 * 10.0 + step).rem_euclid(100.0),
+        value: value.parse().ok()?,
+        chroma: chroma.parse().ok()?,
+    };
+    colour.validate().ok()?;
+    Some(colour)
+}
+
+/// Approximate CIELAB for a Munsell colour. Value maps to luminance with the
+/// ASTM D1535 polynomial; hue and chroma are interpolated from the principal
+/// hues, which is good to a few ΔE for mid values and moderate chroma.
+fn munsell_to_lab(hue: f64, value: f64, chroma: f64) -> [f64; 3] {
+    let y = 1.1914 * value - 0.22533 * value.powi(2) + 0.23352 * value.powi(3)
+        - 0.020484 * value.powi(4)
+        + 0.00081939 * value.powi(5);
+    let l = lab_l_from_y(y / 100.0);
+    if chroma == 0.0 {
+        return [l, 0.0, 0.0];
+    }
+
+    // Principal hues sit at 5, 15, ... 95 on the 0..100 hue circle
+    let position = (hue - 5.0).rem_euclid(100.0) / 10.0;
+    let i = position.floor() as usize % 10;
+    let t = position.fract();
+    let (from, mut to) = (MUNSELL_HUE_ANGLES[i], MUNSELL_HUE_ANGLES[(i + 1) % 10]);
+    if to < from {
+        to += 360.0;
+    }
+    let angle = from + (to - from) * t;
+    lch_to_lab([l, chroma * MUNSELL_CHROMA_SCALE, angle])
+}
+
+fn lab_l_from_y(y: f64) -> f64 {
+    if y > 0.008856 {
+        116.0 * y.cbrt() - 16.0
+    } else {
+        903.3 * y
+    }
+}
+
+/// Cylindrical (lightness, chroma, hue in degrees) to rectangular coordinates
+fn lch_to_lab([l, c, h]: [f64; 3]) -> [f64; 3] {
+    let h = h.to_radians();
+    [l, c * h.cos(), c * h.sin()]
+}
+
+/// HSL to gamma-encoded sRGB in 0..=1
+fn hsl_to_rgb([h, s, l]: [f64; 3]) -> [f64; 3] {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let hp = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (hp % 2.0 - 1.0).abs());
+    let (r, g, b) = match hp as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    [r + m, g + m, b + m]
+}
+
+/// OKLab to linear sRGB (Björn Ottosson's matrices), unclipped
+fn oklab_to_linear_rgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+    let (l, m, s) = (l_.powi(3), m_.powi(3), s_.powi(3));
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn munsell_neutrals() {
+        for text in ["N 5/", "N5", "n 5/0"] {
+            let colour = TargetColour::parse(text).unwrap();
+            assert_eq!(colour, TargetColour::Munsell { hue: 0.0, value: 5.0, chroma: 0.0 });
+            assert!(!colour.is_approximate());
+            assert_eq!(colour.to_string(), "N 5/");
+        }
+    }
+
+    #[test]
+    fn munsell_value_follows_astm_d1535() {
+        // 19.77% against MgO is 19.27% against the perfect diffuser
+        let y = TargetColour::parse("N 5/").unwrap().to_xyz()[1];
+        assert!((y - 0.1927).abs() < 0.001, "Y = {}", y);
+        let [r, g, b] = TargetColour::parse("N 5/").unwrap().to_srgb();
+        assert!(r == g && g == b);
+    }
+
+    #[test]
+    fn munsell_chromatic_is_approximate() {
+        let colour = TargetColour::parse("5R 4/14").unwrap();
+        assert_eq!(colour, TargetColour::Munsell { hue: 5.0, value: 4.0, chroma: 14.0 });
+        assert!(colour.is_approximate());
+        assert_eq!(colour.to_string(), "5R 4/14");
+        assert_eq!(TargetColour::parse("10RP 5/6").unwrap().to_string(), "10RP 5/6");
+    }
+
+    #[test]
+    fn munsell_out_of_range() {
+        assert!(TargetColour::parse("N 11/").is_err());
+        assert!(TargetColour::parse("N 5/2").is_err());
+        assert!(TargetColour::parse("12R 4/6").is_err());
+    }
+}
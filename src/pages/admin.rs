@@ -0,0 +1,629 @@
+use leptos::prelude::*;
+use leptos::web_sys;
+
+use crate::components::{read_file, AuthGuard};
+use crate::models::{DryingSpeed, Lightfastness, Opacity, PaintAttributes, PaintRole};
+use crate::server_fns::{
+    get_current_user, import_measurements, list_admin_brands, list_admin_paints, list_audit_log,
+    recompute_paint_hex, save_brand, set_paint_retired, update_catalogue_paint,
+    upload_paint_spectra, AdminPaint, BrandDetails, MeasuredTarget,
+};
+
+#[component]
+pub fn AdminPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <AdminGate/>
+        </AuthGuard>
+    }
+}
+
+/// Shows the catalogue editor to admins only; the server functions enforce it too
+#[component]
+fn AdminGate() -> impl IntoView {
+    let user = Resource::new(|| (), |_| get_current_user());
+
+    view! {
+        <Suspense fallback=|| view! { <div class="loading">"Loading..."</div> }>
+            {move || {
+                user.get().map(|result| match result {
+                    Ok(Some(u)) if u.is_admin => view! { <AdminCatalogue/> }.into_any(),
+                    _ => view! {
+                        <div class="admin-page">
+                            <h1>"Paint Catalogue"</h1>
+                            <p class="error">"Only admins can edit the paint catalogue."</p>
+                        </div>
+                    }
+                        .into_any(),
+                })
+            }}
+        </Suspense>
+    }
+}
+
+#[component]
+fn AdminCatalogue() -> impl IntoView {
+    // Bumped after every change so brands, paints and the audit log reload
+    let (refresh, set_refresh) = signal(0u32);
+    let (selected, set_selected) = signal(Option::<String>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (notice, set_notice) = signal(Option::<String>::None);
+
+    let brands = Resource::new(move || refresh.get(), |_| list_admin_brands());
+
+    // Brand being edited, and whether it is a new one
+    let (brand, set_brand) = signal(BrandDetails::default());
+    let (creating, set_creating) = signal(true);
+
+    let save = Action::new(move |_: &()| {
+        let (details, create) = (brand.get(), creating.get());
+        async move {
+            set_error.set(None);
+            let id = details.id.clone();
+            match save_brand(details, create).await {
+                Ok(()) => {
+                    set_notice.set(Some(if create { "Brand added" } else { "Brand saved" }.to_string()));
+                    set_creating.set(false);
+                    set_selected.set(Some(id));
+                    set_refresh.update(|n| *n += 1);
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    let text_field = move |label: &'static str,
+                           get: fn(&BrandDetails) -> String,
+                           set: fn(&mut BrandDetails, String)| {
+        view! {
+            <label class="filter-option">
+                {label}
+                <input
+                    type="text"
+                    prop:value=move || get(&brand.get())
+                    on:input=move |ev| set_brand.update(|b| set(b, event_target_value(&ev)))
+                />
+            </label>
+        }
+    };
+
+    view! {
+        <div class="admin-page">
+            <h1>"Paint Catalogue"</h1>
+            <p class="subtitle">
+                "Add brands, upload measured spectra and correct paint data. Every change is recorded in the audit log."
+            </p>
+            {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            {move || notice.get().map(|n| view! { <p class="hint">{n}</p> })}
+
+            <div class="settings-section">
+                <h2>"Brands"</h2>
+                <Suspense fallback=move || view! { <p>"Loading brands..."</p> }>
+                    {move || {
+                        brands
+                            .get()
+                            .map(|result| match result {
+                                Ok(list) => view! {
+                                    <div class="user-paint-list">
+                                        {list
+                                            .into_iter()
+                                            .map(|b| {
+                                                let id = b.details.id.clone();
+                                                let active_id = id.clone();
+                                                let details = b.details.clone();
+                                                view! {
+                                                    <button
+                                                        class="measurement-item"
+                                                        class:active=move || {
+                                                            selected.get().as_deref() == Some(active_id.as_str())
+                                                        }
+                                                        on:click=move |_| {
+                                                            set_selected.set(Some(id.clone()));
+                                                            set_brand.set(details.clone());
+                                                            set_creating.set(false);
+                                                        }
+                                                    >
+                                                        <strong>{b.details.display_name}</strong>
+                                                        <span class="hint">
+                                                            {format!("{} paints, {} retired", b.paints, b.retired)}
+                                                        </span>
+                                                    </button>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </div>
+                                }
+                                    .into_any(),
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            })
+                    }}
+                </Suspense>
+
+                <h3>{move || if creating.get() { "New Brand" } else { "Brand Details" }}</h3>
+                <div class="mix-filters">
+                    <label class="filter-option">
+                        "Id"
+                        <input
+                            type="text"
+                            placeholder="maker_range_oil"
+                            prop:value=move || brand.get().id
+                            disabled=move || !creating.get()
+                            on:input=move |ev| set_brand.update(|b| b.id = event_target_value(&ev))
+                        />
+                    </label>
+                    {text_field("Name", |b| b.display_name.clone(), |b, v| b.display_name = v)}
+                    {text_field(
+                        "Manufacturer",
+                        |b| b.manufacturer.clone().unwrap_or_default(),
+                        |b, v| b.manufacturer = optional(v),
+                    )}
+                    {text_field(
+                        "Medium",
+                        |b| b.medium.clone().unwrap_or_default(),
+                        |b, v| b.medium = optional(v),
+                    )}
+                    {text_field(
+                        "Measurement source",
+                        |b| b.measurement_source.clone().unwrap_or_default(),
+                        |b, v| b.measurement_source = optional(v),
+                    )}
+                    {text_field(
+                        "Data version",
+                        |b| b.data_version.clone().unwrap_or_default(),
+                        |b, v| b.data_version = optional(v),
+                    )}
+                    <button
+                        class="btn primary"
+                        on:click=move |_| { save.dispatch(()); }
+                        disabled=move || save.pending().get()
+                    >
+                        {move || if creating.get() { "Add Brand" } else { "Save Brand" }}
+                    </button>
+                    <button
+                        class="btn btn-secondary"
+                        on:click=move |_| {
+                            set_brand.set(BrandDetails::default());
+                            set_creating.set(true);
+                        }
+                    >
+                        "New Brand"
+                    </button>
+                </div>
+            </div>
+
+            {move || selected.get().map(|brand| view! {
+                <BrandPaints brand=brand refresh=refresh set_refresh=set_refresh set_error=set_error set_notice=set_notice/>
+            })}
+
+            <AuditLog refresh=refresh/>
+        </div>
+    }
+    .into_any()
+}
+
+/// Spectral upload and paint list of one brand
+#[component]
+fn BrandPaints(
+    brand: String,
+    refresh: ReadSignal<u32>,
+    set_refresh: WriteSignal<u32>,
+    set_error: WriteSignal<Option<String>>,
+    set_notice: WriteSignal<Option<String>>,
+) -> impl IntoView {
+    let paints = Resource::new(
+        {
+            let brand = brand.clone();
+            move || (brand.clone(), refresh.get())
+        },
+        |(brand, _)| list_admin_paints(brand),
+    );
+
+    // Samples read from an uploaded spectral file, with the file's name
+    let (upload, set_upload) = signal(Option::<(String, Vec<MeasuredTarget>)>::None);
+    let import = Action::new(move |(filename, data_url): &(String, String)| {
+        let (filename, data_url) = (filename.clone(), data_url.clone());
+        async move {
+            set_error.set(None);
+            match import_measurements(filename.clone(), data_url).await {
+                Ok(samples) => set_upload.set(Some((filename, samples))),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+    let handle_upload = move |ev: web_sys::Event| {
+        read_file(&ev, move |filename, data_url| {
+            import.dispatch((filename, data_url));
+        });
+    };
+    let apply_upload = {
+        let brand = brand.clone();
+        Action::new(move |_: &()| {
+            let brand = brand.clone();
+            let upload = upload.get();
+            async move {
+                let Some((filename, samples)) = upload else {
+                    return;
+                };
+                set_error.set(None);
+                match upload_paint_spectra(brand, filename, samples).await {
+                    Ok(summary) => {
+                        set_notice.set(Some(format!(
+                            "{} paints added, {} curves replaced",
+                            summary.added, summary.replaced
+                        )));
+                        set_upload.set(None);
+                        set_refresh.update(|n| *n += 1);
+                    }
+                    Err(e) => set_error.set(Some(e.to_string())),
+                }
+            }
+        })
+    };
+
+    view! {
+        <div class="settings-section">
+            <h2>"Upload Spectra"</h2>
+            <label class="image-upload-area">
+                <input type="file" accept=".txt,.cgats,.it8,.cxf,.csv" on:change=handle_upload/>
+                <div class="upload-placeholder">
+                    <span class="upload-icon">"📈"</span>
+                    <span>"Upload measured paints for this brand"</span>
+                    <span class="upload-hint">
+                        "CGATS, CxF3 or CSV reflectance covering 400–700 nm. Samples named like an existing paint replace its curve; others are added."
+                    </span>
+                </div>
+            </label>
+            {move || import.pending().get().then(|| view! { <p class="hint">"Reading measurements..."</p> })}
+            {move || upload.get().map(|(filename, samples)| {
+                let existing = paints.get().and_then(|r| r.ok()).unwrap_or_default();
+                view! {
+                    <p class="hint">{format!("{} samples in {}", samples.len(), filename)}</p>
+                    <div class="user-paint-list">
+                        {samples
+                            .into_iter()
+                            .map(|sample| {
+                                let old = existing.iter().find(|p| p.name == sample.name.trim());
+                                let old_hex = old.and_then(|p| p.hex.clone());
+                                let status = if old.is_some() { "replaces curve" } else { "new paint" };
+                                view! {
+                                    <div class="user-paint-item">
+                                        {old_hex.map(|hex| view! {
+                                            <div class="measurement-swatch" style=format!("background-color: {}", hex)></div>
+                                            "→"
+                                        })}
+                                        <div
+                                            class="measurement-swatch"
+                                            style=format!("background-color: {}", sample.hex)
+                                        ></div>
+                                        <div class="history-meta">
+                                            <strong>{sample.name}</strong>
+                                            <span class="hint">{format!("{} · {}", status, sample.hex)}</span>
+                                        </div>
+                                    </div>
+                                }
+                            })
+                            .collect_view()}
+                    </div>
+                    <div class="history-actions">
+                        <button
+                            class="btn primary"
+                            on:click=move |_| { apply_upload.dispatch(()); }
+                            disabled=move || apply_upload.pending().get()
+                        >
+                            "Apply Upload"
+                        </button>
+                        <button class="btn btn-secondary" on:click=move |_| set_upload.set(None)>
+                            "Discard"
+                        </button>
+                    </div>
+                }
+            })}
+        </div>
+
+        <div class="settings-section">
+            <h2>"Paints"</h2>
+            <p class="hint">
+                "Each paint shows its stored colour and the colour recomputed from its curve. Retired paints are kept for old mixes but no longer offered."
+            </p>
+            <Suspense fallback=move || view! { <p>"Loading paints..."</p> }>
+                {
+                    let brand = brand.clone();
+                    move || {
+                        let brand = brand.clone();
+                        paints
+                            .get()
+                            .map(move |result| match result {
+                                Ok(list) => view! {
+                                    <div class="user-paint-list">
+                                        {list
+                                            .into_iter()
+                                            .map(|paint| view! {
+                                                <AdminPaintItem
+                                                    brand=brand.clone()
+                                                    paint=paint
+                                                    set_refresh=set_refresh
+                                                    set_error=set_error
+                                                />
+                                            })
+                                            .collect_view()}
+                                    </div>
+                                }
+                                    .into_any(),
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            })
+                    }
+                }
+            </Suspense>
+        </div>
+    }
+    .into_any()
+}
+
+/// Recent catalogue changes
+#[component]
+fn AuditLog(refresh: ReadSignal<u32>) -> impl IntoView {
+    let audit_log = Resource::new(move || refresh.get(), |_| list_audit_log(None));
+
+    view! {
+        <div class="settings-section">
+            <h2>"Audit Log"</h2>
+            <Suspense fallback=move || view! { <p>"Loading audit log..."</p> }>
+                {move || {
+                    audit_log
+                        .get()
+                        .map(|result| match result {
+                            Ok(list) if list.is_empty() => {
+                                view! { <p class="hint">"No catalogue changes yet"</p> }.into_any()
+                            }
+                            Ok(list) => view! {
+                                <div class="user-paint-list">
+                                    {list
+                                        .into_iter()
+                                        .map(|entry| {
+                                            let subject = [entry.brand, entry.paint]
+                                                .into_iter()
+                                                .flatten()
+                                                .collect::<Vec<_>>()
+                                                .join(" / ");
+                                            view! {
+                                                <div class="user-paint-item audit-entry">
+                                                    <div class="history-meta">
+                                                        <strong>{format!("{} {}", entry.action.label(), subject)}</strong>
+                                                        <span class="hint">
+                                                            {format!("{} · {}", entry.created_at, entry.user_email)}
+                                                        </span>
+                                                        {entry.details.map(|d| view! { <code>{d}</code> })}
+                                                    </div>
+                                                </div>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </div>
+                            }
+                                .into_any(),
+                            Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+/// An empty text field clears an optional brand detail
+fn optional(s: String) -> Option<String> {
+    Some(s).filter(|s| !s.is_empty())
+}
+
+#[component]
+fn AdminPaintItem(
+    brand: String,
+    paint: AdminPaint,
+    set_refresh: WriteSignal<u32>,
+    set_error: WriteSignal<Option<String>>,
+) -> impl IntoView {
+    let (editing, set_editing) = signal(false);
+    let (name, set_name) = signal(paint.name.clone());
+    let (attributes, set_attributes) = signal(paint.attributes.clone());
+    let (pigments, set_pigments) = signal(paint.attributes.pigments.join(", "));
+
+    let done = move |result: Result<(), ServerFnError>| match result {
+        Ok(()) => set_refresh.update(|n| *n += 1),
+        Err(e) => set_error.set(Some(e.to_string())),
+    };
+
+    let save = {
+        let (brand, old_name) = (brand.clone(), paint.name.clone());
+        Action::new(move |_: &()| {
+            let (brand, old_name) = (brand.clone(), old_name.clone());
+            let attributes = PaintAttributes {
+                pigments: PaintAttributes::parse_pigments(&pigments.get()),
+                ..attributes.get()
+            };
+            let new_name = name.get();
+            async move {
+                set_error.set(None);
+                done(update_catalogue_paint(brand, old_name, new_name, attributes).await);
+            }
+        })
+    };
+    let recompute = {
+        let (brand, name) = (brand.clone(), paint.name.clone());
+        Action::new(move |_: &()| {
+            let (brand, name) = (brand.clone(), name.clone());
+            async move { done(recompute_paint_hex(brand, name).await.map(|_| ())) }
+        })
+    };
+    let retired = paint.retired_at.is_some();
+    let retire = {
+        let (brand, name) = (brand.clone(), paint.name.clone());
+        Action::new(move |_: &()| {
+            let (brand, name) = (brand.clone(), name.clone());
+            async move { done(set_paint_retired(brand, name, !retired).await) }
+        })
+    };
+
+    let summary = paint.attributes.summary();
+    let hex_differs = paint.curve_hex.is_some() && paint.curve_hex != paint.hex;
+    let detail = [
+        paint.hex.clone().unwrap_or_else(|| "no colour".to_string()),
+        paint
+            .hex_difference
+            .map(|d| format!("ΔE {:.1} from curve", d))
+            .unwrap_or_default(),
+        summary,
+        paint.retired_at.clone().map(|at| format!("retired {}", at)).unwrap_or_default(),
+        paint.curve_error.clone().unwrap_or_default(),
+    ]
+    .into_iter()
+    .filter(|s| !s.is_empty())
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+    view! {
+        <div class="user-paint-item" class:retired=retired>
+            <div
+                class="measurement-swatch"
+                title="Stored colour"
+                style=format!("background-color: {}", paint.hex.clone().unwrap_or_default())
+            ></div>
+            <div
+                class="measurement-swatch"
+                title="Colour from the curve"
+                style=format!("background-color: {}", paint.curve_hex.clone().unwrap_or_default())
+            ></div>
+            <div class="history-meta">
+                <strong>{paint.name.clone()}</strong>
+                <span class="hint">{detail}</span>
+            </div>
+            <div class="history-actions">
+                {hex_differs.then(|| view! {
+                    <button
+                        class="btn btn-small btn-secondary"
+                        on:click=move |_| { recompute.dispatch(()); }
+                        disabled=move || recompute.pending().get()
+                    >
+                        "Use Curve Colour"
+                    </button>
+                })}
+                <button class="btn btn-small btn-secondary" on:click=move |_| set_editing.update(|e| *e = !*e)>
+                    "Edit"
+                </button>
+                <button
+                    class="btn btn-small btn-secondary"
+                    on:click=move |_| { retire.dispatch(()); }
+                    disabled=move || retire.pending().get()
+                >
+                    {if retired { "Restore" } else { "Retire" }}
+                </button>
+            </div>
+        </div>
+        {move || editing.get().then(|| view! {
+            <div class="mix-filters admin-paint-editor">
+                <label class="filter-option">
+                    "Name"
+                    <input
+                        type="text"
+                        prop:value=move || name.get()
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                    />
+                </label>
+                <label class="filter-option">
+                    "Pigments"
+                    <input
+                        type="text"
+                        prop:value=move || pigments.get()
+                        on:input=move |ev| set_pigments.set(event_target_value(&ev))
+                    />
+                </label>
+                <label class="filter-option">
+                    "Opacity"
+                    <select on:change=move |ev| {
+                        let opacity = Opacity::from_code(&event_target_value(&ev));
+                        set_attributes.update(|a| a.opacity = opacity);
+                    }>
+                        <option value="" selected=move || attributes.get().opacity.is_none()>"Unknown"</option>
+                        {Opacity::all()
+                            .into_iter()
+                            .map(|o| view! {
+                                <option value=o.code() selected=move || attributes.get().opacity == Some(o)>
+                                    {o.label()}
+                                </option>
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="filter-option">
+                    "Lightfastness"
+                    <select on:change=move |ev| {
+                        let lightfastness = Lightfastness::from_code(&event_target_value(&ev));
+                        set_attributes.update(|a| a.lightfastness = lightfastness);
+                    }>
+                        <option value="" selected=move || attributes.get().lightfastness.is_none()>"Unknown"</option>
+                        {Lightfastness::all()
+                            .into_iter()
+                            .map(|lf| view! {
+                                <option value=lf.code() selected=move || attributes.get().lightfastness == Some(lf)>
+                                    {lf.code()}
+                                </option>
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="filter-option">
+                    "Series"
+                    <input
+                        type="number"
+                        min="1"
+                        max="9"
+                        prop:value=move || attributes.get().series.map(|s| s.to_string()).unwrap_or_default()
+                        on:input=move |ev| {
+                            let series = event_target_value(&ev).parse().ok();
+                            set_attributes.update(|a| a.series = series);
+                        }
+                    />
+                </label>
+                <label class="filter-option">
+                    "Drying"
+                    <select on:change=move |ev| {
+                        let drying_speed = DryingSpeed::from_code(&event_target_value(&ev));
+                        set_attributes.update(|a| a.drying_speed = drying_speed);
+                    }>
+                        <option value="" selected=move || attributes.get().drying_speed.is_none()>"Unknown"</option>
+                        {DryingSpeed::all()
+                            .into_iter()
+                            .map(|d| view! {
+                                <option value=d.code() selected=move || attributes.get().drying_speed == Some(d)>
+                                    {d.code()}
+                                </option>
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="filter-option">
+                    "Role"
+                    <select on:change=move |ev| {
+                        let role = PaintRole::from_code(&event_target_value(&ev));
+                        set_attributes.update(|a| a.role = role);
+                    }>
+                        <option value="" selected=move || attributes.get().role.is_none()>"None"</option>
+                        {PaintRole::all()
+                            .into_iter()
+                            .map(|r| view! {
+                                <option value=r.code() selected=move || attributes.get().role == Some(r)>
+                                    {r.label()}
+                                </option>
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <button
+                    class="btn primary"
+                    on:click=move |_| { save.dispatch(()); }
+                    disabled=move || save.pending().get() || name.get().trim().is_empty()
+                >
+                    "Save Paint"
+                </button>
+            </div>
+        })}
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use leptos::prelude::*;
+
+use crate::components::{AuthGuard, MixResultCard, PaletteSelector};
+use crate::models::{BatchMixResult, MixChoice};
+use crate::server_fns::{
+    batch_mix_status, start_batch_mix, BatchTarget, Palette, MAX_BATCH_TARGETS,
+};
+
+/// How often a running batch is polled for progress
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[component]
+pub fn BatchMixPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <BatchMixer/>
+        </AuthGuard>
+    }
+}
+
+/// One target per line, either `colour` or `label: colour`
+fn parse_targets(text: &str) -> Vec<BatchTarget> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(':') {
+            Some((label, colour)) => BatchTarget {
+                label: Some(label.trim().to_string()),
+                colour: colour.trim().to_string(),
+            },
+            None => BatchTarget {
+                label: None,
+                colour: line.to_string(),
+            },
+        })
+        .collect()
+}
+
+#[component]
+fn BatchMixer() -> impl IntoView {
+    let (targets_text, set_targets_text) = signal(String::new());
+    let palette = RwSignal::new(Option::<Palette>::None);
+    let (mix_choice, set_mix_choice) = signal(String::new());
+    Effect::new(move || {
+        if let Some(p) = palette.get() {
+            set_mix_choice.set(p.mix_choice);
+        }
+    });
+
+    let (job_id, set_job_id) = signal(Option::<String>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let start = Action::new(move |_: &()| {
+        let targets = parse_targets(&targets_text.get());
+        let palette_id = palette.get().map(|p| p.id);
+        let choice = Some(mix_choice.get()).filter(|c| !c.is_empty());
+        async move {
+            set_error.set(None);
+            match start_batch_mix(targets, None, palette_id, choice).await {
+                Ok(id) => set_job_id.set(Some(id)),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    // Re-poll the running batch on every tick until it has finished
+    let (tick, set_tick) = signal(0u32);
+    let progress = Resource::new(
+        move || (job_id.get(), tick.get()),
+        |(job_id, _)| async move {
+            match job_id {
+                Some(id) => Some(batch_mix_status(id).await),
+                None => None,
+            }
+        },
+    );
+    Effect::new(move || {
+        if let Some(Some(Ok(status))) = progress.get() {
+            if !status.finished {
+                set_timeout(move || set_tick.update(|t| *t += 1), POLL_INTERVAL);
+            }
+        }
+    });
+
+    let running = move || {
+        start.pending().get()
+            || matches!(progress.get(), Some(Some(Ok(status))) if !status.finished)
+    };
+    let target_count = move || parse_targets(&targets_text.get()).len();
+
+    view! {
+        <div class="batch-page">
+            <h1>"Batch Mix"</h1>
+            <p class="subtitle">"Find recipes for many target colours at once"</p>
+
+            <div class="settings-section">
+                <div class="form-group">
+                    <label>"Targets"</label>
+                    <textarea
+                        rows="10"
+                        placeholder="One per line, e.g.\n#c8553d\nSky: lab(72, -8, -24)\nShadow: slate grey\nLips: 5R 4/14"
+                        prop:value=move || targets_text.get()
+                        on:input=move |ev| set_targets_text.set(event_target_value(&ev))
+                    ></textarea>
+                    <p class="hint">
+                        "Hex, CSS names, lab(), lch(), xyz(), hsl(), oklab(), oklch() or Munsell such as 5R 4/14, optionally prefixed with a label. "
+                        {move || format!("{} of at most {} targets", target_count(), MAX_BATCH_TARGETS)}
+                    </p>
+                </div>
+                <div class="mix-filters">
+                    <label class="filter-option">
+                        "Palette"
+                        <PaletteSelector selected=palette/>
+                    </label>
+                    <label class="filter-option">
+                        "Strategy"
+                        <select on:change=move |ev| set_mix_choice.set(event_target_value(&ev))>
+                            {MixChoice::all()
+                                .into_iter()
+                                .map(|choice| {
+                                    let value = choice.as_str();
+                                    view! {
+                                        <option value=value selected=move || mix_choice.get() == value>
+                                            {value}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                    </label>
+                    <button
+                        class="btn primary"
+                        on:click=move |_| { start.dispatch(()); }
+                        disabled=move || running() || target_count() == 0
+                    >
+                        "Mix All"
+                    </button>
+                </div>
+                {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            </div>
+
+            {move || {
+                progress
+                    .get()
+                    .flatten()
+                    .map(|result| match result {
+                        Ok(status) if !status.finished => {
+                            let percent = status.completed * 100 / status.total.max(1);
+                            view! {
+                                <div class="batch-progress">
+                                    <div class="batch-progress-bar" style=format!("width: {}%", percent)></div>
+                                </div>
+                                <p class="hint">
+                                    {format!("Mixed {} of {} targets...", status.completed, status.total)}
+                                </p>
+                            }
+                                .into_any()
+                        }
+                        Ok(status) => match status.error {
+                            Some(e) => view! { <p class="error">{e}</p> }.into_any(),
+                            None => view! {
+                                <div class="history-list">
+                                    {status
+                                        .results
+                                        .into_iter()
+                                        .map(|result| view! { <BatchResultCard result=result/> })
+                                        .collect_view()}
+                                </div>
+                            }
+                                .into_any(),
+                        },
+                        Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    })
+            }}
+        </div>
+    }
+}
+
+#[component]
+fn BatchResultCard(result: BatchMixResult) -> impl IntoView {
+    let swatch = result
+        .target_hex
+        .clone()
+        .map(|hex| format!("background-color: {}", hex))
+        .unwrap_or_default();
+
+    view! {
+        <div class="history-card">
+            <div class="history-header">
+                <div class="history-swatch" style=swatch title=result.target_hex.clone()></div>
+                <div class="history-meta">
+                    <strong>{result.label.clone()}</strong>
+                    <span class="hint">{result.target_hex.clone()}</span>
+                    {result.error.clone().map(|e| view! { <p class="error">{e}</p> })}
+                </div>
+            </div>
+            <div class="mix-results">
+                {result
+                    .results
+                    .into_iter()
+                    .next()
+                    .map(|mix| view! { <MixResultCard mix=mix rank=1 /> })}
+            </div>
+        </div>
+    }
+    .into_any()
+}
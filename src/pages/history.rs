@@ -0,0 +1,229 @@
+use leptos::prelude::*;
+
+use crate::components::{AuthGuard, MixResultCard, SheetExport, SwatchExport};
+use crate::server_fns::{
+    delete_history_entry, export_history_sheet, export_recipe_swatches, list_mix_history,
+    rerun_recipe, update_recipe, HistoryEntry, HistoryKind, SheetFormat, SwatchFormat,
+};
+
+#[component]
+pub fn HistoryPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <HistoryList/>
+        </AuthGuard>
+    }
+}
+
+#[component]
+fn HistoryList() -> impl IntoView {
+    let (starred_only, set_starred_only) = signal(false);
+    // Hex colour to sort by proximity; empty means newest first
+    let (near_hex, set_near_hex) = signal(String::new());
+    let (search_hex, set_search_hex) = signal("#808080".to_string());
+
+    let history = Resource::new(
+        move || (starred_only.get(), near_hex.get()),
+        |(starred, near)| async move {
+            let near = (!near.is_empty()).then_some(near);
+            list_mix_history(starred, near, None).await
+        },
+    );
+
+    let export = Action::new(move |format: &SheetFormat| {
+        export_history_sheet(starred_only.get_untracked(), *format)
+    });
+
+    let export_swatches = Action::new(|format: &SwatchFormat| export_recipe_swatches(*format));
+
+    view! {
+        <div class="history-page">
+            <h1>"Mix History"</h1>
+            <p class="subtitle">"Previous mixes and saved recipes"</p>
+
+            <div class="mix-filters">
+                <label class="filter-option">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || starred_only.get()
+                        on:change=move |ev| set_starred_only.set(event_target_checked(&ev))
+                    />
+                    "Starred recipes only"
+                </label>
+                <label class="filter-option">
+                    "Near colour"
+                    <input
+                        type="color"
+                        prop:value=move || search_hex.get()
+                        on:input=move |ev| set_search_hex.set(event_target_value(&ev))
+                    />
+                </label>
+                <button class="btn btn-small" on:click=move |_| set_near_hex.set(search_hex.get())>
+                    "Find Similar"
+                </button>
+                <button
+                    class="btn btn-small btn-secondary"
+                    on:click=move |_| set_near_hex.set(String::new())
+                    disabled=move || near_hex.get().is_empty()
+                >
+                    "Clear"
+                </button>
+                <SheetExport export=export/>
+            </div>
+
+            <div class="mix-filters">
+                <span class="filter-option">"Saved recipes as swatches"</span>
+                <SwatchExport export=export_swatches/>
+            </div>
+
+            <Suspense fallback=move || view! { <p>"Loading history..."</p> }>
+                {move || {
+                    history
+                        .get()
+                        .map(|result| {
+                            match result {
+                                Ok(entries) if entries.is_empty() => {
+                                    view! { <p class="hint">"No mixes yet"</p> }.into_any()
+                                }
+                                Ok(entries) => {
+                                    view! {
+                                        <div class="history-list">
+                                            {entries
+                                                .into_iter()
+                                                .map(|entry| {
+                                                    view! {
+                                                        <HistoryCard
+                                                            entry=entry
+                                                            on_change=move || history.refetch()
+                                                        />
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </div>
+                                    }
+                                        .into_any()
+                                }
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn HistoryCard(entry: HistoryEntry, on_change: impl Fn() + Copy + Send + Sync + 'static) -> impl IntoView {
+    let id = StoredValue::new(entry.id.clone());
+    let (starred, set_starred) = signal(entry.starred);
+    let (name, set_name) = signal(entry.name.clone().unwrap_or_default());
+    let (results, set_results) = signal(entry.results.clone());
+    let (palette_name, set_palette_name) = signal(entry.palette_name.clone());
+    let (status, set_status) = signal(Option::<String>::None);
+
+    let save_recipe = Action::new(move |starred: &bool| {
+        let starred = *starred;
+        let name = name.get_untracked();
+        async move {
+            match update_recipe(id.get_value(), starred, Some(name)).await {
+                Ok(()) => {
+                    set_starred.set(starred);
+                    set_status.set(Some("Saved".to_string()));
+                }
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    let rerun = Action::new(move |_: &()| async move {
+        set_status.set(Some("Re-running...".to_string()));
+        match rerun_recipe(id.get_value()).await {
+            Ok(updated) => {
+                set_results.set(updated.results);
+                set_palette_name.set(updated.palette_name);
+                set_status.set(None);
+            }
+            Err(e) => set_status.set(Some(format!("Error: {}", e))),
+        }
+    });
+
+    let delete = Action::new(move |_: &()| async move {
+        match delete_history_entry(id.get_value()).await {
+            Ok(()) => on_change(),
+            Err(e) => set_status.set(Some(format!("Error: {}", e))),
+        }
+    });
+
+    let kind_label = match entry.kind {
+        HistoryKind::Find => "Target mix",
+        HistoryKind::Test => "Test mix",
+        HistoryKind::Measured => "Measured mix",
+    };
+    let can_rerun = entry.kind == HistoryKind::Find;
+
+    view! {
+        <div class="history-card" class:starred=move || starred.get()>
+            <div class="history-header">
+                <div
+                    class="history-swatch"
+                    style=format!("background-color: {}", entry.target_hex)
+                    title=entry.target_hex.clone()
+                ></div>
+                <div class="history-meta">
+                    <input
+                        type="text"
+                        class="select-input"
+                        placeholder="Recipe name"
+                        prop:value=move || name.get()
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                        on:change=move |_| { save_recipe.dispatch(starred.get_untracked()); }
+                    />
+                    <span class="hint">
+                        {kind_label}" · "{entry.target_hex.clone()}" · "{entry.created_at.clone()}
+                        {move || palette_name.get().map(|p| format!(" · {}", p))}
+                        {entry.mix_choice.clone().map(|c| format!(" · {}", c))}
+                        {entry.distance.map(|d| format!(" · ΔE {:.1}", d))}
+                    </span>
+                </div>
+                <div class="history-actions">
+                    <button
+                        class="btn btn-small"
+                        title="Star this recipe"
+                        on:click=move |_| { save_recipe.dispatch(!starred.get_untracked()); }
+                    >
+                        {move || if starred.get() { "★" } else { "☆" }}
+                    </button>
+                    {can_rerun
+                        .then(|| {
+                            view! {
+                                <button
+                                    class="btn btn-small"
+                                    on:click=move |_| { rerun.dispatch(()); }
+                                    disabled=move || rerun.pending().get()
+                                >
+                                    "Re-run"
+                                </button>
+                            }
+                        })}
+                    <button
+                        class="btn btn-small btn-secondary"
+                        on:click=move |_| { delete.dispatch(()); }
+                    >
+                        "Delete"
+                    </button>
+                </div>
+            </div>
+            {move || status.get().map(|s| view! { <p class="hint">{s}</p> })}
+            <div class="mix-results">
+                {move || {
+                    results
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, mix)| view! { <MixResultCard mix=mix rank=i + 1 /> })
+                        .collect_view()
+                }}
+            </div>
+        </div>
+    }
+}
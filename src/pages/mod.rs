@@ -1,19 +1,33 @@
+mod admin;
+mod batch;
 mod home;
 mod login;
 mod register;
 mod verify_email;
 mod forgot_password;
+mod history;
+mod my_paints;
 mod reset_password;
+mod project;
+mod project_summary;
+mod projects;
 mod settings;
 mod target_mix;
 mod test_mix;
 
+pub use admin::AdminPage;
+pub use batch::BatchMixPage;
 pub use home::HomePage;
 pub use login::LoginPage;
 pub use register::RegisterPage;
 pub use verify_email::VerifyEmailPage;
 pub use forgot_password::ForgotPasswordPage;
+pub use history::HistoryPage;
+pub use my_paints::MyPaintsPage;
 pub use reset_password::ResetPasswordPage;
+pub use project::ProjectPage;
+pub use project_summary::ProjectSummaryPage;
+pub use projects::ProjectsPage;
 pub use settings::SettingsPage;
 pub use target_mix::TargetMixPage;
 pub use test_mix::TestMixPage;
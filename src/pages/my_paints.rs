@@ -0,0 +1,282 @@
+use leptos::prelude::*;
+use leptos::web_sys;
+
+use crate::components::{read_file, AuthGuard};
+use crate::models::{Opacity, PaintAttributes, PaintRole};
+use crate::server_fns::{
+    add_user_paint, delete_user_paint, import_measurements, list_user_paints, MeasuredTarget,
+    UserPaint,
+};
+
+#[component]
+pub fn MyPaintsPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <MyPaints/>
+        </AuthGuard>
+    }
+}
+
+#[component]
+fn MyPaints() -> impl IntoView {
+    let (refresh, set_refresh) = signal(0u32);
+    let paints = Resource::new(move || refresh.get(), |_| list_user_paints());
+
+    let (name, set_name) = signal(String::new());
+    let (colour, set_colour) = signal("#808080".to_string());
+    let (pigments, set_pigments) = signal(String::new());
+    let (opacity, set_opacity) = signal(Option::<Opacity>::None);
+    // None detects the role from the paint's curve
+    let (role, set_role) = signal(Option::<PaintRole>::None);
+    // Samples from an uploaded measurement file, and the one to store
+    let (measurements, set_measurements) = signal(Vec::<MeasuredTarget>::new());
+    let (measured, set_measured) = signal(Option::<MeasuredTarget>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let import = Action::new(move |(filename, data_url): &(String, String)| {
+        let (filename, data_url) = (filename.clone(), data_url.clone());
+        async move {
+            set_error.set(None);
+            match import_measurements(filename, data_url).await {
+                Ok(samples) => set_measurements.set(samples),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+    let handle_upload = move |ev: web_sys::Event| {
+        read_file(&ev, move |filename, data_url| {
+            import.dispatch((filename, data_url));
+        });
+    };
+    let select_measurement = move |sample: MeasuredTarget| {
+        if name.get_untracked().trim().is_empty() {
+            set_name.set(sample.name.clone());
+        }
+        set_colour.set(sample.hex.clone());
+        set_measured.set(Some(sample));
+    };
+
+    let add = Action::new(move |_: &()| {
+        let attributes = PaintAttributes {
+            opacity: opacity.get(),
+            pigments: PaintAttributes::parse_pigments(&pigments.get()),
+            role: role.get(),
+            ..Default::default()
+        };
+        let reflectance = measured.get().map(|m| m.reflectance);
+        let (paint_name, paint_colour) = (name.get(), colour.get());
+        async move {
+            set_error.set(None);
+            match add_user_paint(paint_name, paint_colour, reflectance, attributes).await {
+                Ok(_) => {
+                    set_name.set(String::new());
+                    set_pigments.set(String::new());
+                    set_measured.set(None);
+                    set_refresh.update(|n| *n += 1);
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    let delete = Action::new(move |id: &String| {
+        let id = id.clone();
+        async move {
+            match delete_user_paint(id).await {
+                Ok(()) => set_refresh.update(|n| *n += 1),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    view! {
+        <div class="my-paints-page">
+            <h1>"My Paints"</h1>
+            <p class="subtitle">
+                "Add handmade or discontinued tubes and mix them alongside the built-in brands. They appear as the My Paints brand in your palettes."
+            </p>
+
+            <div class="settings-section">
+                <h2>"Add a Paint"</h2>
+                <div class="input-group">
+                    <label for="paint-name">"Name"</label>
+                    <input
+                        type="text"
+                        id="paint-name"
+                        class="select-input"
+                        prop:value=move || name.get()
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                    />
+                </div>
+                <label class="image-upload-area">
+                    <input type="file" accept=".txt,.cgats,.it8,.cxf,.csv" on:change=handle_upload/>
+                    <div class="upload-placeholder">
+                        <span class="upload-icon">"📈"</span>
+                        <span>"Upload a measured drawdown"</span>
+                        <span class="upload-hint">
+                            "CGATS, CxF3 or CSV reflectance covering 400–700 nm. Measured paints mix most accurately."
+                        </span>
+                    </div>
+                </label>
+                {move || import.pending().get().then(|| view! { <p class="hint">"Reading measurements..."</p> })}
+                <div class="measurement-list">
+                    <For
+                        each=move || measurements.get()
+                        key=|m| m.name.clone()
+                        children=move |m| {
+                            let sample_name = m.name.clone();
+                            let hex = m.hex.clone();
+                            let selected_name = sample_name.clone();
+                            view! {
+                                <button
+                                    class="measurement-item"
+                                    class:active=move || {
+                                        measured.get().is_some_and(|s| s.name == selected_name)
+                                    }
+                                    on:click=move |_| select_measurement(m.clone())
+                                >
+                                    <span
+                                        class="measurement-swatch"
+                                        style=format!("background-color: {}", hex)
+                                    ></span>
+                                    {sample_name}
+                                </button>
+                            }
+                        }
+                    />
+                </div>
+                <div class="input-group">
+                    <label for="paint-colour">"Or pick its colour"</label>
+                    <input
+                        type="color"
+                        id="paint-colour"
+                        prop:value=move || colour.get()
+                        on:input=move |ev| {
+                            set_colour.set(event_target_value(&ev));
+                            set_measured.set(None);
+                        }
+                    />
+                    <p class="hint">
+                        {move || {
+                            if measured.get().is_some() {
+                                "Using the measured curve"
+                            } else {
+                                "A reflectance curve is reconstructed from the colour, which is only an estimate of how the paint mixes"
+                            }
+                        }}
+                    </p>
+                </div>
+                <div class="mix-filters">
+                    <label class="filter-option">
+                        "Pigments"
+                        <input
+                            type="text"
+                            placeholder="PW6, PB29"
+                            prop:value=move || pigments.get()
+                            on:input=move |ev| set_pigments.set(event_target_value(&ev))
+                        />
+                    </label>
+                    <label class="filter-option">
+                        "Opacity"
+                        <select on:change=move |ev| {
+                            set_opacity.set(Opacity::from_code(&event_target_value(&ev)))
+                        }>
+                            <option value="" selected=move || opacity.get().is_none()>"Unknown"</option>
+                            {Opacity::all()
+                                .into_iter()
+                                .map(|o| {
+                                    view! {
+                                        <option value=o.code() selected=move || opacity.get() == Some(o)>
+                                            {o.label()}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                    </label>
+                    <label class="filter-option">
+                        "Role"
+                        <select on:change=move |ev| {
+                            set_role.set(PaintRole::from_code(&event_target_value(&ev)))
+                        }>
+                            <option value="" selected=move || role.get().is_none()>"Detect"</option>
+                            {PaintRole::all()
+                                .into_iter()
+                                .map(|r| {
+                                    view! {
+                                        <option value=r.code() selected=move || role.get() == Some(r)>
+                                            {r.label()}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                    </label>
+                    <button
+                        class="btn primary"
+                        on:click=move |_| { add.dispatch(()); }
+                        disabled=move || add.pending().get() || name.get().trim().is_empty()
+                    >
+                        "Add Paint"
+                    </button>
+                </div>
+                {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            </div>
+
+            <div class="settings-section">
+                <h2>"Your Paints"</h2>
+                <Suspense fallback=move || view! { <p>"Loading paints..."</p> }>
+                    {move || {
+                        paints
+                            .get()
+                            .map(|result| match result {
+                                Ok(list) if list.is_empty() => {
+                                    view! { <p class="hint">"You have not added any paints yet"</p> }
+                                        .into_any()
+                                }
+                                Ok(list) => view! {
+                                    <div class="user-paint-list">
+                                        {list
+                                            .into_iter()
+                                            .map(|paint| view! { <UserPaintItem paint=paint delete=delete/> })
+                                            .collect_view()}
+                                    </div>
+                                }
+                                    .into_any(),
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn UserPaintItem(paint: UserPaint, delete: Action<String, ()>) -> impl IntoView {
+    let summary = paint.attributes.summary();
+    let id = paint.id.clone();
+
+    view! {
+        <div class="user-paint-item">
+            <div class="measurement-swatch" style=format!("background-color: {}", paint.hex)></div>
+            <div class="history-meta">
+                <strong>{paint.name}</strong>
+                <span class="hint">
+                    {if summary.is_empty() {
+                        paint.source.label().to_string()
+                    } else {
+                        format!("{} · {}", paint.source.label(), summary)
+                    }}
+                </span>
+            </div>
+            <button
+                class="btn btn-small btn-secondary"
+                on:click=move |_| { delete.dispatch(id.clone()); }
+            >
+                "Delete"
+            </button>
+        </div>
+    }
+    .into_any()
+}
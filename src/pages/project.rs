@@ -0,0 +1,410 @@
+use leptos::prelude::*;
+use leptos::web_sys;
+use leptos_router::components::A;
+use leptos_router::hooks::{use_navigate, use_params_map};
+
+use crate::components::{
+    read_file, read_image_file, sample_image_pixel, AuthGuard, MixResultCard, PaletteSelector,
+    SheetExport,
+};
+use crate::models::{parse_hex, to_hex, MixChoice};
+use crate::server_fns::{
+    add_project_target, delete_project, delete_project_target, export_project_sheet, get_project,
+    import_swatches, set_project_image, update_project, update_project_target, Palette, Project, ProjectTarget, SheetFormat,
+};
+
+/// Longest side of stored reference images, in pixels
+const REFERENCE_IMAGE_SIZE: u32 = 1024;
+
+#[component]
+pub fn ProjectPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <ProjectBoard/>
+        </AuthGuard>
+    }
+}
+
+#[component]
+fn ProjectBoard() -> impl IntoView {
+    let params = use_params_map();
+    let project_id = move || params.read().get("id").unwrap_or_default();
+    let project = Resource::new(project_id, get_project);
+
+    view! {
+        <div class="project-page">
+            <Suspense fallback=move || view! { <p>"Loading project..."</p> }>
+                {move || {
+                    project
+                        .get()
+                        .map(|result| {
+                            match result {
+                                Ok(p) => view! { <ProjectEditor project=p/> }.into_any(),
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn ProjectEditor(project: Project) -> impl IntoView {
+    let id = StoredValue::new(project.id.clone());
+    let (name, set_name) = signal(project.name.clone());
+    let (notes, set_notes) = signal(project.notes.clone());
+    let (image, set_image) = signal(project.reference_image.clone());
+    let (targets, set_targets) = signal(project.targets.clone());
+    let (status, set_status) = signal(Option::<String>::None);
+
+    // Next target to add, picked from the reference image or the colour input
+    let (pick, set_pick) = signal([128u8, 128, 128]);
+    let (label, set_label) = signal(String::new());
+    let palette = RwSignal::new(Option::<Palette>::None);
+    let (mix_choice, set_mix_choice) = signal(String::new());
+    Effect::new(move || {
+        if let Some(p) = palette.get() {
+            set_mix_choice.set(p.mix_choice);
+        }
+    });
+
+    let save = Action::new(move |_: &()| {
+        let name = name.get();
+        let notes = notes.get();
+        async move {
+            match update_project(id.get_value(), name, notes).await {
+                Ok(()) => set_status.set(Some("Project saved!".to_string())),
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    let navigate = use_navigate();
+    let delete = Action::new(move |_: &()| {
+        let navigate = navigate.clone();
+        async move {
+            match delete_project(id.get_value()).await {
+                Ok(()) => navigate("/projects", Default::default()),
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    let upload = Action::new(move |data_url: &Option<String>| {
+        let data_url = data_url.clone();
+        async move {
+            match set_project_image(id.get_value(), data_url.clone()).await {
+                Ok(()) => set_image.set(data_url),
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    let add_target = Action::new(move |_: &()| {
+        let [r, g, b] = pick.get();
+        let label = label.get();
+        let palette_id = palette.get().map(|p| p.id);
+        let choice = Some(mix_choice.get()).filter(|c| !c.is_empty());
+        async move {
+            set_status.set(Some("Finding mixes...".to_string()));
+            match add_project_target(id.get_value(), label, r, g, b, None, palette_id, choice).await {
+                Ok(target) => {
+                    set_targets.update(|list| list.push(target));
+                    set_label.set(String::new());
+                    set_status.set(None);
+                }
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    // Mix every colour from an imported GPL/ASE palette as a new target
+    let import_targets = Action::new(move |(filename, data_url): &(String, String)| {
+        let (filename, data_url) = (filename.clone(), data_url.clone());
+        let palette_id = palette.get().map(|p| p.id);
+        let choice = Some(mix_choice.get()).filter(|c| !c.is_empty());
+        async move {
+            let swatches = match import_swatches(filename, data_url).await {
+                Ok(swatches) => swatches,
+                Err(e) => return set_status.set(Some(format!("Error: {}", e))),
+            };
+            let total = swatches.len();
+            for (i, swatch) in swatches.into_iter().enumerate() {
+                set_status.set(Some(format!("Mixing {} of {}...", i + 1, total)));
+                let Some([r, g, b]) = parse_hex(&swatch.hex) else { continue };
+                match add_project_target(
+                    id.get_value(),
+                    swatch.name,
+                    r,
+                    g,
+                    b,
+                    None,
+                    palette_id.clone(),
+                    choice.clone(),
+                )
+                .await
+                {
+                    Ok(target) => set_targets.update(|list| list.push(target)),
+                    Err(e) => return set_status.set(Some(format!("Error: {}", e))),
+                }
+            }
+            set_status.set(Some(format!("Imported {} target(s)", total)));
+        }
+    });
+
+    let export = Action::new(move |format: &SheetFormat| export_project_sheet(id.get_value(), *format));
+
+    let handle_image_upload = move |ev: web_sys::Event| {
+        read_image_file(&ev, Some(REFERENCE_IMAGE_SIZE), move |data_url| {
+            upload.dispatch(Some(data_url));
+        });
+    };
+
+    let handle_swatch_import = move |ev: web_sys::Event| {
+        read_file(&ev, move |filename, data_url| {
+            import_targets.dispatch((filename, data_url));
+        });
+    };
+
+    let handle_image_click = move |ev: web_sys::MouseEvent| {
+        if let Some(rgb) = sample_image_pixel(&ev) {
+            set_pick.set(rgb);
+        }
+    };
+
+    view! {
+        <div class="page-header">
+            <input
+                type="text"
+                class="project-title"
+                prop:value=move || name.get()
+                on:input=move |ev| set_name.set(event_target_value(&ev))
+            />
+            <div class="settings-actions">
+                <button class="btn primary" on:click=move |_| { save.dispatch(()); }>"Save"</button>
+                <A href=move || format!("/projects/{}/summary", id.get_value()) attr:class="btn">
+                    "Printable Summary"
+                </A>
+                <SheetExport export=export/>
+                <button class="btn btn-secondary" on:click=move |_| { delete.dispatch(()); }>
+                    "Delete Project"
+                </button>
+                {move || status.get().map(|s| view! { <span class="save-status">{s}</span> })}
+            </div>
+        </div>
+
+        <div class="project-layout">
+            <div class="settings-section">
+                <h2>"Reference Image"</h2>
+                {move || {
+                    image
+                        .get()
+                        .map(|src| {
+                            view! {
+                                <img
+                                    class="reference-image"
+                                    src=src
+                                    title="Click to pick a target colour"
+                                    on:click=handle_image_click
+                                />
+                            }
+                        })
+                }}
+                <div class="mix-filters">
+                    <input type="file" accept="image/*" on:change=handle_image_upload/>
+                    <button
+                        class="btn btn-small btn-secondary"
+                        on:click=move |_| { upload.dispatch(None); }
+                        disabled=move || image.get().is_none()
+                    >
+                        "Remove Image"
+                    </button>
+                </div>
+
+                <h2>"Add Target"</h2>
+                <div class="mix-filters">
+                    <input
+                        type="color"
+                        prop:value=move || to_hex(pick.get())
+                        on:input=move |ev| {
+                            if let Some(rgb) = parse_hex(&event_target_value(&ev)) {
+                                set_pick.set(rgb);
+                            }
+                        }
+                    />
+                    <span class="colour-value">{move || to_hex(pick.get())}</span>
+                    <input
+                        type="text"
+                        class="select-input palette-select"
+                        placeholder="Label, e.g. cheek shadow"
+                        prop:value=move || label.get()
+                        on:input=move |ev| set_label.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="mix-filters">
+                    <label class="filter-option">
+                        "Palette"
+                        <PaletteSelector selected=palette/>
+                    </label>
+                    <label class="filter-option">
+                        "Strategy"
+                        <select on:change=move |ev| set_mix_choice.set(event_target_value(&ev))>
+                            {MixChoice::all()
+                                .into_iter()
+                                .map(|choice| {
+                                    let value = choice.as_str();
+                                    view! {
+                                        <option value=value selected=move || mix_choice.get() == value>
+                                            {value}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                    </label>
+                    <button
+                        class="btn primary"
+                        on:click=move |_| { add_target.dispatch(()); }
+                        disabled=move || add_target.pending().get()
+                    >
+                        "Add Target"
+                    </button>
+                </div>
+
+                <div class="mix-filters">
+                    <label class="filter-option">
+                        "Import GPL/ASE palette as targets"
+                        <input
+                            type="file"
+                            accept=".gpl,.ase"
+                            on:change=handle_swatch_import
+                            disabled=move || import_targets.pending().get()
+                        />
+                    </label>
+                </div>
+
+                <h2>"Notes"</h2>
+                <textarea
+                    class="project-notes"
+                    prop:value=move || notes.get()
+                    on:input=move |ev| set_notes.set(event_target_value(&ev))
+                ></textarea>
+            </div>
+
+            <div class="settings-section">
+                <h2>"Targets"</h2>
+                {move || targets.get().is_empty().then(|| view! { <p class="hint">"No targets yet"</p> })}
+                <div class="history-list">
+                    <For
+                        each=move || targets.get()
+                        key=|t| t.id.clone()
+                        children=move |t| {
+                            view! {
+                                <TargetCard
+                                    target=t
+                                    on_delete=move |target_id: String| {
+                                        set_targets.update(|list| list.retain(|t| t.id != target_id));
+                                    }
+                                />
+                            }
+                        }
+                    />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn TargetCard(
+    target: ProjectTarget,
+    on_delete: impl Fn(String) + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let id = StoredValue::new(target.id.clone());
+    let (label, set_label) = signal(target.label.clone());
+    let (notes, set_notes) = signal(target.notes.clone());
+    let (chosen, set_chosen) = signal(target.chosen);
+    let (status, set_status) = signal(Option::<String>::None);
+
+    let save = Action::new(move |_: &()| {
+        let label = label.get_untracked();
+        let notes = notes.get_untracked();
+        let chosen = chosen.get_untracked();
+        async move {
+            if let Err(e) = update_project_target(id.get_value(), label, chosen, notes).await {
+                set_status.set(Some(format!("Error: {}", e)));
+            }
+        }
+    });
+
+    let delete = Action::new(move |_: &()| async move {
+        match delete_project_target(id.get_value()).await {
+            Ok(()) => on_delete(id.get_value()),
+            Err(e) => set_status.set(Some(format!("Error: {}", e))),
+        }
+    });
+
+    view! {
+        <div class="history-card">
+            <div class="history-header">
+                <div
+                    class="history-swatch"
+                    style=format!("background-color: {}", target.target_hex)
+                    title=target.target_hex.clone()
+                ></div>
+                <div class="history-meta">
+                    <input
+                        type="text"
+                        class="select-input"
+                        prop:value=move || label.get()
+                        on:input=move |ev| set_label.set(event_target_value(&ev))
+                        on:change=move |_| { save.dispatch(()); }
+                    />
+                    <span class="hint">
+                        {target.target_hex.clone()}
+                        {target.palette_name.clone().map(|p| format!(" · {}", p))}
+                        {target.mix_choice.clone().map(|c| format!(" · {}", c))}
+                    </span>
+                </div>
+                <div class="history-actions">
+                    <button class="btn btn-small btn-secondary" on:click=move |_| { delete.dispatch(()); }>
+                        "Delete"
+                    </button>
+                </div>
+            </div>
+            {move || status.get().map(|s| view! { <p class="hint">{s}</p> })}
+            <textarea
+                class="project-notes"
+                placeholder="Notes for this target"
+                prop:value=move || notes.get()
+                on:input=move |ev| set_notes.set(event_target_value(&ev))
+                on:change=move |_| { save.dispatch(()); }
+            ></textarea>
+            <div class="mix-results">
+                {target
+                    .results
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, mix)| {
+                        view! {
+                            <div class="project-recipe" class:chosen=move || chosen.get().unwrap_or(0) == i>
+                                <MixResultCard mix=mix rank=i + 1/>
+                                <button
+                                    class="btn btn-small"
+                                    on:click=move |_| {
+                                        set_chosen.set(Some(i));
+                                        save.dispatch(());
+                                    }
+                                >
+                                    {move || if chosen.get().unwrap_or(0) == i { "Chosen" } else { "Use This Recipe" }}
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </div>
+    }
+    .into_any()
+}
@@ -0,0 +1,132 @@
+use leptos::prelude::*;
+use leptos_router::components::A;
+use leptos_router::hooks::use_params_map;
+
+use crate::components::{AuthGuard, SheetExport};
+use crate::server_fns::{export_project_sheet, get_project, Project, SheetFormat};
+
+#[component]
+pub fn ProjectSummaryPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <ProjectSummarySheet/>
+        </AuthGuard>
+    }
+}
+
+#[component]
+fn ProjectSummarySheet() -> impl IntoView {
+    let params = use_params_map();
+    let project_id = move || params.read().get("id").unwrap_or_default();
+    let project = Resource::new(project_id, get_project);
+
+    view! {
+        <div class="project-summary">
+            <Suspense fallback=move || view! { <p>"Loading project..."</p> }>
+                {move || {
+                    project
+                        .get()
+                        .map(|result| {
+                            match result {
+                                Ok(p) => view! { <SummaryContent project=p/> }.into_any(),
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn SummaryContent(project: Project) -> impl IntoView {
+    let id = StoredValue::new(project.id.clone());
+    let export = Action::new(move |format: &SheetFormat| export_project_sheet(id.get_value(), *format));
+
+    let print = move |_| {
+        #[cfg(feature = "hydrate")]
+        if let Some(window) = leptos::web_sys::window() {
+            let _ = window.print();
+        }
+    };
+
+    view! {
+        <div class="settings-actions no-print">
+            <A href=format!("/projects/{}", project.id) attr:class="btn btn-secondary">
+                "Back to Project"
+            </A>
+            <button class="btn primary" on:click=print>"Print"</button>
+            <SheetExport export=export/>
+        </div>
+
+        <h1>{project.name.clone()}</h1>
+        <p class="hint">"Updated "{project.updated_at.clone()}</p>
+
+        {project
+            .reference_image
+            .clone()
+            .map(|src| view! { <img class="summary-image" src=src/> })}
+
+        {(!project.notes.is_empty()).then(|| view! { <p class="summary-notes">{project.notes.clone()}</p> })}
+
+        <table class="summary-table">
+            <thead>
+                <tr>
+                    <th>"Target"</th>
+                    <th>"Recipe"</th>
+                    <th>"ΔE"</th>
+                    <th>"Notes"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {project
+                    .targets
+                    .iter()
+                    .map(|t| {
+                        let recipe = t.recipe().cloned();
+                        let error = recipe.as_ref().map(|m| format!("{:.2}", m.error)).unwrap_or_default();
+                        view! {
+                            <tr>
+                                <td>
+                                    <div class="summary-target">
+                                        <span
+                                            class="paint-swatch"
+                                            style=format!("background-color: {}", t.target_hex)
+                                        ></span>
+                                        <span>{t.label.clone()}</span>
+                                        <span class="hint">{t.target_hex.clone()}</span>
+                                    </div>
+                                </td>
+                                <td>
+                                    {recipe
+                                        .map(|mix| {
+                                            let total: f64 = mix.weights.iter().sum();
+                                            mix.paints
+                                                .iter()
+                                                .zip(mix.weights.iter())
+                                                .enumerate()
+                                                .map(|(i, (name, weight))| {
+                                                    let brand = mix.brands.get(i).cloned().unwrap_or_default();
+                                                    view! {
+                                                        <div class="paint-row">
+                                                            <span class="paint-name">{name.clone()}</span>
+                                                            <span class="paint-brand">{brand}</span>
+                                                            <span class="paint-percentage">
+                                                                {(weight / total * 100.0).round()}"%"
+                                                            </span>
+                                                        </div>
+                                                    }
+                                                })
+                                                .collect_view()
+                                        })}
+                                </td>
+                                <td>{error}</td>
+                                <td>{t.notes.clone()}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()}
+            </tbody>
+        </table>
+    }
+}
@@ -0,0 +1,94 @@
+use leptos::prelude::*;
+use leptos_router::components::A;
+use leptos_router::hooks::use_navigate;
+
+use crate::components::AuthGuard;
+use crate::server_fns::{create_project, list_projects};
+
+#[component]
+pub fn ProjectsPage() -> impl IntoView {
+    view! {
+        <AuthGuard>
+            <ProjectList/>
+        </AuthGuard>
+    }
+}
+
+#[component]
+fn ProjectList() -> impl IntoView {
+    let projects = Resource::new(|| (), |_| list_projects());
+    let (new_name, set_new_name) = signal(String::new());
+    let (error, set_error) = signal(Option::<String>::None);
+    let navigate = use_navigate();
+
+    let create = Action::new(move |_: &()| {
+        let name = new_name.get();
+        let navigate = navigate.clone();
+        async move {
+            match create_project(name).await {
+                Ok(id) => navigate(&format!("/projects/{}", id), Default::default()),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    view! {
+        <div class="projects-page">
+            <h1>"Projects"</h1>
+            <p class="subtitle">"Group target colours and recipes for each painting"</p>
+
+            <div class="mix-filters">
+                <input
+                    type="text"
+                    class="select-input palette-select"
+                    placeholder="New project name"
+                    prop:value=move || new_name.get()
+                    on:input=move |ev| set_new_name.set(event_target_value(&ev))
+                />
+                <button
+                    class="btn primary"
+                    on:click=move |_| { create.dispatch(()); }
+                    disabled=move || new_name.get().trim().is_empty()
+                >
+                    "Create Project"
+                </button>
+                {move || error.get().map(|e| view! { <span class="error">{e}</span> })}
+            </div>
+
+            <Suspense fallback=move || view! { <p>"Loading projects..."</p> }>
+                {move || {
+                    projects
+                        .get()
+                        .map(|result| {
+                            match result {
+                                Ok(list) if list.is_empty() => {
+                                    view! { <p class="hint">"No projects yet"</p> }.into_any()
+                                }
+                                Ok(list) => {
+                                    view! {
+                                        <div class="project-list">
+                                            {list
+                                                .into_iter()
+                                                .map(|p| {
+                                                    view! {
+                                                        <A href=format!("/projects/{}", p.id) attr:class="project-card">
+                                                            <span class="project-name">{p.name}</span>
+                                                            <span class="hint">
+                                                                {p.target_count}" target(s) · updated "{p.updated_at}
+                                                            </span>
+                                                        </A>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </div>
+                                    }
+                                        .into_any()
+                                }
+                                Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
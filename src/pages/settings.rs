@@ -1,9 +1,10 @@
 use leptos::prelude::*;
 
+use crate::components::SwatchExport;
 use crate::models::MixChoice;
 use crate::server_fns::{
-    get_paint_brands, get_paint_colors, get_user_paint_settings, save_user_paint_settings,
-    PaintColorInfo,
+    create_palette, delete_palette, export_palette_swatches, get_paint_brands, get_paint_colors,
+    list_palettes, update_palette, PaintBrand, PaintColorInfo, Palette, PalettePaint, SwatchFormat,
 };
 
 const DEFAULT_BRAND: &str = "michael_harding";
@@ -11,11 +12,22 @@ const DEFAULT_BRAND: &str = "michael_harding";
 #[component]
 pub fn SettingsPage() -> impl IntoView {
     let brands = Resource::new(|| (), |_| get_paint_brands());
-    let settings = Resource::new(|| (), |_| get_user_paint_settings());
+    let palettes = Resource::new(|| (), |_| list_palettes());
+    let brand_list = Signal::derive(move || {
+        brands.get().and_then(|result| result.ok()).unwrap_or_default()
+    });
+
+    // Palette being edited; None means a new, unsaved palette
+    let (editing_id, set_editing_id) = signal(Option::<String>::None);
+    let (palette_name, set_palette_name) = signal("My Palette".to_string());
 
     let (selected_brand, set_selected_brand) = signal(DEFAULT_BRAND.to_string());
-    let (selected_colors, set_selected_colors) = signal(Vec::<String>::new());
+    // Selected paints across every brand; the brand dropdown only changes which brand is shown
+    let (selected_colors, set_selected_colors) = signal(Vec::<PalettePaint>::new());
     let (mix_choice, set_mix_choice) = signal("black + white + 2 colours".to_string());
+    // Paints chosen as the palette's white and black; None picks them by role
+    let (white, set_white) = signal(Option::<PalettePaint>::None);
+    let (black, set_black) = signal(Option::<PalettePaint>::None);
     let (save_status, set_save_status) = signal(Option::<String>::None);
     let (initialized, set_initialized) = signal(false);
     let (user_has_interacted, set_user_has_interacted) = signal(false);
@@ -32,17 +44,39 @@ pub fn SettingsPage() -> impl IntoView {
         },
     );
 
-    // Initialize from saved settings or use defaults
+    let load_palette = move |p: Palette| {
+        set_editing_id.set(Some(p.id));
+        set_palette_name.set(p.name);
+        if !p.mix_choice.is_empty() {
+            set_mix_choice.set(p.mix_choice);
+        }
+        if let Some(first) = p.paints.first() {
+            set_selected_brand.set(first.brand.clone());
+        }
+        set_selected_colors.set(p.paints);
+        set_white.set(p.white);
+        set_black.set(p.black);
+        set_save_status.set(None);
+    };
+
+    let new_palette = move || {
+        set_editing_id.set(None);
+        set_palette_name.set("New Palette".to_string());
+        set_selected_colors.set(vec![]);
+        set_white.set(None);
+        set_black.set(None);
+        set_user_has_interacted.set(true);
+        set_save_status.set(None);
+    };
+
+    // Initialize from the active palette, or start a new one with defaults
     Effect::new(move || {
-        if let Some(Ok(s)) = settings.get() {
-            if !s.brand.is_empty() {
-                set_selected_brand.set(s.brand);
-                if !s.colors.is_empty() {
-                    set_selected_colors.set(s.colors);
-                }
+        if let Some(Ok(list)) = palettes.get() {
+            if initialized.get_untracked() {
+                return;
             }
-            if !s.mix_choice.is_empty() {
-                set_mix_choice.set(s.mix_choice);
+            if let Some(p) = list.iter().find(|p| p.active).or(list.first()) {
+                load_palette(p.clone());
             }
             set_initialized.set(true);
         }
@@ -59,8 +93,14 @@ pub fn SettingsPage() -> impl IntoView {
                 && selected_colors.get().is_empty()
                 && !color_list.is_empty()
             {
-                let all_ids: Vec<String> = color_list.iter().map(|c| c.id.clone()).collect();
-                set_selected_colors.set(all_ids);
+                let all: Vec<PalettePaint> = color_list
+                    .iter()
+                    .map(|c| PalettePaint {
+                        brand: c.brand.clone(),
+                        name: c.id.clone(),
+                    })
+                    .collect();
+                set_selected_colors.set(all);
             }
         }
     });
@@ -73,31 +113,85 @@ pub fn SettingsPage() -> impl IntoView {
         }
     });
 
-    // Check if all colors are selected
+    // Number of selected paints from the brand currently shown
+    let selected_in_brand = move || {
+        let brand = selected_brand.get();
+        selected_colors
+            .get()
+            .iter()
+            .filter(|p| p.brand == brand)
+            .count()
+    };
+
+    // Check if all colors of the current brand are selected
     let all_selected = move || {
         let available = available_colors.get();
-        let selected = selected_colors.get();
-        !available.is_empty() && available.len() == selected.len()
+        !available.is_empty() && available.len() == selected_in_brand()
+    };
+
+    // Number of distinct brands in the palette
+    let brand_count = move || {
+        let mut brands: Vec<String> = selected_colors.get().into_iter().map(|p| p.brand).collect();
+        brands.sort();
+        brands.dedup();
+        brands.len()
     };
 
     let save_settings = Action::new(move |_: &()| {
-        let brand = selected_brand.get();
+        let id = editing_id.get();
+        let name = palette_name.get();
         let colors = selected_colors.get();
         let choice = mix_choice.get();
+        let (white, black) = (white.get(), black.get());
 
         async move {
             set_save_status.set(Some("Saving...".to_string()));
-            match save_user_paint_settings(choice, brand, colors).await {
-                Ok(()) => set_save_status.set(Some("Settings saved!".to_string())),
+            let result = match id {
+                Some(id) => update_palette(id, name, choice, colors, white, black).await,
+                None => create_palette(name, choice, colors, white, black)
+                    .await
+                    .map(|p| set_editing_id.set(Some(p.id))),
+            };
+            match result {
+                Ok(()) => {
+                    set_save_status.set(Some("Palette saved!".to_string()));
+                    palettes.refetch();
+                }
                 Err(e) => set_save_status.set(Some(format!("Error: {}", e))),
             }
         }
     });
 
-    let toggle_color = move |color: String| {
+    let delete_current = Action::new(move |_: &()| {
+        let id = editing_id.get();
+
+        async move {
+            let Some(id) = id else { return };
+            match delete_palette(id).await {
+                Ok(()) => {
+                    new_palette();
+                    palettes.refetch();
+                }
+                Err(e) => set_save_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
+    // Export the saved palette for digital painting tools
+    let export = Action::new(move |format: &SwatchFormat| {
+        export_palette_swatches(editing_id.get_untracked(), *format)
+    });
+
+    let toggle_color = move |color: PalettePaint| {
         set_selected_colors.update(|colors| {
             if colors.contains(&color) {
                 colors.retain(|c| c != &color);
+                // A paint taken out of the palette can no longer be its white or black
+                for (choice, set) in [(white, set_white), (black, set_black)] {
+                    if choice.get_untracked().as_ref() == Some(&color) {
+                        set.set(None);
+                    }
+                }
             } else {
                 colors.push(color);
             }
@@ -107,10 +201,64 @@ pub fn SettingsPage() -> impl IntoView {
     view! {
         <div class="settings-page">
             <h1>"Paint Settings"</h1>
-            <p class="subtitle">"Configure your paint palette and mixing preferences"</p>
+            <p class="subtitle">"Configure your paint palettes and mixing preferences"</p>
+
+            <div class="settings-section">
+                <h2>"Palettes"</h2>
+                <div class="palette-tabs">
+                    <Suspense fallback=move || view! { <p>"Loading palettes..."</p> }>
+                        {move || {
+                            palettes
+                                .get()
+                                .map(|result| {
+                                    match result {
+                                        Ok(list) => {
+                                            list.into_iter()
+                                                .map(|p| {
+                                                    let id = p.id.clone();
+                                                    let name = p.name.clone();
+                                                    view! {
+                                                        <button
+                                                            class="mode-btn"
+                                                            class:active=move || {
+                                                                editing_id.get().as_deref() == Some(id.as_str())
+                                                            }
+                                                            on:click=move |_| load_palette(p.clone())
+                                                        >
+                                                            {name}
+                                                        </button>
+                                                    }
+                                                })
+                                                .collect_view()
+                                                .into_any()
+                                        }
+                                        Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                                    }
+                                })
+                        }}
+                    </Suspense>
+                    <button class="mode-btn" on:click=move |_| new_palette()>"+ New Palette"</button>
+                </div>
+                <div class="input-group">
+                    <label for="palette-name">"Name"</label>
+                    <input
+                        type="text"
+                        id="palette-name"
+                        class="select-input"
+                        prop:value=move || palette_name.get()
+                        on:input=move |ev| set_palette_name.set(event_target_value(&ev))
+                    />
+                </div>
+            </div>
 
             <div class="settings-section">
-                <h2>"Mix Strategy"</h2>
+                <h2>"Export Swatches"</h2>
+                <p class="hint">"Download the saved palette for digital painting tools"</p>
+                <SwatchExport export=export disabled=Signal::derive(move || editing_id.get().is_none())/>
+            </div>
+
+            <div class="settings-section">
+                <h2>"Default Mix Strategy"</h2>
                 <select
                     class="select-input"
                     on:change=move |ev| {
@@ -132,6 +280,29 @@ pub fn SettingsPage() -> impl IntoView {
                 </select>
             </div>
 
+            <div class="settings-section">
+                <h2>"White and Black"</h2>
+                <p class="hint">
+                    "The paints the black + white strategies mix with. Automatic uses the lightest paint detected as white and the darkest detected as black."
+                </p>
+                <div class="mix-filters">
+                    <RoleChoiceSelect
+                        label="White"
+                        choice=white
+                        set_choice=set_white
+                        paints=selected_colors
+                        brands=brand_list
+                    />
+                    <RoleChoiceSelect
+                        label="Black"
+                        choice=black
+                        set_choice=set_black
+                        paints=selected_colors
+                        brands=brand_list
+                    />
+                </div>
+            </div>
+
             <div class="settings-section">
                 <h2>"Paint Brand"</h2>
                 <Suspense fallback=move || view! { <p>"Loading brands..."</p> }>
@@ -147,7 +318,6 @@ pub fn SettingsPage() -> impl IntoView {
                                                 on:change=move |ev| {
                                                     let brand = event_target_value(&ev);
                                                     set_selected_brand.set(brand);
-                                                    set_selected_colors.set(vec![]);
                                                 }
                                                 prop:value=move || selected_brand.get()
                                             >
@@ -182,21 +352,31 @@ pub fn SettingsPage() -> impl IntoView {
                 <div class="colour-controls">
                     <p class="hint">
                         "Click colours to add them to your palette. Selected: "
-                        {move || selected_colors.get().len()}
+                        {selected_in_brand}
                         " / "
                         {move || available_colors.get().len()}
+                        " in this brand, "
+                        {move || selected_colors.get().len()}
+                        " paints from "
+                        {brand_count}
+                        " brand(s) in total"
                     </p>
                     <button
                         class="btn toggle-all"
                         on:click=move |_| {
                             set_user_has_interacted.set(true);
                             let available = available_colors.get();
-                            if all_selected() {
-                                set_selected_colors.set(vec![]);
-                            } else {
-                                let all_ids: Vec<String> = available.iter().map(|c| c.id.clone()).collect();
-                                set_selected_colors.set(all_ids);
-                            }
+                            let brand = selected_brand.get();
+                            let select = !all_selected();
+                            set_selected_colors.update(|colors| {
+                                colors.retain(|p| p.brand != brand);
+                                if select {
+                                    colors.extend(available.iter().map(|c| PalettePaint {
+                                        brand: c.brand.clone(),
+                                        name: c.id.clone(),
+                                    }));
+                                }
+                            });
                         }
                         disabled=move || available_colors.get().is_empty()
                     >
@@ -219,17 +399,32 @@ pub fn SettingsPage() -> impl IntoView {
                                                     {color_list
                                                         .into_iter()
                                                         .map(|c| {
-                                                            let id = c.id.clone();
-                                                            let id2 = c.id.clone();
+                                                            let paint = PalettePaint {
+                                                                brand: c.brand.clone(),
+                                                                name: c.id.clone(),
+                                                            };
+                                                            let id = paint.clone();
+                                                            let id2 = paint;
                                                             let hex = c.hex.clone();
+                                                            let summary = c.attributes.summary();
+                                                            let mut title = if summary.is_empty() {
+                                                                c.id.clone()
+                                                            } else {
+                                                                format!("{} ({})", c.id, summary)
+                                                            };
+                                                            let unusable = c.curve_error.is_some();
+                                                            if let Some(e) = &c.curve_error {
+                                                                title.push_str(&format!(". Cannot be mixed: {}", e));
+                                                            }
                                                             view! {
                                                                 <button
                                                                     class="colour-swatch"
                                                                     class:selected=move || {
                                                                         selected_colors.get().contains(&id)
                                                                     }
+                                                                    class:unusable=unusable
                                                                     style=format!("background-color: {}", hex)
-                                                                    title=id2.clone()
+                                                                    title=title
                                                                     on:click=move |_| toggle_color(id2.clone())
                                                                 >
                                                                     <span class="colour-name">{c.id}</span>
@@ -251,7 +446,14 @@ pub fn SettingsPage() -> impl IntoView {
 
             <div class="settings-actions">
                 <button class="btn primary" on:click=move |_| { save_settings.dispatch(()); }>
-                    "Save Settings"
+                    "Save Palette"
+                </button>
+                <button
+                    class="btn btn-secondary"
+                    on:click=move |_| { delete_current.dispatch(()); }
+                    disabled=move || editing_id.get().is_none()
+                >
+                    "Delete Palette"
                 </button>
                 {move || {
                     save_status
@@ -264,3 +466,50 @@ pub fn SettingsPage() -> impl IntoView {
         </div>
     }
 }
+
+/// Dropdown choosing which of the palette's paints fills a role
+#[component]
+fn RoleChoiceSelect(
+    label: &'static str,
+    choice: ReadSignal<Option<PalettePaint>>,
+    set_choice: WriteSignal<Option<PalettePaint>>,
+    paints: ReadSignal<Vec<PalettePaint>>,
+    brands: Signal<Vec<PaintBrand>>,
+) -> impl IntoView {
+    let key = |p: &PalettePaint| format!("{}/{}", p.brand, p.name);
+    let brand_name = move |id: &str| {
+        brands
+            .get()
+            .into_iter()
+            .find(|b| b.id == id)
+            .map(|b| b.name)
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    view! {
+        <label class="filter-option">
+            {label}
+            <select on:change=move |ev| {
+                let value = event_target_value(&ev);
+                set_choice.set(paints.get_untracked().into_iter().find(|p| key(p) == value));
+            }>
+                <option value="" selected=move || choice.get().is_none()>"Automatic"</option>
+                {move || {
+                    paints
+                        .get()
+                        .into_iter()
+                        .map(|p| {
+                            let value = key(&p);
+                            let label = format!("{} ({})", p.name, brand_name(&p.brand));
+                            view! {
+                                <option value=value selected=move || choice.get().as_ref() == Some(&p)>
+                                    {label}
+                                </option>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </select>
+        </label>
+    }
+}
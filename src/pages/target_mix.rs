@@ -1,25 +1,62 @@
 use leptos::prelude::*;
 use leptos::web_sys;
-#[cfg(feature = "hydrate")]
-use wasm_bindgen::JsCast;
 
-use crate::models::MixingResult;
-use crate::server_fns::find_paint_mix;
+use crate::components::{
+    read_file, read_image_file, sample_image_pixel, MixResultCard, PaletteSelector,
+};
+use crate::models::{parse_hex, to_hex, MixChoice, MixingResult, Opacity, PaintFilters, TargetColour};
+use crate::server_fns::{
+    find_measured_mix, find_paint_mix, get_paint_brands, import_measurements, MeasuredTarget,
+    Palette,
+};
 
 #[derive(Clone, Copy, PartialEq)]
 enum InputMode {
     Picker,
     Image,
+    Measurement,
 }
 
 #[component]
 pub fn TargetMixPage() -> impl IntoView {
     let (target_colour, set_target_colour) = signal("#808080".to_string());
     let (r, g, b) = (signal(128u8), signal(128u8), signal(128u8));
+    // Target typed in another notation (Lab, LCh, Munsell...); sent as written
+    // so colours outside sRGB keep their full chroma
+    let (notation, set_notation) = signal(String::new());
+    // Spectrophotometer measurements from an imported file, and the one in use
+    let (measurements, set_measurements) = signal(Vec::<MeasuredTarget>::new());
+    let (measured, set_measured) = signal(Option::<MeasuredTarget>::None);
     let (results, set_results) = signal(Option::<Vec<MixingResult>>::None);
     let (error, set_error) = signal(Option::<String>::None);
     let (loading, set_loading) = signal(false);
 
+    // Palette to mix from and its strategy (defaults to the palette's own)
+    let palette = RwSignal::new(Option::<Palette>::None);
+    let (mix_choice, set_mix_choice) = signal(String::new());
+    Effect::new(move || {
+        if let Some(p) = palette.get() {
+            set_mix_choice.set(p.mix_choice);
+        }
+    });
+
+    // Optional palette filters
+    let (exclude_fugitive, set_exclude_fugitive) = signal(false);
+    let (max_series, set_max_series) = signal(Option::<u8>::None);
+    let (opacity_filter, set_opacity_filter) = signal(Vec::<Opacity>::new());
+    let brands = Resource::new(|| (), |_| get_paint_brands());
+    // Brands in the palette that publish no paint metadata, which the filters keep whole
+    let brands_without_metadata = move || {
+        let palette = palette.get()?;
+        let brands = brands.get()?.ok()?;
+        let missing: Vec<String> = brands
+            .into_iter()
+            .filter(|b| !b.has_metadata && palette.paints.iter().any(|p| p.brand == b.id))
+            .map(|b| b.name)
+            .collect();
+        (!missing.is_empty()).then(|| missing.join(", "))
+    };
+
     // Input mode: colour picker or image (default to image)
     let (input_mode, set_input_mode) = signal(InputMode::Image);
 
@@ -28,6 +65,8 @@ pub fn TargetMixPage() -> impl IntoView {
 
     // Update RGB from hex
     let update_from_hex = move |hex: String| {
+        set_notation.set(String::new());
+        set_measured.set(None);
         if hex.len() == 7 && hex.starts_with('#') {
             if let (Ok(red), Ok(green), Ok(blue)) = (
                 u8::from_str_radix(&hex[1..3], 16),
@@ -44,127 +83,114 @@ pub fn TargetMixPage() -> impl IntoView {
 
     // Update hex from RGB
     let update_hex = move || {
+        set_notation.set(String::new());
+        set_measured.set(None);
         let hex = format!("#{:02x}{:02x}{:02x}", r.0.get(), g.0.get(), b.0.get());
         set_target_colour.set(hex);
     };
 
     // Handle image file selection
-    #[allow(unused_variables)]
     let handle_image_upload = move |ev: web_sys::Event| {
-        #[cfg(feature = "hydrate")]
-        {
-            use ::web_sys::{FileReader, HtmlInputElement};
-
-            let input = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
-            if let Some(files) = input.files() {
-                if let Some(file) = files.get(0) {
-                    let reader = FileReader::new().unwrap();
-                    let reader_clone = reader.clone();
-
-                    let closure = wasm_bindgen::closure::Closure::once(Box::new(move || {
-                        if let Ok(result) = reader_clone.result() {
-                            if let Some(data_url) = result.as_string() {
-                                set_image_src.set(Some(data_url));
-                                set_input_mode.set(InputMode::Image);
-                            }
-                        }
-                    }) as Box<dyn FnOnce()>);
-
-                    reader.set_onload(Some(closure.as_ref().unchecked_ref()));
-                    closure.forget();
-
-                    let _ = reader.read_as_data_url(&file);
-                }
-            }
-        }
-        let _ = ev;
+        read_image_file(&ev, None, move |data_url| {
+            set_image_src.set(Some(data_url));
+            set_input_mode.set(InputMode::Image);
+        });
     };
 
     // Handle clicking on image to pick colour
-    // Simple approach: use offsetX/offsetY which gives position relative to the element
-    #[allow(unused_variables)]
     let handle_image_click = move |ev: web_sys::MouseEvent| {
-        #[cfg(feature = "hydrate")]
-        {
-            use ::web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
-
-            let target = ev.target().unwrap();
-            let img = target.dyn_into::<HtmlImageElement>().unwrap();
-
-            // Get natural (original) dimensions of the image
-            let natural_width = img.natural_width();
-            let natural_height = img.natural_height();
-
-            // Get the displayed dimensions
-            let displayed_width = img.width() as f64;
-            let displayed_height = img.height() as f64;
-
-            if displayed_width == 0.0 || displayed_height == 0.0 || natural_width == 0 || natural_height == 0 {
-                return;
-            }
-
-            // Use offsetX/offsetY - position relative to the clicked element
-            let offset_x = ev.offset_x() as f64;
-            let offset_y = ev.offset_y() as f64;
-
-            // Scale from displayed size to natural size
-            let scale_x = natural_width as f64 / displayed_width;
-            let scale_y = natural_height as f64 / displayed_height;
-
-            let x = (offset_x * scale_x).round() as u32;
-            let y = (offset_y * scale_y).round() as u32;
-
-            // Clamp to valid range
-            let x = x.min(natural_width - 1);
-            let y = y.min(natural_height - 1);
-
-            // Create canvas at natural size for accurate sampling
-            let document = ::web_sys::window().unwrap().document().unwrap();
-            let canvas = document
-                .create_element("canvas")
-                .unwrap()
-                .dyn_into::<HtmlCanvasElement>()
-                .unwrap();
-            canvas.set_width(natural_width);
-            canvas.set_height(natural_height);
-
-            let ctx = canvas
-                .get_context("2d")
-                .unwrap()
-                .unwrap()
-                .dyn_into::<CanvasRenderingContext2d>()
-                .unwrap();
-
-            // Draw image at natural size
-            let _ = ctx.draw_image_with_html_image_element(&img, 0.0, 0.0);
+        if let Some([red, green, blue]) = sample_image_pixel(&ev) {
+            r.1.set(red);
+            g.1.set(green);
+            b.1.set(blue);
+            update_hex();
+        }
+    };
 
-            // Sample pixel at calculated position
-            if let Ok(image_data) = ctx.get_image_data(x as f64, y as f64, 1.0, 1.0) {
-                let data = image_data.data();
-                let red = data[0];
-                let green = data[1];
-                let blue = data[2];
+    // Preview a typed notation as its nearest sRGB colour
+    let update_from_notation = move |text: String| {
+        if let Ok(colour) = TargetColour::parse(&text) {
+            let [red, green, blue] = colour.to_srgb();
+            r.1.set(red);
+            g.1.set(green);
+            b.1.set(blue);
+            set_target_colour.set(to_hex(colour.to_srgb()));
+        }
+        set_measured.set(None);
+        set_notation.set(text);
+    };
 
-                r.1.set(red);
-                g.1.set(green);
-                b.1.set(blue);
-                update_hex();
+    let import = Action::new(move |(filename, data_url): &(String, String)| {
+        let (filename, data_url) = (filename.clone(), data_url.clone());
+        async move {
+            set_error.set(None);
+            match import_measurements(filename, data_url).await {
+                Ok(samples) => set_measurements.set(samples),
+                Err(e) => set_error.set(Some(e.to_string())),
             }
         }
-        let _ = ev;
+    });
+    let handle_measurement_upload = move |ev: web_sys::Event| {
+        read_file(&ev, move |filename, data_url| {
+            import.dispatch((filename, data_url));
+        });
+    };
+    let select_measurement = move |sample: MeasuredTarget| {
+        if let Some([red, green, blue]) = parse_hex(&sample.hex) {
+            r.1.set(red);
+            g.1.set(green);
+            b.1.set(blue);
+        }
+        set_target_colour.set(sample.hex.clone());
+        set_notation.set(String::new());
+        set_measured.set(Some(sample));
+    };
+    let notation_status = move || {
+        let text = notation.get();
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(match TargetColour::parse(&text) {
+            Ok(colour) => {
+                let mut status = if colour.in_srgb_gamut() {
+                    colour.to_string()
+                } else {
+                    "Outside sRGB: the preview is clipped, the mix uses the full colour".to_string()
+                };
+                if colour.is_approximate() {
+                    status.push_str(" (approximate: Munsell hue and chroma are estimated)");
+                }
+                status
+            }
+            Err(e) => e,
+        })
     };
 
     let find_mix = Action::new(move |_: &()| {
-        let red = r.0.get();
-        let green = g.0.get();
-        let blue = b.0.get();
+        let sample = measured.get();
+        let target = Some(notation.get())
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| format!("#{:02x}{:02x}{:02x}", r.0.get(), g.0.get(), b.0.get()));
+        let palette_id = palette.get().map(|p| p.id);
+        let choice = Some(mix_choice.get()).filter(|c| !c.is_empty());
+        let filters = PaintFilters {
+            exclude_fugitive: exclude_fugitive.get(),
+            opacity: opacity_filter.get(),
+            max_series: max_series.get(),
+            ..Default::default()
+        };
 
         async move {
             set_loading.set(true);
             set_error.set(None);
             set_results.set(None);
 
-            match find_paint_mix(red, green, blue).await {
+            let filters = (!filters.is_empty()).then_some(filters);
+            let found = match sample {
+                Some(sample) => find_measured_mix(sample, filters, palette_id, choice).await,
+                None => find_paint_mix(target, filters, palette_id, choice).await,
+            };
+            match found {
                 Ok(res) => {
                     set_results.set(Some(res));
                 }
@@ -198,6 +224,13 @@ pub fn TargetMixPage() -> impl IntoView {
                     >
                         "Image Upload"
                     </button>
+                    <button
+                        class="mode-btn"
+                        class:active=move || input_mode.get() == InputMode::Measurement
+                        on:click=move |_| set_input_mode.set(InputMode::Measurement)
+                    >
+                        "Measurement"
+                    </button>
                 </div>
 
                 <div class="selected-colour-display">
@@ -206,6 +239,11 @@ pub fn TargetMixPage() -> impl IntoView {
                         style=move || format!("background-color: {}", target_colour.get())
                     ></div>
                     <span class="colour-value">{move || target_colour.get()}</span>
+                    {move || {
+                        measured
+                            .get()
+                            .map(|m| view! { <span class="colour-rgb">{format!("Measured: {}", m.name)}</span> })
+                    }}
                     <span class="colour-rgb">
                         {move || format!("RGB({}, {}, {})", r.0.get(), g.0.get(), b.0.get())}
                     </span>
@@ -220,6 +258,89 @@ pub fn TargetMixPage() -> impl IntoView {
                 </button>
             </div>
 
+            // Palette, strategy and filters
+            <div class="mix-filters">
+                <label class="filter-option">
+                    "Palette"
+                    <PaletteSelector selected=palette/>
+                </label>
+                <label class="filter-option">
+                    "Strategy"
+                    <select on:change=move |ev| set_mix_choice.set(event_target_value(&ev))>
+                        {MixChoice::all()
+                            .into_iter()
+                            .map(|choice| {
+                                let value = choice.as_str();
+                                view! {
+                                    <option value=value selected=move || mix_choice.get() == value>
+                                        {value}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="filter-option">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || exclude_fugitive.get()
+                        on:change=move |ev| set_exclude_fugitive.set(event_target_checked(&ev))
+                    />
+                    "Exclude fugitive pigments"
+                </label>
+                <label class="filter-option">
+                    "Max series"
+                    <select on:change=move |ev| {
+                        set_max_series.set(event_target_value(&ev).parse().ok());
+                    }>
+                        <option value="">"Any"</option>
+                        {(1..=8u8)
+                            .map(|n| view! { <option value=n.to_string()>{n}</option> })
+                            .collect_view()}
+                    </select>
+                </label>
+                <div class="filter-option">
+                    "Opacity"
+                    {Opacity::all()
+                        .into_iter()
+                        .map(|o| {
+                            view! {
+                                <button
+                                    class="mode-btn"
+                                    class:active=move || opacity_filter.get().contains(&o)
+                                    on:click=move |_| {
+                                        set_opacity_filter.update(|list| {
+                                            if list.contains(&o) {
+                                                list.retain(|x| *x != o);
+                                            } else {
+                                                list.push(o);
+                                            }
+                                        });
+                                    }
+                                >
+                                    {o.label()}
+                                </button>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <Suspense fallback=|| ()>
+                    {move || {
+                        brands_without_metadata()
+                            .map(|names| {
+                                view! {
+                                    <p class="hint">
+                                        {format!(
+                                            "No pigment, lightfastness, opacity or series data for {}, so the filters keep all of their paints",
+                                            names,
+                                        )}
+                                    </p>
+                                }
+                            })
+                    }}
+                </Suspense>
+            </div>
+
             // Main content area
             <div class="main-content" class:has-results=move || results.get().is_some()>
                 // Left panel: Input (picker or image)
@@ -245,6 +366,17 @@ pub fn TargetMixPage() -> impl IntoView {
                                             />
                                         </div>
 
+                                        <div class="input-group">
+                                            <label>"Lab, LCh, XYZ, HSL, OKLCH or Munsell"</label>
+                                            <input
+                                                type="text"
+                                                prop:value=move || notation.get()
+                                                on:input=move |ev| update_from_notation(event_target_value(&ev))
+                                                placeholder="lab(54 62 40) or 5R 4/14"
+                                            />
+                                            {move || notation_status().map(|s| view! { <span class="hint">{s}</span> })}
+                                        </div>
+
                                         <div class="input-group">
                                             <label>"Hex"</label>
                                             <input
@@ -364,6 +496,54 @@ pub fn TargetMixPage() -> impl IntoView {
                             }
                                 .into_any()
                         }
+                        InputMode::Measurement => {
+                            view! {
+                                <div class="measurement-section">
+                                    <label class="image-upload-area">
+                                        <input
+                                            type="file"
+                                            accept=".txt,.cgats,.it8,.cxf,.csv"
+                                            on:change=handle_measurement_upload
+                                        />
+                                        <div class="upload-placeholder">
+                                            <span class="upload-icon">"📈"</span>
+                                            <span>"Upload a spectrophotometer file"</span>
+                                            <span class="upload-hint">
+                                                "CGATS, CxF3 or CSV reflectance covering 400–700 nm. The measured curve is matched directly."
+                                            </span>
+                                        </div>
+                                    </label>
+                                    {move || import.pending().get().then(|| view! { <p class="hint">"Reading measurements..."</p> })}
+                                    <div class="measurement-list">
+                                        <For
+                                            each=move || measurements.get()
+                                            key=|m| m.name.clone()
+                                            children=move |m| {
+                                                let name = m.name.clone();
+                                                let hex = m.hex.clone();
+                                                let selected_name = name.clone();
+                                                view! {
+                                                    <button
+                                                        class="measurement-item"
+                                                        class:active=move || {
+                                                            measured.get().is_some_and(|s| s.name == selected_name)
+                                                        }
+                                                        on:click=move |_| select_measurement(m.clone())
+                                                    >
+                                                        <span
+                                                            class="measurement-swatch"
+                                                            style=format!("background-color: {}", hex)
+                                                        ></span>
+                                                        {name}
+                                                    </button>
+                                                }
+                                            }
+                                        />
+                                    </div>
+                                </div>
+                            }
+                                .into_any()
+                        }
                     }}
                 </div>
 
@@ -420,62 +600,3 @@ pub fn TargetMixPage() -> impl IntoView {
         </div>
     }
 }
-
-#[component]
-fn MixResultCard(mix: MixingResult, rank: usize) -> impl IntoView {
-    let total_weight: f64 = mix.weights.iter().sum();
-
-    view! {
-        <div class="mix-result-card">
-            <div class="card-header">
-                <span class="mix-rank">{"#"}{rank}</span>
-                <span class="mix-error">"ΔE: "{format!("{:.2}", mix.error)}</span>
-            </div>
-
-            // Horizontal bar chart showing paint proportions
-            <div class="mix-bar-chart">
-                {mix
-                    .weights
-                    .iter()
-                    .zip(mix.hex_colors.iter())
-                    .zip(mix.paints.iter())
-                    .map(|((weight, hex), name)| {
-                        let percentage = (weight / total_weight * 100.0).round();
-                        view! {
-                            <div
-                                class="bar-segment"
-                                style=format!("background-color: {}; width: {}%;", hex, percentage)
-                                title=format!("{}: {}%", name, percentage)
-                            >
-                                {(percentage >= 10.0).then(|| format!("{}%", percentage))}
-                            </div>
-                        }
-                    })
-                    .collect_view()}
-            </div>
-
-            // Paint list with details
-            <div class="paint-details">
-                {mix
-                    .paints
-                    .iter()
-                    .zip(mix.weights.iter())
-                    .zip(mix.hex_colors.iter())
-                    .map(|((name, weight), hex)| {
-                        let percentage = (weight / total_weight * 100.0).round();
-                        view! {
-                            <div class="paint-row">
-                                <div
-                                    class="paint-swatch"
-                                    style=format!("background-color: {}", hex)
-                                ></div>
-                                <span class="paint-name">{name.clone()}</span>
-                                <span class="paint-percentage">{percentage}"%"</span>
-                            </div>
-                        }
-                    })
-                    .collect_view()}
-            </div>
-        </div>
-    }
-}
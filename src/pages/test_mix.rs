@@ -1,39 +1,39 @@
 use leptos::prelude::*;
 
-use crate::server_fns::{get_paint_colors, get_user_paint_settings, test_paint_mix};
+use crate::components::PaletteSelector;
+use crate::server_fns::{get_palette_colors, save_test_mix, test_paint_mix, Palette, PalettePaint};
 
 #[component]
 pub fn TestMixPage() -> impl IntoView {
-    let settings = Resource::new(|| (), |_| get_user_paint_settings());
+    // Paints from every brand in the chosen palette
+    let palette = RwSignal::new(Option::<Palette>::None);
+    let colors = Resource::new(
+        move || palette.get().map(|p| p.id),
+        |id| async move {
+            match id {
+                Some(id) => get_palette_colors(Some(id)).await,
+                None => Ok(vec![]),
+            }
+        },
+    );
     // Store raw weights (will be normalized to percentages for display)
-    let (selected_paints, set_selected_paints) = signal(Vec::<(String, f64)>::new());
+    let (selected_paints, set_selected_paints) = signal(Vec::<(PalettePaint, f64)>::new());
     let (result_color, set_result_color) = signal(Option::<String>::None);
     let (error, set_error) = signal(Option::<String>::None);
     let (loading, set_loading) = signal(false);
 
-    // Track the brand from settings
-    let (current_brand, set_current_brand) = signal(String::new());
-
-    // Update brand when settings load
-    Effect::new(move || {
-        if let Some(Ok(s)) = settings.get() {
-            if !s.brand.is_empty() {
-                set_current_brand.set(s.brand);
-            }
-        }
-    });
-
-    // Load colors based on current brand
-    let colors = Resource::new(
-        move || current_brand.get(),
-        |brand| async move {
-            if brand.is_empty() {
-                Ok(vec![])
-            } else {
-                get_paint_colors(brand).await
-            }
-        },
-    );
+    // Brand display name of a paint in the mix, from the palette's paints
+    let brand_name_of = move |paint: &PalettePaint| {
+        colors
+            .get()
+            .and_then(|result| result.ok())
+            .and_then(|list| {
+                list.into_iter()
+                    .find(|c| c.brand == paint.brand && c.id == paint.name)
+                    .map(|c| c.brand_name)
+            })
+            .unwrap_or_else(|| paint.brand.clone())
+    };
 
     // Calculate total weight for percentage display
     let total_weight = Memo::new(move |_| {
@@ -59,7 +59,7 @@ pub fn TestMixPage() -> impl IntoView {
             set_loading.set(true);
             set_error.set(None);
 
-            let paint_names: Vec<String> = paints.iter().map(|(p, _)| p.clone()).collect();
+            let paint_names: Vec<PalettePaint> = paints.iter().map(|(p, _)| p.clone()).collect();
             let weights: Vec<f64> = paints.iter().map(|(_, w)| *w).collect();
 
             match test_paint_mix(paint_names, weights).await {
@@ -70,13 +70,30 @@ pub fn TestMixPage() -> impl IntoView {
         }
     });
 
+    // Keep the current mixture in the user's history
+    let (save_status, set_save_status) = signal(Option::<String>::None);
+    let save_mix = Action::new(move |_: &()| {
+        let paints = selected_paints.get();
+
+        async move {
+            let paint_names: Vec<PalettePaint> = paints.iter().map(|(p, _)| p.clone()).collect();
+            let weights: Vec<f64> = paints.iter().map(|(_, w)| *w).collect();
+
+            match save_test_mix(paint_names, weights).await {
+                Ok(()) => set_save_status.set(Some("Saved to history".to_string())),
+                Err(e) => set_save_status.set(Some(format!("Error: {}", e))),
+            }
+        }
+    });
+
     // Trigger recalculation when paints change
     Effect::new(move |_| {
         let _ = selected_paints.get();
+        set_save_status.set(None);
         calculate_mix.dispatch(());
     });
 
-    let add_paint = move |paint: String| {
+    let add_paint = move |paint: PalettePaint| {
         set_selected_paints.update(|paints| {
             if !paints.iter().any(|(p, _)| p == &paint) {
                 // Add with equal weight
@@ -85,13 +102,13 @@ pub fn TestMixPage() -> impl IntoView {
         });
     };
 
-    let remove_paint = move |paint: String| {
+    let remove_paint = move |paint: PalettePaint| {
         set_selected_paints.update(|paints| {
             paints.retain(|(p, _)| p != &paint);
         });
     };
 
-    let update_weight = move |paint: String, weight: f64| {
+    let update_weight = move |paint: PalettePaint, weight: f64| {
         set_selected_paints.update(|paints| {
             if let Some((_, w)) = paints.iter_mut().find(|(p, _)| p == &paint) {
                 *w = weight;
@@ -103,6 +120,12 @@ pub fn TestMixPage() -> impl IntoView {
         <div class="test-mix-page">
             <h1>"Test Paint Mix"</h1>
             <p class="subtitle">"Create custom paint mixtures and preview the result"</p>
+            <div class="mix-filters">
+                <label class="filter-option">
+                    "Palette"
+                    <PaletteSelector selected=palette/>
+                </label>
+            </div>
 
             <div class="mix-builder">
                 <div class="available-paints">
@@ -127,14 +150,17 @@ pub fn TestMixPage() -> impl IntoView {
                                                         {color_list
                                                             .into_iter()
                                                             .map(|c| {
-                                                                let id = c.id.clone();
-                                                                let id2 = c.id.clone();
+                                                                let title = format!("{} ({})", c.id, c.brand_name);
+                                                                let paint = PalettePaint {
+                                                                    brand: c.brand.clone(),
+                                                                    name: c.id.clone(),
+                                                                };
                                                                 view! {
                                                                     <button
                                                                         class="paint-chip"
                                                                         style=format!("background-color: {}", c.hex)
-                                                                        title=id.clone()
-                                                                        on:click=move |_| add_paint(id2.clone())
+                                                                        title=title
+                                                                        on:click=move |_| add_paint(paint.clone())
                                                                     >
                                                                         <span>{c.id}</span>
                                                                     </button>
@@ -172,7 +198,9 @@ pub fn TestMixPage() -> impl IntoView {
                                             let percentage = (weight / total * 100.0).round() as u32;
                                             view! {
                                                 <div class="mix-item">
-                                                    <span class="paint-name">{paint.clone()}</span>
+                                                    <span class="paint-name" title=brand_name_of(&paint)>
+                                                        {paint.name.clone()}
+                                                    </span>
                                                     <input
                                                         type="range"
                                                         min="0.1"
@@ -230,6 +258,19 @@ pub fn TestMixPage() -> impl IntoView {
                                             <div class="result-swatch" style=format!("background-color: {}", hex)>
                                             </div>
                                             <span class="result-hex">{hex}</span>
+                                            <button
+                                                class="btn btn-small"
+                                                on:click=move |_| { save_mix.dispatch(()); }
+                                            >
+                                                "Save to History"
+                                            </button>
+                                            {move || {
+                                                save_status
+                                                    .get()
+                                                    .map(|status| {
+                                                        view! { <p class="hint">{status}</p> }
+                                                    })
+                                            }}
                                         </div>
                                     }
                                 })
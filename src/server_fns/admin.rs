@@ -0,0 +1,665 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::PaintAttributes;
+use crate::server_fns::MeasuredTarget;
+
+#[cfg(feature = "ssr")]
+use crate::server_fns::SessionUser;
+
+/// Audit log entries shown on the admin page by default
+pub const DEFAULT_AUDIT_LIMIT: i64 = 100;
+
+/// A change to the paint catalogue, as recorded in the audit log
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditAction {
+    CreateBrand,
+    UpdateBrand,
+    AddPaint,
+    ReplaceCurve,
+    UpdatePaint,
+    RenamePaint,
+    RecomputeHex,
+    RetirePaint,
+    RestorePaint,
+}
+
+impl AuditAction {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuditAction::CreateBrand => "create_brand",
+            AuditAction::UpdateBrand => "update_brand",
+            AuditAction::AddPaint => "add_paint",
+            AuditAction::ReplaceCurve => "replace_curve",
+            AuditAction::UpdatePaint => "update_paint",
+            AuditAction::RenamePaint => "rename_paint",
+            AuditAction::RecomputeHex => "recompute_hex",
+            AuditAction::RetirePaint => "retire_paint",
+            AuditAction::RestorePaint => "restore_paint",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Option<Self> {
+        match s {
+            "create_brand" => Some(AuditAction::CreateBrand),
+            "update_brand" => Some(AuditAction::UpdateBrand),
+            "add_paint" => Some(AuditAction::AddPaint),
+            "replace_curve" => Some(AuditAction::ReplaceCurve),
+            "update_paint" => Some(AuditAction::UpdatePaint),
+            "rename_paint" => Some(AuditAction::RenamePaint),
+            "recompute_hex" => Some(AuditAction::RecomputeHex),
+            "retire_paint" => Some(AuditAction::RetirePaint),
+            "restore_paint" => Some(AuditAction::RestorePaint),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::CreateBrand => "created brand",
+            AuditAction::UpdateBrand => "edited brand",
+            AuditAction::AddPaint => "added paint",
+            AuditAction::ReplaceCurve => "replaced curve",
+            AuditAction::UpdatePaint => "edited paint",
+            AuditAction::RenamePaint => "renamed paint",
+            AuditAction::RecomputeHex => "recomputed colour",
+            AuditAction::RetirePaint => "retired paint",
+            AuditAction::RestorePaint => "restored paint",
+        }
+    }
+}
+
+/// Editable catalogue details of a brand
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BrandDetails {
+    /// Stable id, lower case letters, digits and underscores
+    pub id: String,
+    pub display_name: String,
+    pub manufacturer: Option<String>,
+    pub medium: Option<String>,
+    pub measurement_source: Option<String>,
+    pub data_version: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+impl BrandDetails {
+    fn from_row(row: crate::db::BrandRow) -> Self {
+        BrandDetails {
+            id: row.id,
+            display_name: row.display_name,
+            manufacturer: row.manufacturer,
+            medium: row.medium,
+            measurement_source: row.measurement_source,
+            data_version: row.data_version,
+        }
+    }
+
+    fn into_row(self) -> crate::db::BrandRow {
+        crate::db::BrandRow {
+            id: self.id,
+            display_name: self.display_name,
+            manufacturer: self.manufacturer,
+            medium: self.medium,
+            measurement_source: self.measurement_source,
+            data_version: self.data_version,
+        }
+    }
+
+    /// Trim every field, dropping empty optional ones, and check the id and name
+    fn normalised(self) -> Result<Self, ServerFnError> {
+        use crate::server_fns::USER_PAINTS_BRAND;
+
+        let optional = |s: Option<String>| s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let id = self.id.trim().to_string();
+        if id.is_empty()
+            || id == USER_PAINTS_BRAND
+            || !id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(ServerFnError::new(
+                "Brand id must be lower case letters, digits and underscores",
+            ));
+        }
+        let display_name = self.display_name.trim().to_string();
+        if display_name.is_empty() {
+            return Err(ServerFnError::new("Brand name cannot be empty"));
+        }
+        Ok(BrandDetails {
+            id,
+            display_name,
+            manufacturer: optional(self.manufacturer),
+            medium: optional(self.medium),
+            measurement_source: optional(self.measurement_source),
+            data_version: optional(self.data_version),
+        })
+    }
+}
+
+/// A catalogued brand with how many paints it has
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminBrand {
+    pub details: BrandDetails,
+    pub paints: i64,
+    pub retired: i64,
+}
+
+/// A catalogued paint as an admin sees it, retired or not
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AdminPaint {
+    pub name: String,
+    /// Stored display colour
+    pub hex: Option<String>,
+    /// Display colour recomputed from the spectral curve
+    pub curve_hex: Option<String>,
+    /// CIELAB ΔE between the stored and recomputed colours
+    pub hex_difference: Option<f64>,
+    pub attributes: PaintAttributes,
+    pub retired_at: Option<String>,
+    pub curve_error: Option<String>,
+}
+
+/// Paints added and replaced by a spectral upload
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct UploadSummary {
+    pub added: usize,
+    pub replaced: usize,
+}
+
+/// One change to the paint catalogue
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_email: String,
+    pub action: AuditAction,
+    pub brand: Option<String>,
+    pub paint: Option<String>,
+    pub details: Option<String>,
+    pub created_at: String,
+}
+
+/// Check the signed in user is an admin, reading the flag from the database
+/// so revoking it takes effect immediately
+#[cfg(feature = "ssr")]
+async fn require_admin(users: &dyn crate::db::UserStore) -> Result<SessionUser, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+    match users.get_user_by_id(&user.id).await {
+        Some(u) if u.is_admin => Ok(user),
+        _ => Err(ServerFnError::new("Admin access required")),
+    }
+}
+
+/// A catalogue change by `user`, for the audit log
+#[cfg(feature = "ssr")]
+fn audit_entry(
+    user: &SessionUser,
+    action: AuditAction,
+    brand: Option<&str>,
+    paint: Option<&str>,
+    details: serde_json::Value,
+) -> crate::db::NewAuditEntry {
+    crate::db::NewAuditEntry {
+        user_id: user.id.clone(),
+        user_email: user.email.clone(),
+        action: action.code().to_string(),
+        brand_id: brand.map(str::to_string),
+        paint_name: paint.map(str::to_string),
+        details: Some(details.to_string()),
+    }
+}
+
+/// The admins' edits, which every catalogue read applies
+#[cfg(feature = "ssr")]
+async fn catalogue_overlay(
+    users: &dyn crate::db::UserStore,
+) -> Result<crate::db::CatalogueOverlay, ServerFnError> {
+    users
+        .catalogue_overlay()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+#[cfg(feature = "ssr")]
+fn map_catalogue_error(e: sqlx::Error, duplicate: &str) -> ServerFnError {
+    match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            ServerFnError::new(duplicate.to_string())
+        }
+        e => ServerFnError::new(e.to_string()),
+    }
+}
+
+/// Display colour of a paint curve under D65, as stored in `d65_10deg_hex`
+#[cfg(feature = "ssr")]
+fn curve_hex(
+    curve: &crate::db::SpectralCurve,
+    colorimetry: &crate::services::colorimetry::Colorimetry,
+) -> String {
+    use ndarray::Array1;
+    use crate::models::{to_hex, xyz_to_srgb};
+
+    let reflectance = Array1::from_vec(curve.resampled(colorimetry.grid()));
+    to_hex(xyz_to_srgb(colorimetry.reflectance_to_xyz(&reflectance)))
+}
+
+/// Every catalogued brand with its paint counts
+#[server]
+pub async fn list_admin_brands() -> Result<Vec<AdminBrand>, ServerFnError> {
+    use crate::db;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    require_admin(state.users.as_ref()).await?;
+
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let counts = db::count_brand_paints(&state.db, &overlay).await;
+    Ok(db::list_brands(&state.db, &overlay)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .into_iter()
+        .map(|row| {
+            let (paints, retired) = counts
+                .iter()
+                .find(|(brand, _, _)| *brand == row.id)
+                .map(|(_, paints, retired)| (*paints, *retired))
+                .unwrap_or_default();
+            AdminBrand {
+                details: BrandDetails::from_row(row),
+                paints,
+                retired,
+            }
+        })
+        .collect())
+}
+
+/// Add a brand to the catalogue (`create`) or edit an existing one's details
+#[server]
+pub async fn save_brand(brand: BrandDetails, create: bool) -> Result<(), ServerFnError> {
+    use crate::db;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let user = require_admin(state.users.as_ref()).await?;
+
+    let brand = brand.normalised()?;
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let before = db::get_brand(&state.db, &overlay, &brand.id)
+        .await
+        .map(BrandDetails::from_row);
+    let action = match (create, &before) {
+        (true, Some(_)) => return Err(ServerFnError::new("A brand with that id already exists")),
+        (false, None) => return Err(ServerFnError::new("Brand not found")),
+        (true, None) => AuditAction::CreateBrand,
+        (false, Some(_)) => AuditAction::UpdateBrand,
+    };
+    let entry = audit_entry(
+        &user,
+        action,
+        Some(&brand.id),
+        None,
+        serde_json::json!({ "before": before, "after": brand }),
+    );
+    state
+        .users
+        .save_catalogue_brand(&brand.clone().into_row(), &entry)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    state.paints.reload(&state.db, state.users.as_ref()).await;
+    Ok(())
+}
+
+/// Every paint of a brand, retired ones included, with stored and recomputed colours
+#[server]
+pub async fn list_admin_paints(brand: String) -> Result<Vec<AdminPaint>, ServerFnError> {
+    use crate::db;
+    use crate::models::{delta_e76, parse_hex, srgb_to_lab};
+    use crate::services::colorimetry::Colorimetry;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    require_admin(state.users.as_ref()).await?;
+
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let colorimetry = Colorimetry::default();
+    Ok(db::list_brand_paints(&state.db, &overlay, &brand)
+        .await
+        .into_iter()
+        .map(|row| {
+            let curve = row.color.curve();
+            let curve_hex = curve.as_ref().ok().map(|c| curve_hex(c, &colorimetry));
+            let lab = |hex: &str| parse_hex(hex).map(srgb_to_lab);
+            let hex_difference = match (row.color.d65_10deg_hex.as_deref(), curve_hex.as_deref()) {
+                (Some(stored), Some(computed)) => match (lab(stored), lab(computed)) {
+                    (Some(a), Some(b)) => Some(delta_e76(&a, &b)),
+                    _ => None,
+                },
+                _ => None,
+            };
+            AdminPaint {
+                attributes: row.color.attributes(),
+                curve_error: curve.err().map(|e| e.to_string()),
+                name: row.color._id,
+                hex: row.color.d65_10deg_hex,
+                curve_hex,
+                hex_difference,
+                retired_at: row.retired_at,
+            }
+        })
+        .collect())
+}
+
+/// Rename a catalogued paint and replace its metadata. Palettes using the
+/// paint follow the rename.
+#[server]
+pub async fn update_catalogue_paint(
+    brand: String,
+    name: String,
+    new_name: String,
+    attributes: PaintAttributes,
+) -> Result<(), ServerFnError> {
+    use crate::db;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let user = require_admin(state.users.as_ref()).await?;
+
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(ServerFnError::new("Paint name cannot be empty"));
+    }
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let mut paint = db::get_brand_paint(&state.db, &overlay, &brand, &name)
+        .await
+        .ok_or_else(|| ServerFnError::new("Paint not found"))?;
+    if new_name != name
+        && db::get_brand_paint(&state.db, &overlay, &brand, &new_name)
+            .await
+            .is_some()
+    {
+        return Err(ServerFnError::new("The brand already has a paint with that name"));
+    }
+    let before = paint.color.attributes();
+
+    let mut entries = Vec::new();
+    if new_name != name {
+        paint.color._id = new_name.clone();
+        entries.push(audit_entry(
+            &user,
+            AuditAction::RenamePaint,
+            Some(&brand),
+            Some(&new_name),
+            serde_json::json!({ "before": name, "after": new_name }),
+        ));
+    }
+    if attributes != before {
+        paint.color.set_attributes(&attributes);
+        entries.push(audit_entry(
+            &user,
+            AuditAction::UpdatePaint,
+            Some(&brand),
+            Some(&new_name),
+            serde_json::json!({ "before": before, "after": attributes }),
+        ));
+    }
+    if entries.is_empty() {
+        return Ok(());
+    }
+    state
+        .users
+        .save_catalogue_paints(&[paint], &entries)
+        .await
+        .map_err(|e| map_catalogue_error(e, "The brand already has a paint with that name"))?;
+    state.paints.reload(&state.db, state.users.as_ref()).await;
+
+    // Palettes follow once the catalogue has changed
+    if new_name != name {
+        state
+            .users
+            .rename_palette_paint(&brand, &name, &new_name)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Replace a paint's stored display colour with the one its curve gives
+#[server]
+pub async fn recompute_paint_hex(brand: String, name: String) -> Result<String, ServerFnError> {
+    use crate::db;
+    use crate::services::colorimetry::Colorimetry;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let user = require_admin(state.users.as_ref()).await?;
+
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let mut paint = db::get_brand_paint(&state.db, &overlay, &brand, &name)
+        .await
+        .ok_or_else(|| ServerFnError::new("Paint not found"))?;
+    let curve = paint
+        .color
+        .curve()
+        .map_err(|e| ServerFnError::new(format!("The paint's curve cannot be read: {}", e)))?;
+    let hex = curve_hex(&curve, &Colorimetry::default());
+
+    let before = paint.color.d65_10deg_hex.replace(hex.clone());
+    let entry = audit_entry(
+        &user,
+        AuditAction::RecomputeHex,
+        Some(&brand),
+        Some(&name),
+        serde_json::json!({ "before": before, "after": hex }),
+    );
+    state
+        .users
+        .save_catalogue_paints(&[paint], &[entry])
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    state.paints.reload(&state.db, state.users.as_ref()).await;
+    Ok(hex)
+}
+
+/// Store measured curves (as read by `import_measurements`) for a brand. A
+/// sample named like an existing paint replaces its curve; any other sample
+/// is added as a new paint with a detected role. Display colours are
+/// recomputed from the curves.
+#[server]
+pub async fn upload_paint_spectra(
+    brand: String,
+    filename: String,
+    paints: Vec<MeasuredTarget>,
+) -> Result<UploadSummary, ServerFnError> {
+    use ndarray::Array1;
+    use uuid::Uuid;
+    use crate::db::{self, BrandPaintRow, CurveMetadata, CurveOrigin, PaintColor, SpectralCurve};
+    use crate::models::SpectralGrid;
+    use crate::server_fns::validate_measured_curve;
+    use crate::services::colorimetry::Colorimetry;
+    use crate::services::paint_roles::detect_role;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let user = require_admin(state.users.as_ref()).await?;
+
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    if db::get_brand(&state.db, &overlay, &brand).await.is_none() {
+        return Err(ServerFnError::new("Brand not found"));
+    }
+    if paints.is_empty() {
+        return Err(ServerFnError::new("The upload has no paints"));
+    }
+
+    let colorimetry = Colorimetry::default();
+    let mut summary = UploadSummary::default();
+    let mut current = db::list_brand_paints(&state.db, &overlay, &brand).await;
+    let mut changed = Vec::new();
+    let mut entries = Vec::new();
+    for paint in paints {
+        let name = paint.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ServerFnError::new("Every uploaded paint needs a name"));
+        }
+        validate_measured_curve(&paint.reflectance)?;
+
+        let curve = SpectralCurve::new(
+            SpectralGrid::PAINT_DATA,
+            paint.reflectance,
+            CurveMetadata {
+                origin: CurveOrigin::Measured,
+                note: Some(format!("uploaded from {}", filename)),
+                ..Default::default()
+            },
+        );
+        let hex = curve_hex(&curve, &colorimetry);
+
+        let (row, action, before) = match current.iter_mut().find(|p| p.color._id == name) {
+            Some(existing) => {
+                let before = existing.color.d65_10deg_hex.replace(hex.clone());
+                existing.color.spectral_curve = Some(curve.encode());
+                summary.replaced += 1;
+                (existing.clone(), AuditAction::ReplaceCurve, before)
+            }
+            None => {
+                let role = detect_role(
+                    &Array1::from_vec(curve.resampled(colorimetry.grid())),
+                    &colorimetry,
+                );
+                let added = BrandPaintRow {
+                    brand_id: brand.clone(),
+                    base_name: Uuid::new_v4().to_string(),
+                    added: true,
+                    color: PaintColor {
+                        _id: name.clone(),
+                        spectral_curve: Some(curve.encode()),
+                        d65_10deg_hex: Some(hex.clone()),
+                        opacity: None,
+                        pigments: None,
+                        lightfastness: None,
+                        series: None,
+                        drying_speed: None,
+                        role: role.map(|r| r.code().to_string()),
+                    },
+                    retired_at: None,
+                };
+                current.push(added.clone());
+                summary.added += 1;
+                (added, AuditAction::AddPaint, None)
+            }
+        };
+        entries.push(audit_entry(
+            &user,
+            action,
+            Some(&brand),
+            Some(&name),
+            serde_json::json!({ "file": filename, "before": before, "after": hex }),
+        ));
+        changed.push(row);
+    }
+    state
+        .users
+        .save_catalogue_paints(&changed, &entries)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    state.paints.reload(&state.db, state.users.as_ref()).await;
+    Ok(summary)
+}
+
+/// Retire a discontinued paint so it is no longer offered for mixing, or restore it
+#[server]
+pub async fn set_paint_retired(brand: String, name: String, retired: bool) -> Result<(), ServerFnError> {
+    use crate::db;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let user = require_admin(state.users.as_ref()).await?;
+
+    let overlay = catalogue_overlay(state.users.as_ref()).await?;
+    let mut paint = db::get_brand_paint(&state.db, &overlay, &brand, &name)
+        .await
+        .filter(|paint| paint.retired_at.is_some() != retired)
+        .ok_or_else(|| {
+            ServerFnError::new(if retired {
+                "Paint not found or already retired"
+            } else {
+                "Paint not found or not retired"
+            })
+        })?;
+    paint.retired_at =
+        retired.then(|| chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string());
+    let action = if retired {
+        AuditAction::RetirePaint
+    } else {
+        AuditAction::RestorePaint
+    };
+    let entry = audit_entry(&user, action, Some(&brand), Some(&name), serde_json::json!({}));
+    state
+        .users
+        .save_catalogue_paints(&[paint], &[entry])
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    state.paints.reload(&state.db, state.users.as_ref()).await;
+    Ok(())
+}
+
+/// Recent catalogue changes, newest first
+#[server]
+pub async fn list_audit_log(limit: Option<i64>) -> Result<Vec<AuditEntry>, ServerFnError> {
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    require_admin(state.users.as_ref()).await?;
+
+    let limit = limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, 1000);
+    Ok(state
+        .users
+        .list_audit_log(limit)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .into_iter()
+        .filter_map(|row| {
+            Some(AuditEntry {
+                id: row.id,
+                user_email: row.user_email,
+                action: AuditAction::from_code(&row.action)?,
+                brand: row.brand_id,
+                paint: row.paint_name,
+                details: row.details,
+                created_at: row.created_at,
+            })
+        })
+        .collect())
+}
@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 pub struct SessionUser {
     pub id: String,
     pub email: String,
+    /// Shows the admin pages; admin server functions check the database instead
+    #[serde(default)]
+    pub is_admin: bool,
 }
 
 #[server]
@@ -33,7 +36,7 @@ pub async fn login(email: String, password: String) -> Result<SessionUser, Serve
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let user = auth::login(&state.db, &email, &password)
+    let user = auth::login(state.users.as_ref(), &email, &password)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
@@ -44,6 +47,7 @@ pub async fn login(email: String, password: String) -> Result<SessionUser, Serve
     let session_user = SessionUser {
         id: user.id,
         email: user.email,
+        is_admin: user.is_admin,
     };
     session.insert("user", &session_user).await?;
     Ok(session_user)
@@ -59,11 +63,11 @@ pub async fn register(email: String, password: String) -> Result<(), ServerFnErr
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let user_id = auth::register(&state.db, &email, &password)
+    let user_id = auth::register(state.users.as_ref(), &email, &password)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let token = auth::create_verification_token(&state.db, &user_id)
+    let token = auth::create_verification_token(state.users.as_ref(), &user_id)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
@@ -98,7 +102,7 @@ pub async fn verify_email(token: String) -> Result<(), ServerFnError> {
     let Extension(state) = extract::<Extension<AppState>>()
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
-    auth::verify_email(&state.db, &token)
+    auth::verify_email(state.users.as_ref(), &token)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))
 }
@@ -106,7 +110,7 @@ pub async fn verify_email(token: String) -> Result<(), ServerFnError> {
 #[server]
 pub async fn request_password_reset(email: String) -> Result<(), ServerFnError> {
     use axum::Extension;
-    use crate::{db, services::auth, state::AppState};
+    use crate::{services::auth, state::AppState};
     use leptos_axum::extract;
 
     let Extension(state) = extract::<Extension<AppState>>()
@@ -114,8 +118,8 @@ pub async fn request_password_reset(email: String) -> Result<(), ServerFnError>
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
     // Always succeed to prevent email enumeration
-    if let Some(user) = db::get_user_by_email(&state.db, &email).await {
-        if let Ok(token) = auth::create_reset_token(&state.db, &user.id).await {
+    if let Some(user) = state.users.get_user_by_email(&email).await {
+        if let Ok(token) = auth::create_reset_token(state.users.as_ref(), &user.id).await {
             let _ = state.email.send_password_reset(&email, &token).await;
         }
     }
@@ -132,7 +136,7 @@ pub async fn reset_password(token: String, password: String) -> Result<(), Serve
     let Extension(state) = extract::<Extension<AppState>>()
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
-    auth::reset_password(&state.db, &token, &password)
+    auth::reset_password(state.users.as_ref(), &token, &password)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))
 }
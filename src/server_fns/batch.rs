@@ -0,0 +1,192 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{BatchMixResult, PaintFilters};
+
+/// Most targets accepted in one batch
+pub const MAX_BATCH_TARGETS: usize = 100;
+
+/// Most batches a user may have running at once
+#[cfg(feature = "ssr")]
+const MAX_RUNNING_BATCHES: i64 = 2;
+
+/// One target of a batch: a colour in any `TargetColour` notation with an optional label
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchTarget {
+    pub label: Option<String>,
+    pub colour: String,
+}
+
+/// Progress of a batch run; `results` is filled in once it has finished
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchStatus {
+    pub total: usize,
+    pub completed: usize,
+    pub finished: bool,
+    pub results: Vec<BatchMixResult>,
+    pub error: Option<String>,
+}
+
+/// Start mixing a list of targets against one palette. The paint data is
+/// loaded once and the targets are mixed in parallel; returns a job id to
+/// poll with `batch_mix_status`
+#[server]
+pub async fn start_batch_mix(
+    targets: Vec<BatchTarget>,
+    filters: Option<PaintFilters>,
+    palette_id: Option<String>,
+    mix_choice: Option<String>,
+) -> Result<String, ServerFnError> {
+    use std::sync::Arc;
+    use rayon::prelude::*;
+    use uuid::Uuid;
+    use crate::models::{to_hex, TargetColour};
+    use crate::server_fns::{get_current_user, MixSetup};
+    use crate::services::batch::{self, BatchJob};
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    if targets.is_empty() {
+        return Err(ServerFnError::new("Add at least one target colour"));
+    }
+    if targets.len() > MAX_BATCH_TARGETS {
+        return Err(ServerFnError::new(format!(
+            "At most {} targets can be mixed at once",
+            MAX_BATCH_TARGETS
+        )));
+    }
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let now = batch::now();
+    let running = state
+        .users
+        .count_running_batch_jobs(&user.id, batch::live_since(now))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if running >= MAX_RUNNING_BATCHES {
+        return Err(ServerFnError::new(
+            "Please wait for your running batches to finish",
+        ));
+    }
+
+    let filters = filters.unwrap_or_default();
+    let setup = Arc::new(
+        MixSetup::load(
+            &state,
+            &user.id,
+            &filters,
+            palette_id.as_deref(),
+            mix_choice,
+        )
+        .await?,
+    );
+
+    let job_id = Uuid::new_v4().to_string();
+    if let Err(e) = state
+        .users
+        .delete_old_batch_jobs(now - batch::JOB_TTL.as_secs() as i64)
+        .await
+    {
+        eprintln!("Failed to delete old batch jobs: {}", e);
+    }
+    state
+        .users
+        .create_batch_job(&job_id, &user.id, targets.len() as i64, now)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let job = Arc::new(BatchJob::default());
+
+    let worker_job = job.clone();
+    let worker = tokio::task::spawn_blocking(move || {
+        targets
+            .par_iter()
+            .map(|target| {
+                let label = target
+                    .label
+                    .clone()
+                    .filter(|l| !l.trim().is_empty())
+                    .unwrap_or_else(|| target.colour.trim().to_string());
+                let result = match TargetColour::parse(&target.colour) {
+                    Ok(colour) => match setup.mix(&colour) {
+                        Ok(results) => BatchMixResult {
+                            label,
+                            target_hex: Some(to_hex(colour.to_srgb())),
+                            results,
+                            error: None,
+                        },
+                        Err(e) => BatchMixResult {
+                            label,
+                            target_hex: Some(to_hex(colour.to_srgb())),
+                            results: vec![],
+                            error: Some(e.to_string()),
+                        },
+                    },
+                    Err(e) => BatchMixResult {
+                        label,
+                        target_hex: None,
+                        results: vec![],
+                        error: Some(e),
+                    },
+                };
+                worker_job.advance();
+                result
+            })
+            .collect::<Vec<_>>()
+    });
+    tokio::spawn(batch::track(state.users.clone(), job_id.clone(), job, worker));
+
+    Ok(job_id)
+}
+
+/// Progress and, once finished, results of a batch started by `start_batch_mix`
+#[server]
+pub async fn batch_mix_status(job_id: String) -> Result<BatchStatus, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    use crate::services::batch;
+
+    let job = state
+        .users
+        .get_batch_job(&user.id, &job_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Batch not found"))?;
+
+    if batch::is_stale(&job, batch::now()) {
+        return Err(ServerFnError::new(
+            "The server running this batch stopped before it finished; please start it again",
+        ));
+    }
+    let results = match &job.results {
+        Some(json) => serde_json::from_str(json).map_err(|e| ServerFnError::new(e.to_string()))?,
+        None => vec![],
+    };
+    Ok(BatchStatus {
+        total: job.total as usize,
+        completed: job.completed as usize,
+        finished: job.finished,
+        results,
+        error: job.error,
+    })
+}
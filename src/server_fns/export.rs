@@ -0,0 +1,154 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Output format of a mixing sheet
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SheetFormat {
+    Svg,
+    Pdf,
+}
+
+impl SheetFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SheetFormat::Svg => "svg",
+            SheetFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SheetFormat::Svg => "image/svg+xml",
+            SheetFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// A generated file, base64 encoded so it can travel through a server function
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportFile {
+    pub filename: String,
+    pub mime_type: String,
+    pub data: String,
+}
+
+impl ExportFile {
+    /// Data URL for use as a download link
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl ExportFile {
+    pub(crate) fn new(filename: String, mime_type: &str, bytes: &[u8]) -> Self {
+        use base64::Engine;
+
+        ExportFile {
+            filename,
+            mime_type: mime_type.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// Decode an uploaded file sent as a base64 data URL, rejecting anything
+/// longer than `max_len` characters
+#[cfg(feature = "ssr")]
+pub(crate) fn decode_data_url(data_url: &str, max_len: usize) -> Result<Vec<u8>, ServerFnError> {
+    use base64::Engine;
+
+    if data_url.len() > max_len {
+        return Err(ServerFnError::new("File is too large"));
+    }
+    let encoded = data_url
+        .split_once(";base64,")
+        .map(|(_, data)| data)
+        .ok_or_else(|| ServerFnError::new("Invalid file data"))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Turn a name into something safe to use as a file name
+#[cfg(feature = "ssr")]
+pub(crate) fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let stem = stem
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if stem.is_empty() {
+        "export".to_string()
+    } else {
+        stem
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn render_sheet(
+    title: &str,
+    entries: &[crate::services::mixing_sheet::SheetEntry],
+    format: SheetFormat,
+) -> ExportFile {
+    use crate::services::mixing_sheet::{render_pdf, render_svg};
+
+    let bytes = match format {
+        SheetFormat::Svg => render_svg(title, entries).into_bytes(),
+        SheetFormat::Pdf => render_pdf(title, entries),
+    };
+    ExportFile::new(
+        format!("{}-mixing-sheet.{}", file_stem(title), format.extension()),
+        format.mime_type(),
+        &bytes,
+    )
+}
+
+/// Printable mixing sheet with the chosen recipe for every target in a project
+#[server]
+pub async fn export_project_sheet(id: String, format: SheetFormat) -> Result<ExportFile, ServerFnError> {
+    use crate::services::mixing_sheet::SheetEntry;
+    use crate::server_fns::get_project;
+
+    let project = get_project(id).await?;
+
+    let entries: Vec<SheetEntry> = project
+        .targets
+        .iter()
+        .map(|t| SheetEntry {
+            label: t.label.clone(),
+            target_hex: t.target_hex.clone(),
+            recipe: t.recipe().cloned(),
+        })
+        .collect();
+
+    Ok(render_sheet(&project.name, &entries, format))
+}
+
+/// Printable mixing sheet with the best recipe from each history entry
+#[server]
+pub async fn export_history_sheet(
+    starred_only: bool,
+    format: SheetFormat,
+) -> Result<ExportFile, ServerFnError> {
+    use crate::services::mixing_sheet::SheetEntry;
+    use crate::server_fns::list_mix_history;
+
+    let history = list_mix_history(starred_only, None, None).await?;
+
+    let entries: Vec<SheetEntry> = history
+        .into_iter()
+        .map(|h| SheetEntry {
+            label: h.name.unwrap_or_else(|| h.target_hex.clone()),
+            target_hex: h.target_hex,
+            recipe: h.results.into_iter().next(),
+        })
+        .collect();
+
+    let title = if starred_only { "Saved Recipes" } else { "Mix History" };
+    Ok(render_sheet(title, &entries, format))
+}
@@ -0,0 +1,288 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{MixingResult, PaintFilters};
+
+/// Where a history entry came from
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// A target colour searched with `find_paint_mix`
+    Find,
+    /// A hand-built mixture from the test mix page
+    Test,
+    /// A spectrophotometer measurement searched with `find_measured_mix`
+    Measured,
+}
+
+impl HistoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryKind::Find => "find",
+            HistoryKind::Test => "test",
+            HistoryKind::Measured => "measured",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Self {
+        match s {
+            "test" => HistoryKind::Test,
+            "measured" => HistoryKind::Measured,
+            _ => HistoryKind::Find,
+        }
+    }
+}
+
+/// A saved mix: the target, how it was mixed and what was recommended
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub kind: HistoryKind,
+    pub target_hex: String,
+    pub palette_id: Option<String>,
+    pub palette_name: Option<String>,
+    pub mix_choice: Option<String>,
+    pub filters: PaintFilters,
+    pub results: Vec<MixingResult>,
+    pub starred: bool,
+    pub name: Option<String>,
+    pub created_at: String,
+    /// ΔE to the searched colour, when searching by proximity
+    pub distance: Option<f64>,
+}
+
+#[cfg(feature = "ssr")]
+impl HistoryEntry {
+    fn from_row(row: crate::db::MixHistoryRow) -> Self {
+        HistoryEntry {
+            id: row.id,
+            kind: HistoryKind::from_code(&row.kind),
+            target_hex: row.target_hex,
+            palette_id: row.palette_id,
+            palette_name: row.palette_name,
+            mix_choice: row.mix_choice,
+            filters: row
+                .filters
+                .as_deref()
+                .and_then(|f| serde_json::from_str(f).ok())
+                .unwrap_or_default(),
+            results: serde_json::from_str(&row.results).unwrap_or_default(),
+            starred: row.starred,
+            name: row.name,
+            created_at: row.created_at,
+            distance: None,
+        }
+    }
+}
+
+/// Store a mix in the user's history
+#[cfg(feature = "ssr")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn record_mix_history(
+    users: &dyn crate::db::UserStore,
+    user_id: &str,
+    kind: HistoryKind,
+    target_hex: &str,
+    palette: Option<&crate::db::PaletteRow>,
+    mix_choice: Option<&str>,
+    filters: Option<&PaintFilters>,
+    results: &[MixingResult],
+) -> Result<(), ServerFnError> {
+    use uuid::Uuid;
+
+    let filters = filters.map(serde_json::to_string).transpose()?;
+    users
+        .insert_mix_history(
+            &Uuid::new_v4().to_string(),
+            user_id,
+            kind.as_str(),
+            target_hex,
+            palette.map(|p| p.id.as_str()),
+            palette.map(|p| p.name.as_str()),
+            mix_choice,
+            filters.as_deref(),
+            &serde_json::to_string(results)?,
+        )
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// List the user's mix history, newest first, or closest first when `near_hex` is given
+#[server]
+pub async fn list_mix_history(
+    starred_only: bool,
+    near_hex: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, ServerFnError> {
+    use crate::models::{delta_e76, parse_hex, srgb_to_lab};
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut entries: Vec<HistoryEntry> = state
+        .users
+        .list_mix_history(&user.id, starred_only)
+        .await
+        .into_iter()
+        .map(HistoryEntry::from_row)
+        .collect();
+
+    if let Some(near) = near_hex.filter(|h| !h.trim().is_empty()) {
+        let near = parse_hex(&near)
+            .map(srgb_to_lab)
+            .ok_or_else(|| ServerFnError::new("Invalid colour, expected #rrggbb"))?;
+        for entry in entries.iter_mut() {
+            entry.distance = parse_hex(&entry.target_hex)
+                .map(|rgb| delta_e76(&near, &srgb_to_lab(rgb)));
+        }
+        entries.sort_by(|a, b| {
+            a.distance
+                .unwrap_or(f64::MAX)
+                .partial_cmp(&b.distance.unwrap_or(f64::MAX))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    entries.truncate(limit.unwrap_or(100));
+    Ok(entries)
+}
+
+/// Star or unstar a history entry and give it a recipe name
+#[server]
+pub async fn update_recipe(
+    id: String,
+    starred: bool,
+    name: Option<String>,
+) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let updated = state
+        .users
+        .update_mix_history_recipe(&id, &user.id, starred, name.as_deref())
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !updated {
+        return Err(ServerFnError::new("History entry not found"));
+    }
+    Ok(())
+}
+
+/// Remove an entry from the user's history
+#[server]
+pub async fn delete_history_entry(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let deleted = state
+        .users
+        .delete_mix_history(&id, &user.id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !deleted {
+        return Err(ServerFnError::new("History entry not found"));
+    }
+    Ok(())
+}
+
+/// Re-run a target mix against the current contents of its palette (or the
+/// active palette if it has been deleted) and store the new results on the entry
+#[server]
+pub async fn rerun_recipe(id: String) -> Result<HistoryEntry, ServerFnError> {
+    use crate::models::parse_hex;
+    use crate::server_fns::{get_current_user, run_paint_mix};
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let row = state
+        .users
+        .get_mix_history(&user.id, &id)
+        .await
+        .ok_or_else(|| ServerFnError::new("History entry not found"))?;
+    let entry = HistoryEntry::from_row(row);
+
+    if entry.kind != HistoryKind::Find {
+        return Err(ServerFnError::new("Only target mixes can be re-run"));
+    }
+    let rgb = parse_hex(&entry.target_hex)
+        .ok_or_else(|| ServerFnError::new("Invalid target colour in history"))?;
+
+    // Fall back to the active palette if the original one is gone
+    let palette_id = match entry.palette_id.as_deref() {
+        Some(pid) if state.users.get_palette(&user.id, pid).await.is_some() => Some(pid),
+        _ => None,
+    };
+
+    let (palette, _, results) = run_paint_mix(
+        &state,
+        &user.id,
+        &rgb.into(),
+        &entry.filters,
+        palette_id,
+        entry.mix_choice.clone(),
+    )
+    .await?;
+
+    state
+        .users
+        .update_mix_history_results(
+            &id,
+            &user.id,
+            Some(&palette.id),
+            Some(&palette.name),
+            &serde_json::to_string(&results)?,
+        )
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(HistoryEntry {
+        palette_id: Some(palette.id),
+        palette_name: Some(palette.name),
+        results,
+        ..entry
+    })
+}
@@ -0,0 +1,128 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{MixingResult, PaintFilters};
+
+/// Largest measurement file accepted for import, as a base64 data URL
+#[cfg(feature = "ssr")]
+const MAX_IMPORT_LEN: usize = 2_000_000;
+
+/// A reflectance curve read from a spectrophotometer file
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MeasuredTarget {
+    pub name: String,
+    /// Display colour under D65
+    pub hex: String,
+    /// Reflectance in 0..=1 on `SpectralGrid::PAINT_DATA`
+    pub reflectance: Vec<f64>,
+}
+
+/// Check a measured curve has one value in 0..=1 per `SpectralGrid::PAINT_DATA` wavelength
+#[cfg(feature = "ssr")]
+pub(crate) fn validate_measured_curve(reflectance: &[f64]) -> Result<(), ServerFnError> {
+    use crate::models::SpectralGrid;
+
+    let grid = SpectralGrid::PAINT_DATA;
+    if reflectance.len() != grid.count || reflectance.iter().any(|v| !(0.0..=1.0).contains(v)) {
+        return Err(ServerFnError::new(format!(
+            "Expected {} reflectance values in 0..1 at {}",
+            grid.count, grid
+        )));
+    }
+    Ok(())
+}
+
+/// Read spectral measurements from a CGATS, CxF3 or CSV file (sent as a base64 data URL)
+#[server]
+pub async fn import_measurements(
+    filename: String,
+    data_url: String,
+) -> Result<Vec<MeasuredTarget>, ServerFnError> {
+    use ndarray::Array1;
+    use crate::models::{to_hex, xyz_to_srgb};
+    use crate::server_fns::{decode_data_url, get_current_user};
+    use crate::models::SpectralGrid;
+    use crate::services::colorimetry::{Colorimetry, Illuminant};
+    use crate::services::spectral_import;
+
+    get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    let bytes = decode_data_url(&data_url, MAX_IMPORT_LEN)?;
+    let samples = spectral_import::import_measurements(&filename, &bytes).map_err(ServerFnError::new)?;
+
+    let colorimetry = Colorimetry::with_grid(Illuminant::D65, SpectralGrid::PAINT_DATA);
+    Ok(samples
+        .into_iter()
+        .map(|sample| {
+            let xyz = colorimetry.reflectance_to_xyz(&Array1::from_vec(sample.reflectance.clone()));
+            MeasuredTarget {
+                name: sample.name,
+                hex: to_hex(xyz_to_srgb(xyz)),
+                reflectance: sample.reflectance,
+            }
+        })
+        .collect())
+}
+
+/// Find paint mixes for a measured reflectance curve, used as the target
+/// directly instead of reconstructing one from a colour with LHTSS
+#[server]
+pub async fn find_measured_mix(
+    target: MeasuredTarget,
+    filters: Option<PaintFilters>,
+    palette_id: Option<String>,
+    mix_choice: Option<String>,
+) -> Result<Vec<MixingResult>, ServerFnError> {
+    use ndarray::Array1;
+    use crate::models::SpectralGrid;
+    use crate::server_fns::{get_current_user, record_mix_history, HistoryKind, MixSetup};
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    validate_measured_curve(&target.reflectance)?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let filters = filters.unwrap_or_default();
+    let setup = MixSetup::load(
+        &state,
+        &user.id,
+        &filters,
+        palette_id.as_deref(),
+        mix_choice,
+    )
+    .await?;
+    let reflectance = setup
+        .grid()
+        .resample(&SpectralGrid::PAINT_DATA, &target.reflectance)
+        .ok_or_else(|| ServerFnError::new("Could not resample the measured curve"))?;
+    let results = setup.mix_reflectance(&Array1::from_vec(reflectance))?;
+
+    // Keep the result in the user's history; a failure here should not lose the mix
+    if let Err(e) = record_mix_history(
+        state.users.as_ref(),
+        &user.id,
+        HistoryKind::Measured,
+        &target.hex,
+        Some(&setup.palette),
+        Some(&setup.mix_choice),
+        Some(&filters),
+        &results,
+    )
+    .await
+    {
+        eprintln!("Failed to record mix history for {}: {}", user.id, e);
+    }
+
+    Ok(results)
+}
@@ -1,5 +1,23 @@
+mod admin;
 mod auth;
+mod batch;
+mod export;
+mod history;
+mod measurements;
 mod paint;
+mod palette;
+mod project;
+mod swatches;
+mod user_paints;
 
+pub use admin::*;
 pub use auth::*;
+pub use batch::*;
+pub use export::*;
+pub use history::*;
+pub use measurements::*;
 pub use paint::*;
+pub use palette::*;
+pub use project::*;
+pub use swatches::*;
+pub use user_paints::*;
@@ -1,34 +1,79 @@
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::models::MixingResult;
+use crate::models::{MixingResult, PaintAttributes, PaintFilters};
 
 /// Paint brand info for the frontend
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct PaintBrand {
     pub id: String,
     pub name: String,
+    pub manufacturer: Option<String>,
+    pub medium: Option<String>,
+    /// Whether any of its paints has pigment, opacity, lightfastness, series
+    /// or drying data; filters cannot act on a brand without any
+    pub has_metadata: bool,
 }
 
 /// Paint color info for the frontend
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct PaintColorInfo {
     pub id: String,
+    pub brand: String,
+    /// Display name of the brand
+    pub brand_name: String,
     pub hex: String,
+    pub attributes: PaintAttributes,
+    /// Why the paint's spectral data cannot be used for mixing, if it cannot
+    pub curve_error: Option<String>,
 }
 
-/// User paint settings
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
-pub struct UserPaintSettings {
-    pub mix_choice: String,
+#[cfg(feature = "ssr")]
+impl PaintColorInfo {
+    fn from_paint(
+        brand: &str,
+        brand_name: &str,
+        paint: &crate::services::paint_cache::CachedPaint,
+    ) -> Self {
+        PaintColorInfo {
+            id: paint.name.clone(),
+            brand: brand.to_string(),
+            brand_name: brand_name.to_string(),
+            hex: paint.hex.clone(),
+            attributes: paint.attributes.clone(),
+            curve_error: paint.curves.as_ref().err().cloned(),
+        }
+    }
+}
+
+/// A paint in a user's palette, identified by brand id and paint name
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PalettePaint {
     pub brand: String,
-    pub colors: Vec<String>,
+    pub name: String,
+}
+
+/// A brand's display name from the catalogue, where the user's own paints
+/// are one more brand. Unknown ids are shown as they are.
+#[cfg(feature = "ssr")]
+pub(crate) fn brand_name(state: &crate::state::AppState, brand: &str) -> String {
+    use crate::server_fns::{USER_PAINTS_BRAND, USER_PAINTS_BRAND_NAME};
+
+    if brand == USER_PAINTS_BRAND {
+        return USER_PAINTS_BRAND_NAME.to_string();
+    }
+    state
+        .paints
+        .catalogue()
+        .brand(brand)
+        .map(|b| b.row.display_name.clone())
+        .unwrap_or_else(|| brand.to_string())
 }
 
 /// Get available paint brands
 #[server]
 pub async fn get_paint_brands() -> Result<Vec<PaintBrand>, ServerFnError> {
-    use crate::db;
+    use crate::server_fns::{get_current_user, USER_PAINTS_BRAND, USER_PAINTS_BRAND_NAME};
     use axum::Extension;
     use leptos_axum::extract;
     use crate::state::AppState;
@@ -37,32 +82,44 @@ pub async fn get_paint_brands() -> Result<Vec<PaintBrand>, ServerFnError> {
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let brands = db::get_paint_brands(&state.db).await;
-
-    Ok(brands
-        .into_iter()
-        .map(|id| {
-            let name = id
-                .replace('_', " ")
-                .split_whitespace()
-                .map(|w| {
-                    let mut chars = w.chars();
-                    match chars.next() {
-                        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
-                        None => String::new(),
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
-            PaintBrand { id, name }
+    let mut brands: Vec<PaintBrand> = state
+        .paints
+        .catalogue()
+        .brands()
+        .iter()
+        .map(|b| PaintBrand {
+            id: b.row.id.clone(),
+            name: b.row.display_name.clone(),
+            manufacturer: b.row.manufacturer.clone(),
+            medium: b.row.medium.clone(),
+            has_metadata: b.paints.iter().any(|p| p.attributes.has_metadata()),
         })
-        .collect())
+        .collect();
+
+    // The user's own paints come first, once they have added any
+    if let Some(user) = get_current_user().await? {
+        let paints = state.users.get_user_paint_colors(&user.id).await;
+        if !paints.is_empty() {
+            brands.insert(
+                0,
+                PaintBrand {
+                    id: USER_PAINTS_BRAND.to_string(),
+                    name: USER_PAINTS_BRAND_NAME.to_string(),
+                    manufacturer: None,
+                    medium: None,
+                    has_metadata: paints.iter().any(|p| p.attributes().has_metadata()),
+                },
+            );
+        }
+    }
+
+    Ok(brands)
 }
 
 /// Get paint colors for a brand
 #[server]
 pub async fn get_paint_colors(brand: String) -> Result<Vec<PaintColorInfo>, ServerFnError> {
-    use crate::db;
+    use crate::server_fns::get_current_user;
     use axum::Extension;
     use leptos_axum::extract;
     use crate::state::AppState;
@@ -71,21 +128,22 @@ pub async fn get_paint_colors(brand: String) -> Result<Vec<PaintColorInfo>, Serv
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let colors = db::get_paint_colors(&state.db, &brand).await;
+    let user = get_current_user().await?;
+    let user_id = user.as_ref().map(|u| u.id.as_str()).unwrap_or_default();
+    let colors = paint_colors_for(&state, user_id, &brand).await;
+    let name = brand_name(&state, &brand);
 
     Ok(colors
-        .into_iter()
-        .map(|c| PaintColorInfo {
-            id: c._id,
-            hex: c.d65_10deg_hex.unwrap_or_else(|| "#808080".to_string()),
-        })
+        .iter()
+        .map(|c| PaintColorInfo::from_paint(&brand, &name, c))
         .collect())
 }
 
-/// Get user's paint settings
+/// Get the paints in a palette (the active one by default), across all brands
 #[server]
-pub async fn get_user_paint_settings() -> Result<UserPaintSettings, ServerFnError> {
-    use crate::db;
+pub async fn get_palette_colors(
+    palette_id: Option<String>,
+) -> Result<Vec<PaintColorInfo>, ServerFnError> {
     use crate::server_fns::get_current_user;
 
     let user = get_current_user()
@@ -100,59 +158,152 @@ pub async fn get_user_paint_settings() -> Result<UserPaintSettings, ServerFnErro
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let settings = db::get_user_settings(&state.db, &user.id).await;
-
-    match settings {
-        Some(s) => {
-            let selected: serde_json::Value = s
-                .selected_colors
-                .as_deref()
-                .and_then(|s| serde_json::from_str(s).ok())
-                .unwrap_or(serde_json::json!({}));
-
-            // Extract brand and colors from the JSON structure
-            let (brand, colors) = if let Some(obj) = selected.as_object() {
-                if let Some((brand_name, colors_val)) = obj.iter().next() {
-                    let colors = colors_val
-                        .as_array()
-                        .map(|arr| {
-                            arr.iter()
-                                .filter_map(|v| v.as_str().map(String::from))
-                                .collect()
-                        })
-                        .unwrap_or_default();
-                    (brand_name.clone(), colors)
-                } else {
-                    (String::new(), vec![])
+    let selected = match resolve_palette(state.users.as_ref(), &user.id, palette_id.as_deref()).await {
+        Ok(palette) => palette.selected_by_brand(),
+        Err(_) => Default::default(),
+    };
+
+    let mut colors = Vec::new();
+    for (brand, names) in selected {
+        let name = brand_name(&state, &brand);
+        colors.extend(
+            paint_colors_for(&state, &user.id, &brand)
+                .await
+                .iter()
+                .filter(|c| names.contains(&c.name))
+                .map(|c| PaintColorInfo::from_paint(&brand, &name, c)),
+        );
+    }
+    Ok(colors)
+}
+
+/// A brand's paints, where the user's own paints are one more brand. Catalogue
+/// paints come from the paint cache; only the user's own are loaded and decoded.
+#[cfg(feature = "ssr")]
+pub(crate) async fn paint_colors_for(
+    state: &crate::state::AppState,
+    user_id: &str,
+    brand: &str,
+) -> std::sync::Arc<Vec<crate::services::paint_cache::CachedPaint>> {
+    use crate::server_fns::USER_PAINTS_BRAND;
+    use crate::services::paint_cache::CachedPaint;
+
+    if brand == USER_PAINTS_BRAND {
+        if user_id.is_empty() {
+            return Default::default();
+        }
+        let paints = state.users.get_user_paint_colors(user_id).await;
+        std::sync::Arc::new(paints.into_iter().map(CachedPaint::from_color).collect())
+    } else {
+        state
+            .paints
+            .catalogue()
+            .brand(brand)
+            .map(|b| b.paints.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Look up the requested palette, or the user's active palette when none is given
+#[cfg(feature = "ssr")]
+async fn resolve_palette(
+    users: &dyn crate::db::UserStore,
+    user_id: &str,
+    palette_id: Option<&str>,
+) -> Result<crate::db::PaletteRow, ServerFnError> {
+    match palette_id {
+        Some(id) => users
+            .get_palette(user_id, id)
+            .await
+            .ok_or_else(|| ServerFnError::new("Palette not found")),
+        None => users
+            .get_active_palette(user_id)
+            .await
+            .ok_or_else(|| ServerFnError::new("Please configure your paint settings first")),
+    }
+}
+
+/// Spectral data for the selected paints of every brand in a palette, on the
+/// paint data grid the mixing service uses. The palette's chosen white and black take
+/// those roles over any others. Paints whose data cannot be decoded are left
+/// out and described in the second list.
+#[cfg(feature = "ssr")]
+async fn load_palette_paints(
+    state: &crate::state::AppState,
+    user_id: &str,
+    palette: &crate::db::PaletteRow,
+    filters: &PaintFilters,
+) -> (Vec<crate::services::paint_mixing::MixPaint>, Vec<String>) {
+    use crate::models::PaintRole;
+    use crate::server_fns::decode_role_choice;
+    use crate::services::paint_mixing::MixPaint;
+
+    let selected = palette.selected_by_brand();
+    let choices = [
+        (PaintRole::White, decode_role_choice(palette.white.as_deref())),
+        (PaintRole::Black, decode_role_choice(palette.black.as_deref())),
+    ];
+
+    let mut paints = Vec::new();
+    let mut unusable = Vec::new();
+    for (brand, names) in &selected {
+        let display_name = brand_name(state, brand);
+        for c in paint_colors_for(state, user_id, brand).await.iter() {
+            if !names.contains(&c.name) || !filters.matches(&c.attributes) {
+                continue;
+            }
+            let curves = match &c.curves {
+                Ok(curves) => curves,
+                Err(e) => {
+                    eprintln!("WARNING: skipping {} / {}: {}", brand, c.name, e);
+                    unusable.push(format!("{} ({}): {}", c.name, display_name, e));
+                    continue;
                 }
-            } else {
-                (String::new(), vec![])
             };
-
-            Ok(UserPaintSettings {
-                mix_choice: s.colour_mix_choice.unwrap_or_default(),
-                brand,
-                colors,
-            })
+            let mut attributes = c.attributes.clone();
+            let chosen = choices.iter().find_map(|(role, choice)| {
+                choice
+                    .as_ref()
+                    .is_some_and(|p| &p.brand == brand && p.name == c.name)
+                    .then_some(*role)
+            });
+            if chosen.is_some() {
+                attributes.role = chosen;
+            }
+            paints.push(MixPaint {
+                attributes,
+                preferred: chosen.is_some(),
+                reflectance: curves.reflectance.clone(),
+                ks: curves.ks.clone(),
+                hex: c.hex.clone(),
+                name: c.name.clone(),
+                brand: display_name.clone(),
+            });
         }
-        None => Ok(UserPaintSettings::default()),
     }
+    (paints, unusable)
 }
 
-/// Save user's paint settings
+/// Find optimal paint combinations for a target color using a palette (the
+/// active one by default), optionally overriding its mix strategy and
+/// restricting it by paint attributes. The target may be written in any
+/// notation `TargetColour::parse` accepts (hex, Lab, LCh, XYZ, HSL, OKLCH, Munsell)
 #[server]
-pub async fn save_user_paint_settings(
-    mix_choice: String,
-    brand: String,
-    colors: Vec<String>,
-) -> Result<(), ServerFnError> {
-    use crate::db;
+pub async fn find_paint_mix(
+    target: String,
+    filters: Option<PaintFilters>,
+    palette_id: Option<String>,
+    mix_choice: Option<String>,
+) -> Result<Vec<MixingResult>, ServerFnError> {
+    use crate::models::{to_hex, TargetColour};
     use crate::server_fns::get_current_user;
 
     let user = get_current_user()
         .await?
         .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
 
+    let target = TargetColour::parse(&target).map_err(ServerFnError::new)?;
+
     use axum::Extension;
     use leptos_axum::extract;
     use crate::state::AppState;
@@ -161,134 +312,170 @@ pub async fn save_user_paint_settings(
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    // Store as JSON: { "brand_name": ["color1", "color2", ...] }
-    let selected_colors = serde_json::json!({ brand: colors }).to_string();
+    let filters = filters.unwrap_or_default();
+    let (palette, mix_choice, results) = run_paint_mix(
+        &state,
+        &user.id,
+        &target,
+        &filters,
+        palette_id.as_deref(),
+        mix_choice,
+    )
+    .await?;
+
+    // Keep the result in the user's history; a failure here should not lose the mix
+    if let Err(e) = crate::server_fns::record_mix_history(
+        state.users.as_ref(),
+        &user.id,
+        crate::server_fns::HistoryKind::Find,
+        &to_hex(target.to_srgb()),
+        Some(&palette),
+        Some(&mix_choice),
+        Some(&filters),
+        &results,
+    )
+    .await
+    {
+        eprintln!("Failed to record mix history for {}: {}", user.id, e);
+    }
 
-    db::upsert_user_settings(&state.db, &user.id, &user.email, &mix_choice, &selected_colors)
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    Ok(results)
+}
 
-    Ok(())
+/// A mix strategy by name, as the mixing search reads it
+#[cfg(feature = "ssr")]
+pub(crate) fn parse_mix_choice(
+    mix_choice: &str,
+) -> Result<crate::models::MixChoice, ServerFnError> {
+    mix_choice
+        .parse()
+        .map_err(|_| ServerFnError::new(format!("Unknown mix strategy '{}'", mix_choice)))
 }
 
-/// Find optimal paint combinations for a target color
-#[server]
-pub async fn find_paint_mix(
-    r: u8,
-    g: u8,
-    b: u8,
-) -> Result<Vec<MixingResult>, ServerFnError> {
-    use crate::db;
-    use crate::server_fns::get_current_user;
-    use crate::services::paint_mixing::{get_default_t_matrix, PaintMixingService};
-    use ndarray::Array1;
+/// Palette, strategy and paint data for a mixing run, loaded once and shared
+/// by every target mixed against it
+#[cfg(feature = "ssr")]
+pub(crate) struct MixSetup {
+    pub palette: crate::db::PaletteRow,
+    pub mix_choice: String,
+    paints: Vec<crate::services::paint_mixing::MixPaint>,
+    service: crate::services::paint_mixing::PaintMixingService,
+}
 
-    let user = get_current_user()
-        .await?
-        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+#[cfg(feature = "ssr")]
+impl MixSetup {
+    /// Load a palette's paints, restricted by the filters
+    pub(crate) async fn load(
+        state: &crate::state::AppState,
+        user_id: &str,
+        filters: &PaintFilters,
+        palette_id: Option<&str>,
+        mix_choice: Option<String>,
+    ) -> Result<Self, ServerFnError> {
+        use crate::services::colorimetry::Colorimetry;
+        use crate::services::paint_mixing::PaintMixingService;
+
+        // Get the palette to mix from
+        let palette = resolve_palette(state.users.as_ref(), user_id, palette_id).await?;
+        let mix_choice = mix_choice.unwrap_or(palette.mix_choice.clone());
+        parse_mix_choice(&mix_choice)?;
+
+        let selected = palette.selected_by_brand();
+        if selected.values().all(|names| names.is_empty()) {
+            return Err(ServerFnError::new("Please select at least some paints"));
+        }
 
-    use axum::Extension;
-    use leptos_axum::extract;
-    use crate::state::AppState;
+        // Building the target lookup table on first use blocks, so keep it off
+        // the async workers
+        let service =
+            tokio::task::spawn_blocking(|| PaintMixingService::new(Colorimetry::default()))
+                .await
+                .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let Extension(state) = extract::<Extension<AppState>>()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
+        // Load spectral data for the selected paints of every brand
+        let (paints, unusable) = load_palette_paints(state, user_id, &palette, filters).await;
 
-    // Get user settings
-    let settings = db::get_user_settings(&state.db, &user.id)
-        .await
-        .ok_or_else(|| ServerFnError::new("Please configure your paint settings first"))?;
-
-    let mix_choice = settings
-        .colour_mix_choice
-        .unwrap_or_else(|| "black + white + 2 colours".to_string());
-
-    let selected: serde_json::Value = settings
-        .selected_colors
-        .as_deref()
-        .and_then(|s| serde_json::from_str(s).ok())
-        .ok_or_else(|| ServerFnError::new("Please select your paints first"))?;
-
-    // Extract brand and colors
-    let (brand, color_names): (String, Vec<String>) = selected
-        .as_object()
-        .and_then(|obj| obj.iter().next())
-        .map(|(brand, colors)| {
-            let names = colors
-                .as_array()
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_str().map(String::from))
-                        .collect()
-                })
-                .unwrap_or_default();
-            (brand.clone(), names)
-        })
-        .ok_or_else(|| ServerFnError::new("Invalid paint selection"))?;
+        if paints.len() < 3 {
+            let mut message = if filters.is_empty() {
+                "Not enough paint data. Please select more colors.".to_string()
+            } else {
+                "Not enough paints match the filters. Please relax them or select more colors."
+                    .to_string()
+            };
+            if !unusable.is_empty() {
+                message.push_str(&format!(" Unusable paints: {}", unusable.join("; ")));
+            }
+            return Err(ServerFnError::new(message));
+        }
 
-    if color_names.is_empty() {
-        return Err(ServerFnError::new("Please select at least some paints"));
+        Ok(MixSetup {
+            palette,
+            mix_choice,
+            paints,
+            service,
+        })
     }
 
-    // Get paint data
-    let all_colors = db::get_paint_colors(&state.db, &brand).await;
-
-    // Filter to selected colors and convert spectral data
-    let paint_data: Vec<(String, Array1<f64>, String)> = all_colors
-        .into_iter()
-        .filter(|c| color_names.contains(&c._id))
-        .filter_map(|c| {
-            let spectral = c.spectral_curve?;
-            // Decode spectral curve from bincode (Vec<u8> -> Vec<f64>)
-            let curve: Vec<f64> = bincode::deserialize(&spectral).ok()?;
-            let hex = c.d65_10deg_hex.unwrap_or_else(|| "#808080".to_string());
-            Some((c._id, Array1::from_vec(curve), hex))
-        })
-        .collect();
+    /// Wavelengths target curves passed to `mix_reflectance` must be sampled at
+    pub(crate) fn grid(&self) -> crate::models::SpectralGrid {
+        self.service.grid()
+    }
 
-    if paint_data.len() < 3 {
-        return Err(ServerFnError::new(
-            "Not enough paint data. Please select more colors.",
-        ));
+    /// Find the best mixes for one target colour
+    pub(crate) fn mix(&self, target: &crate::models::TargetColour) -> Result<Vec<MixingResult>, ServerFnError> {
+        let target = self
+            .service
+            .calculate_target_reflectance(target.to_xyz())
+            .map_err(|e| ServerFnError::new(format!("Failed to compute target reflectance: {}", e)))?;
+        self.mix_reflectance(&target)
     }
 
-    // Create mixing service and find combinations
-    let service = PaintMixingService::new(get_default_t_matrix());
-
-    let target = service
-        .calculate_target_reflectance([r, g, b])
-        .map_err(|e| ServerFnError::new(format!("Failed to compute target reflectance: {}", e)))?;
-
-    // Verify paint data dimensions match target
-    for (name, curve, _) in &paint_data {
-        if curve.len() != target.len() {
-            return Err(ServerFnError::new(format!(
-                "Paint '{}' has {} spectral values, expected {}",
-                name,
-                curve.len(),
-                target.len()
-            )));
+    /// Find the best mixes for a target reflectance curve on the setup's grid,
+    /// such as one measured with a spectrophotometer
+    pub(crate) fn mix_reflectance(
+        &self,
+        target: &ndarray::Array1<f64>,
+    ) -> Result<Vec<MixingResult>, ServerFnError> {
+        // Verify paint data dimensions match target
+        for paint in &self.paints {
+            if paint.reflectance.len() != target.len() {
+                return Err(ServerFnError::new(format!(
+                    "Paint '{}' has {} spectral values, expected {}",
+                    paint.name,
+                    paint.reflectance.len(),
+                    target.len()
+                )));
+            }
         }
-    }
 
-    let results = service
-        .find_combinations(&target, &paint_data, &mix_choice)
-        .map_err(|e| ServerFnError::new(format!("Failed to find combinations: {}", e)))?;
+        self.service
+            .find_combinations(target, &self.paints, &self.mix_choice)
+            .map_err(|e| ServerFnError::new(format!("Failed to find combinations: {}", e)))
+    }
+}
 
-    Ok(results)
+/// Run the mixing search for a target colour against a palette
+#[cfg(feature = "ssr")]
+pub(crate) async fn run_paint_mix(
+    state: &crate::state::AppState,
+    user_id: &str,
+    target: &crate::models::TargetColour,
+    filters: &PaintFilters,
+    palette_id: Option<&str>,
+    mix_choice: Option<String>,
+) -> Result<(crate::db::PaletteRow, String, Vec<MixingResult>), ServerFnError> {
+    let setup = MixSetup::load(state, user_id, filters, palette_id, mix_choice).await?;
+    let results = setup.mix(target)?;
+    Ok((setup.palette, setup.mix_choice, results))
 }
 
 /// Test a custom paint mixture
 #[server]
 pub async fn test_paint_mix(
-    paints: Vec<String>,
+    paints: Vec<PalettePaint>,
     weights: Vec<f64>,
 ) -> Result<String, ServerFnError> {
-    use crate::db;
     use crate::server_fns::get_current_user;
-    use crate::services::optimization::kubelka_munk_mix;
-    use ndarray::Array1;
 
     let user = get_current_user()
         .await?
@@ -302,108 +489,132 @@ pub async fn test_paint_mix(
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    // Get user settings for brand
-    let settings = db::get_user_settings(&state.db, &user.id)
-        .await
-        .ok_or_else(|| ServerFnError::new("Please configure your paint settings first"))?;
+    mix_test_paints(&state, &user.id, &paints, &weights).await
+}
 
-    let selected: serde_json::Value = settings
-        .selected_colors
-        .as_deref()
-        .and_then(|s| serde_json::from_str(s).ok())
-        .ok_or_else(|| ServerFnError::new("Please select your paints first"))?;
+/// Save a custom paint mixture to the user's history
+#[server]
+pub async fn save_test_mix(
+    paints: Vec<PalettePaint>,
+    weights: Vec<f64>,
+) -> Result<(), ServerFnError> {
+    use crate::server_fns::{get_current_user, record_mix_history, HistoryKind};
 
-    let brand = selected
-        .as_object()
-        .and_then(|obj| obj.keys().next())
-        .ok_or_else(|| ServerFnError::new("Invalid paint selection"))?;
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
 
-    // Get paint data
-    let all_colors = db::get_paint_colors(&state.db, brand).await;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
 
-    // For a single paint, return its database hex value directly
-    if paints.len() == 1 {
-        let paint_name = &paints[0];
-        if let Some(color) = all_colors.iter().find(|c| &c._id == paint_name) {
-            return Ok(color.d65_10deg_hex.clone().unwrap_or_else(|| "#808080".to_string()));
-        }
-    }
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    // Get spectral data for requested paints
-    let paint_reflectances: Vec<Array1<f64>> = paints
-        .iter()
-        .filter_map(|paint_name| {
-            all_colors.iter().find(|c| &c._id == paint_name).and_then(|c| {
-                let spectral = c.spectral_curve.as_ref()?;
-                let curve: Vec<f64> = bincode::deserialize(spectral).ok()?;
-                Some(Array1::from_vec(curve))
-            })
-        })
-        .collect();
+    let mixed_hex = mix_test_paints(&state, &user.id, &paints, &weights).await?;
 
-    if paint_reflectances.len() != paints.len() {
-        return Err(ServerFnError::new("Could not find all paint data"));
+    // Look up each paint's own swatch for the recipe line
+    let mut hex_colors = Vec::with_capacity(paints.len());
+    for paint in &paints {
+        hex_colors.push(mix_test_paints(&state, &user.id, std::slice::from_ref(paint), &[1.0]).await?);
     }
 
-    // Mix the reflectances using Kubelka-Munk theory
-    let mixed = kubelka_munk_mix(&paint_reflectances, &weights);
-
-    // Convert mixed reflectance to XYZ using CIE 1931 2-degree observer and D65 illuminant
-    // Wavelengths: 400nm to 700nm in 10nm steps (31 values)
-    // These are the standard color matching functions scaled by D65 illuminant
-    let cmf_x: [f64; 31] = [
-        0.0143, 0.0435, 0.1344, 0.2839, 0.3483, 0.3362, 0.2908, 0.1954, 0.0956,
-        0.0320, 0.0049, 0.0093, 0.0633, 0.1655, 0.2904, 0.4334, 0.5945, 0.7621,
-        0.9163, 1.0263, 1.0622, 1.0026, 0.8544, 0.6424, 0.4479, 0.2835, 0.1649,
-        0.0874, 0.0468, 0.0227, 0.0114,
-    ];
-    let cmf_y: [f64; 31] = [
-        0.0004, 0.0012, 0.0040, 0.0116, 0.0230, 0.0380, 0.0600, 0.0910, 0.1390,
-        0.2080, 0.3230, 0.5030, 0.7100, 0.8620, 0.9540, 0.9950, 0.9950, 0.9520,
-        0.8700, 0.7570, 0.6310, 0.5030, 0.3810, 0.2650, 0.1750, 0.1070, 0.0610,
-        0.0320, 0.0170, 0.0082, 0.0041,
-    ];
-    let cmf_z: [f64; 31] = [
-        0.0679, 0.2074, 0.6456, 1.3856, 1.7471, 1.7721, 1.6692, 1.2876, 0.8130,
-        0.4652, 0.2720, 0.1582, 0.0782, 0.0422, 0.0203, 0.0087, 0.0039, 0.0021,
-        0.0017, 0.0011, 0.0008, 0.0003, 0.0002, 0.0000, 0.0000, 0.0000, 0.0000,
-        0.0000, 0.0000, 0.0000, 0.0000,
-    ];
+    let mut brands = Vec::with_capacity(paints.len());
+    for paint in &paints {
+        brands.push(brand_name(&state, &paint.brand));
+    }
 
-    // Compute XYZ by integrating reflectance * CMF
-    let mut x = 0.0;
-    let mut y = 0.0;
-    let mut z = 0.0;
-    for i in 0..31 {
-        x += mixed[i] * cmf_x[i];
-        y += mixed[i] * cmf_y[i];
-        z += mixed[i] * cmf_z[i];
+    let result = MixingResult {
+        paints: paints.iter().map(|p| p.name.clone()).collect(),
+        brands,
+        weights,
+        error: 0.0,
+        hex_colors,
+        warnings: vec![],
+    };
+
+    record_mix_history(
+        state.users.as_ref(),
+        &user.id,
+        HistoryKind::Test,
+        &mixed_hex,
+        None,
+        None,
+        None,
+        &[result],
+    )
+    .await
+}
+
+/// Mix specific paints with the given weights and return the resulting hex colour
+#[cfg(feature = "ssr")]
+pub(crate) async fn mix_test_paints(
+    state: &crate::state::AppState,
+    user_id: &str,
+    paints: &[PalettePaint],
+    weights: &[f64],
+) -> Result<String, ServerFnError> {
+    use crate::models::{to_hex, xyz_to_srgb};
+    use crate::services::colorimetry::Colorimetry;
+    use crate::services::optimization::mix_ks_into;
+    use std::collections::BTreeMap;
+
+    if paints.is_empty() {
+        return Err(ServerFnError::new("Choose at least one paint to mix"));
+    }
+    if weights.len() != paints.len() {
+        return Err(ServerFnError::new(format!(
+            "{} weights given for {} paints",
+            weights.len(),
+            paints.len()
+        )));
+    }
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err(ServerFnError::new("Weights must be positive numbers or 0"));
+    }
+    if weights.iter().all(|&w| w == 0.0) {
+        return Err(ServerFnError::new("Give at least one paint a weight above 0"));
     }
 
-    // Normalize to D65 white point (sum of Y should equal 1 for perfect white)
-    let y_sum: f64 = cmf_y.iter().sum();
-    x /= y_sum;
-    y /= y_sum;
-    z /= y_sum;
-
-    // XYZ to linear sRGB (D65 reference)
-    let r_lin = 3.2404542 * x - 1.5371385 * y - 0.4985314 * z;
-    let g_lin = -0.9692660 * x + 1.8760108 * y + 0.0415560 * z;
-    let b_lin = 0.0556434 * x - 0.2040259 * y + 1.0572252 * z;
-
-    // Apply sRGB gamma correction
-    let gamma = |c: f64| {
-        let c = c.max(0.0).min(1.0);
-        if c <= 0.0031308 {
-            12.92 * c
-        } else {
-            1.055 * c.powf(1.0 / 2.4) - 0.055
+    // Get paint data for every brand involved in the mix
+    let mut all_colors = BTreeMap::new();
+    for paint in paints {
+        if !all_colors.contains_key(&paint.brand) {
+            let colors = paint_colors_for(state, user_id, &paint.brand).await;
+            all_colors.insert(paint.brand.clone(), colors);
         }
+    }
+    let find_color = |paint: &PalettePaint| {
+        all_colors
+            .get(&paint.brand)
+            .and_then(|colors| colors.iter().find(|c| c.name == paint.name))
     };
 
-    let r = (gamma(r_lin) * 255.0).round() as u8;
-    let g = (gamma(g_lin) * 255.0).round() as u8;
-    let b = (gamma(b_lin) * 255.0).round() as u8;
+    // For a single paint, return its database hex value directly
+    if paints.len() == 1 {
+        if let Some(color) = find_color(&paints[0]) {
+            return Ok(color.hex.clone());
+        }
+    }
+
+    // Get the precomputed K/S curves of the requested paints
+    let colorimetry = Colorimetry::default();
+    let paint_ks = paints
+        .iter()
+        .map(|paint| {
+            let color = find_color(paint)
+                .ok_or_else(|| ServerFnError::new(format!("Paint '{}' not found", paint.name)))?;
+            let curves = color
+                .curves
+                .as_ref()
+                .map_err(|e| ServerFnError::new(format!("Paint '{}': {}", paint.name, e)))?;
+            Ok(curves.ks.as_slice().expect("K/S curves are contiguous"))
+        })
+        .collect::<Result<Vec<_>, ServerFnError>>()?;
 
-    Ok(format!("#{:02x}{:02x}{:02x}", r, g, b))
+    // Mix using Kubelka-Munk theory and view the result under D65
+    let mut mixed = vec![0.0; colorimetry.grid().count];
+    mix_ks_into(&paint_ks, weights, &mut mixed);
+    Ok(to_hex(xyz_to_srgb(colorimetry.reflectance_to_xyz(mixed.as_slice()))))
 }
@@ -0,0 +1,288 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::server_fns::PalettePaint;
+
+/// A named palette with its own paints and default mix strategy
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Palette {
+    pub id: String,
+    pub name: String,
+    pub mix_choice: String,
+    pub paints: Vec<PalettePaint>,
+    /// Paint to mix with as white, instead of the lightest paint with the white role
+    pub white: Option<PalettePaint>,
+    /// Paint to mix with as black, instead of the darkest paint with the black role
+    pub black: Option<PalettePaint>,
+    pub active: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl Palette {
+    fn from_row(row: crate::db::PaletteRow, active_id: Option<&str>) -> Self {
+        let paints = row
+            .selected_by_brand()
+            .into_iter()
+            .flat_map(|(brand, names)| {
+                names.into_iter().map(move |name| PalettePaint {
+                    brand: brand.clone(),
+                    name,
+                })
+            })
+            .collect();
+        let white = decode_role_choice(row.white.as_deref());
+        let black = decode_role_choice(row.black.as_deref());
+        Palette {
+            active: active_id == Some(row.id.as_str()),
+            id: row.id,
+            name: row.name,
+            mix_choice: row.mix_choice,
+            paints,
+            white,
+            black,
+        }
+    }
+}
+
+/// Store paints as JSON: { "brand_a": ["color1", ...], "brand_b": [...] }
+#[cfg(feature = "ssr")]
+fn encode_paints(paints: Vec<PalettePaint>) -> Result<String, ServerFnError> {
+    use std::collections::BTreeMap;
+
+    let mut by_brand: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for paint in paints {
+        by_brand.entry(paint.brand).or_default().push(paint.name);
+    }
+    Ok(serde_json::to_string(&by_brand)?)
+}
+
+/// Read a stored white or black choice, ignoring one that cannot be parsed
+#[cfg(feature = "ssr")]
+pub(crate) fn decode_role_choice(stored: Option<&str>) -> Option<PalettePaint> {
+    stored.and_then(|s| serde_json::from_str(s).ok())
+}
+
+/// Store a palette's white or black choice as JSON, checking it is one of its paints
+#[cfg(feature = "ssr")]
+fn encode_role_choice(
+    choice: Option<PalettePaint>,
+    paints: &[PalettePaint],
+    role: crate::models::PaintRole,
+) -> Result<Option<String>, ServerFnError> {
+    let Some(choice) = choice else {
+        return Ok(None);
+    };
+    if !paints.contains(&choice) {
+        return Err(ServerFnError::new(format!(
+            "The palette's {} must be one of its paints",
+            role.label()
+        )));
+    }
+    Ok(Some(serde_json::to_string(&choice)?))
+}
+
+#[cfg(feature = "ssr")]
+fn validate_name(name: &str) -> Result<String, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServerFnError::new("Palette name cannot be empty"));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(feature = "ssr")]
+fn map_palette_error(e: sqlx::Error) -> ServerFnError {
+    match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            ServerFnError::new("A palette with that name already exists")
+        }
+        e => ServerFnError::new(e.to_string()),
+    }
+}
+
+/// List the user's palettes
+#[server]
+pub async fn list_palettes() -> Result<Vec<Palette>, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let active = state.users.get_active_palette(&user.id).await.map(|p| p.id);
+
+    Ok(state
+        .users
+        .list_palettes(&user.id)
+        .await
+        .into_iter()
+        .map(|row| Palette::from_row(row, active.as_deref()))
+        .collect())
+}
+
+/// Create a new palette
+#[server]
+pub async fn create_palette(
+    name: String,
+    mix_choice: String,
+    paints: Vec<PalettePaint>,
+    white: Option<PalettePaint>,
+    black: Option<PalettePaint>,
+) -> Result<Palette, ServerFnError> {
+    use crate::models::PaintRole;
+    use crate::server_fns::{get_current_user, parse_mix_choice};
+    use uuid::Uuid;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let name = validate_name(&name)?;
+    let mix_choice = parse_mix_choice(&mix_choice)?.as_str();
+    let white = encode_role_choice(white, &paints, PaintRole::White)?;
+    let black = encode_role_choice(black, &paints, PaintRole::Black)?;
+    let id = Uuid::new_v4().to_string();
+    state
+        .users
+        .create_palette(
+            &id,
+            &user.id,
+            &name,
+            mix_choice,
+            &encode_paints(paints)?,
+            white.as_deref(),
+            black.as_deref(),
+        )
+        .await
+        .map_err(map_palette_error)?;
+
+    let active = state.users.get_active_palette(&user.id).await.map(|p| p.id);
+    let row = state
+        .users
+        .get_palette(&user.id, &id)
+        .await
+        .ok_or_else(|| ServerFnError::new("Palette not found"))?;
+    Ok(Palette::from_row(row, active.as_deref()))
+}
+
+/// Rename a palette or change its paints, default mix strategy, white and black
+#[server]
+pub async fn update_palette(
+    id: String,
+    name: String,
+    mix_choice: String,
+    paints: Vec<PalettePaint>,
+    white: Option<PalettePaint>,
+    black: Option<PalettePaint>,
+) -> Result<(), ServerFnError> {
+    use crate::models::PaintRole;
+    use crate::server_fns::{get_current_user, parse_mix_choice};
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let name = validate_name(&name)?;
+    let mix_choice = parse_mix_choice(&mix_choice)?.as_str();
+    let white = encode_role_choice(white, &paints, PaintRole::White)?;
+    let black = encode_role_choice(black, &paints, PaintRole::Black)?;
+    let updated = state
+        .users
+        .update_palette(
+            &id,
+            &user.id,
+            &name,
+            mix_choice,
+            &encode_paints(paints)?,
+            white.as_deref(),
+            black.as_deref(),
+        )
+        .await
+        .map_err(map_palette_error)?;
+
+    if !updated {
+        return Err(ServerFnError::new("Palette not found"));
+    }
+    Ok(())
+}
+
+/// Delete a palette
+#[server]
+pub async fn delete_palette(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let deleted = state
+        .users
+        .delete_palette(&id, &user.id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !deleted {
+        return Err(ServerFnError::new("Palette not found"));
+    }
+    Ok(())
+}
+
+/// Make a palette the default for mixing
+#[server]
+pub async fn set_active_palette(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if state.users.get_palette(&user.id, &id).await.is_none() {
+        return Err(ServerFnError::new("Palette not found"));
+    }
+
+    state
+        .users
+        .set_active_palette(&user.id, &user.email, &id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
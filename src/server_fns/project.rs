@@ -0,0 +1,434 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{MixingResult, PaintFilters};
+
+/// Reference images are downscaled in the browser; this guards the database
+/// against anything that slipped through
+#[cfg(feature = "ssr")]
+const MAX_REFERENCE_IMAGE_LEN: usize = 1_500_000;
+
+/// Project as shown in the project list
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProjectSummary {
+    pub id: String,
+    pub name: String,
+    pub target_count: usize,
+    pub updated_at: String,
+}
+
+/// A painting: its reference image, target swatches with their mixes, and notes
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub notes: String,
+    pub reference_image: Option<String>,
+    pub targets: Vec<ProjectTarget>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A target colour in a project and the mixes found for it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProjectTarget {
+    pub id: String,
+    pub label: String,
+    pub target_hex: String,
+    pub palette_name: Option<String>,
+    pub mix_choice: Option<String>,
+    pub results: Vec<MixingResult>,
+    /// Index into `results` of the recipe the painter picked
+    pub chosen: Option<usize>,
+    pub notes: String,
+}
+
+impl ProjectTarget {
+    /// The chosen recipe, or the best match if none has been picked
+    pub fn recipe(&self) -> Option<&MixingResult> {
+        self.chosen
+            .and_then(|i| self.results.get(i))
+            .or_else(|| self.results.first())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl ProjectTarget {
+    fn from_row(row: crate::db::ProjectTargetRow) -> Self {
+        ProjectTarget {
+            id: row.id,
+            label: row.label,
+            target_hex: row.target_hex,
+            palette_name: row.palette_name,
+            mix_choice: row.mix_choice,
+            results: serde_json::from_str(&row.results).unwrap_or_default(),
+            chosen: row.chosen.map(|i| i as usize),
+            notes: row.notes,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn validate_name(name: &str) -> Result<String, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServerFnError::new("Project name cannot be empty"));
+    }
+    Ok(name.to_string())
+}
+
+/// List the user's projects, most recently changed first
+#[server]
+pub async fn list_projects() -> Result<Vec<ProjectSummary>, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(state
+        .users
+        .list_projects(&user.id)
+        .await
+        .into_iter()
+        .map(|row| ProjectSummary {
+            id: row.id,
+            name: row.name,
+            target_count: row.target_count as usize,
+            updated_at: row.updated_at,
+        })
+        .collect())
+}
+
+/// Load a project with all of its targets
+#[server]
+pub async fn get_project(id: String) -> Result<Project, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let row = state
+        .users
+        .get_project(&user.id, &id)
+        .await
+        .ok_or_else(|| ServerFnError::new("Project not found"))?;
+
+    let targets = state
+        .users
+        .list_project_targets(&row.id)
+        .await
+        .into_iter()
+        .map(ProjectTarget::from_row)
+        .collect();
+
+    Ok(Project {
+        id: row.id,
+        name: row.name,
+        notes: row.notes,
+        reference_image: row.reference_image,
+        targets,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+/// Create an empty project and return its id
+#[server]
+pub async fn create_project(name: String) -> Result<String, ServerFnError> {
+    use crate::server_fns::get_current_user;
+    use uuid::Uuid;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let name = validate_name(&name)?;
+    let id = Uuid::new_v4().to_string();
+
+    state
+        .users
+        .create_project(&id, &user.id, &name)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(id)
+}
+
+/// Rename a project and update its notes
+#[server]
+pub async fn update_project(id: String, name: String, notes: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let name = validate_name(&name)?;
+    let updated = state
+        .users
+        .update_project(&id, &user.id, &name, &notes)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !updated {
+        return Err(ServerFnError::new("Project not found"));
+    }
+    Ok(())
+}
+
+/// Replace or remove a project's reference image (an image data URL)
+#[server]
+pub async fn set_project_image(id: String, image: Option<String>) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    if let Some(image) = &image {
+        if !image.starts_with("data:image/") {
+            return Err(ServerFnError::new("Reference image must be an image"));
+        }
+        if image.len() > MAX_REFERENCE_IMAGE_LEN {
+            return Err(ServerFnError::new("Reference image is too large"));
+        }
+    }
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let updated = state
+        .users
+        .set_project_image(&id, &user.id, image.as_deref())
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !updated {
+        return Err(ServerFnError::new("Project not found"));
+    }
+    Ok(())
+}
+
+/// Delete a project and all of its targets
+#[server]
+pub async fn delete_project(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let deleted = state
+        .users
+        .delete_project(&id, &user.id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !deleted {
+        return Err(ServerFnError::new("Project not found"));
+    }
+    Ok(())
+}
+
+/// Add a target colour to a project and find mixes for it
+#[server]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_project_target(
+    project_id: String,
+    label: String,
+    r: u8,
+    g: u8,
+    b: u8,
+    filters: Option<PaintFilters>,
+    palette_id: Option<String>,
+    mix_choice: Option<String>,
+) -> Result<ProjectTarget, ServerFnError> {
+    use crate::models::to_hex;
+    use crate::server_fns::{get_current_user, run_paint_mix};
+    use uuid::Uuid;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    state
+        .users
+        .get_project(&user.id, &project_id)
+        .await
+        .ok_or_else(|| ServerFnError::new("Project not found"))?;
+
+    let target_hex = to_hex([r, g, b]);
+    let label = match label.trim() {
+        "" => target_hex.clone(),
+        l => l.to_string(),
+    };
+
+    let filters = filters.unwrap_or_default();
+    let (palette, mix_choice, results) = run_paint_mix(
+        &state,
+        &user.id,
+        &[r, g, b].into(),
+        &filters,
+        palette_id.as_deref(),
+        mix_choice,
+    )
+    .await?;
+
+    let id = Uuid::new_v4().to_string();
+    state
+        .users
+        .insert_project_target(
+            &id,
+            &project_id,
+            &label,
+            &target_hex,
+            Some(&palette.name),
+            Some(&mix_choice),
+            &serde_json::to_string(&results)?,
+            None,
+        )
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(ProjectTarget {
+        id,
+        label,
+        target_hex,
+        palette_name: Some(palette.name),
+        mix_choice: Some(mix_choice),
+        results,
+        chosen: None,
+        notes: String::new(),
+    })
+}
+
+/// Rename a target, pick its recipe and update its notes
+#[server]
+pub async fn update_project_target(
+    id: String,
+    label: String,
+    chosen: Option<usize>,
+    notes: String,
+) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let target = state
+        .users
+        .get_project_target(&user.id, &id)
+        .await
+        .ok_or_else(|| ServerFnError::new("Target not found"))?;
+
+    let label = match label.trim() {
+        "" => target.target_hex.clone(),
+        l => l.to_string(),
+    };
+
+    state
+        .users
+        .update_project_target(
+            &id,
+            &target.project_id,
+            &label,
+            chosen.map(|i| i as i64),
+            &notes,
+        )
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Remove a target from its project
+#[server]
+pub async fn delete_project_target(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let target = state
+        .users
+        .get_project_target(&user.id, &id)
+        .await
+        .ok_or_else(|| ServerFnError::new("Target not found"))?;
+
+    state
+        .users
+        .delete_project_target(&id, &target.project_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
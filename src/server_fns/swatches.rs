@@ -0,0 +1,170 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::server_fns::ExportFile;
+
+/// Largest swatch file accepted for import, as a base64 data URL
+#[cfg(feature = "ssr")]
+const MAX_IMPORT_LEN: usize = 1_000_000;
+
+/// Swatch file formats for export
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SwatchFormat {
+    Ase,
+    Gpl,
+    Procreate,
+    Css,
+}
+
+impl SwatchFormat {
+    pub fn all() -> Vec<SwatchFormat> {
+        vec![
+            SwatchFormat::Ase,
+            SwatchFormat::Gpl,
+            SwatchFormat::Procreate,
+            SwatchFormat::Css,
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SwatchFormat::Ase => "Adobe ASE",
+            SwatchFormat::Gpl => "GIMP GPL",
+            SwatchFormat::Procreate => "Procreate",
+            SwatchFormat::Css => "CSS",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SwatchFormat::Ase => "ase",
+            SwatchFormat::Gpl => "gpl",
+            SwatchFormat::Procreate => "swatches",
+            SwatchFormat::Css => "css",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SwatchFormat::Ase => "application/octet-stream",
+            SwatchFormat::Gpl => "text/plain",
+            SwatchFormat::Procreate => "application/zip",
+            SwatchFormat::Css => "text/css",
+        }
+    }
+}
+
+/// A colour read from an imported swatch file
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImportedSwatch {
+    pub name: String,
+    pub hex: String,
+}
+
+#[cfg(feature = "ssr")]
+fn render_swatches(
+    title: &str,
+    swatches: &[crate::services::swatches::Swatch],
+    format: SwatchFormat,
+) -> ExportFile {
+    use crate::server_fns::file_stem;
+    use crate::services::swatches::{export_ase, export_css, export_gpl, export_procreate};
+
+    let bytes = match format {
+        SwatchFormat::Ase => export_ase(swatches),
+        SwatchFormat::Gpl => export_gpl(title, swatches).into_bytes(),
+        SwatchFormat::Procreate => export_procreate(title, swatches),
+        SwatchFormat::Css => export_css(title, swatches).into_bytes(),
+    };
+    ExportFile::new(
+        format!("{}.{}", file_stem(title), format.extension()),
+        format.mime_type(),
+        &bytes,
+    )
+}
+
+/// Export the paints in a palette (the active one by default) as a swatch file
+#[server]
+pub async fn export_palette_swatches(
+    palette_id: Option<String>,
+    format: SwatchFormat,
+) -> Result<ExportFile, ServerFnError> {
+    use crate::models::parse_hex;
+    use crate::server_fns::{get_palette_colors, list_palettes};
+    use crate::services::swatches::Swatch;
+
+    let palettes = list_palettes().await?;
+    let palette = match &palette_id {
+        Some(id) => palettes.iter().find(|p| &p.id == id),
+        None => palettes.iter().find(|p| p.active).or(palettes.first()),
+    }
+    .ok_or_else(|| ServerFnError::new("Palette not found"))?;
+
+    let swatches: Vec<Swatch> = get_palette_colors(Some(palette.id.clone()))
+        .await?
+        .into_iter()
+        .filter_map(|c| {
+            Some(Swatch {
+                name: format!("{} ({})", c.id, c.brand_name),
+                rgb: parse_hex(&c.hex)?,
+            })
+        })
+        .collect();
+
+    Ok(render_swatches(&palette.name, &swatches, format))
+}
+
+/// Export saved recipes (starred history entries) as a swatch file of their target colours
+#[server]
+pub async fn export_recipe_swatches(format: SwatchFormat) -> Result<ExportFile, ServerFnError> {
+    use crate::models::parse_hex;
+    use crate::server_fns::list_mix_history;
+    use crate::services::swatches::Swatch;
+
+    let swatches: Vec<Swatch> = list_mix_history(true, None, None)
+        .await?
+        .into_iter()
+        .filter_map(|h| {
+            Some(Swatch {
+                rgb: parse_hex(&h.target_hex)?,
+                name: h.name.unwrap_or(h.target_hex),
+            })
+        })
+        .collect();
+
+    Ok(render_swatches("Saved Recipes", &swatches, format))
+}
+
+/// Read a GPL or ASE file (sent as a base64 data URL) as a list of target colours
+#[server]
+pub async fn import_swatches(
+    filename: String,
+    data_url: String,
+) -> Result<Vec<ImportedSwatch>, ServerFnError> {
+    use crate::models::to_hex;
+    use crate::server_fns::{decode_data_url, get_current_user};
+    use crate::services::swatches::{import_ase, import_gpl};
+
+    get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    let bytes = decode_data_url(&data_url, MAX_IMPORT_LEN)?;
+
+    let swatches = if bytes.starts_with(b"ASEF") {
+        import_ase(&bytes)
+    } else if filename.to_lowercase().ends_with(".gpl") || bytes.starts_with(b"GIMP Palette") {
+        import_gpl(&String::from_utf8_lossy(&bytes))
+    } else {
+        Err("Unsupported file, expected a .gpl or .ase palette".to_string())
+    }
+    .map_err(ServerFnError::new)?;
+
+    Ok(swatches
+        .into_iter()
+        .map(|s| ImportedSwatch {
+            hex: to_hex(s.rgb),
+            name: s.name,
+        })
+        .collect())
+}
@@ -0,0 +1,252 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::PaintAttributes;
+
+/// Brand id under which a user's own paints appear in palettes and pickers
+pub const USER_PAINTS_BRAND: &str = "my_paints";
+/// Display name of the user's own paints as a brand
+pub const USER_PAINTS_BRAND_NAME: &str = "My Paints";
+
+/// How a user paint's reflectance curve was obtained
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum UserPaintSource {
+    /// Measured with a spectrophotometer and imported from a CGATS, CxF or CSV file
+    Measured,
+    /// Reconstructed from a colour with LHTSS, so only as good as a metameric guess
+    Reconstructed,
+}
+
+impl UserPaintSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserPaintSource::Measured => "measured",
+            UserPaintSource::Reconstructed => "srgb",
+        }
+    }
+
+    pub fn from_code(s: &str) -> Self {
+        match s {
+            "measured" => UserPaintSource::Measured,
+            _ => UserPaintSource::Reconstructed,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UserPaintSource::Measured => "measured",
+            UserPaintSource::Reconstructed => "from colour",
+        }
+    }
+}
+
+/// A paint the user added themselves, e.g. a handmade or discontinued tube
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct UserPaint {
+    pub id: String,
+    pub name: String,
+    pub hex: String,
+    pub source: UserPaintSource,
+    pub attributes: PaintAttributes,
+}
+
+#[cfg(feature = "ssr")]
+impl UserPaint {
+    fn from_row(row: crate::db::UserPaintRow) -> Self {
+        let attributes = crate::db::PaintColor {
+            _id: row.name.clone(),
+            spectral_curve: None,
+            d65_10deg_hex: None,
+            opacity: row.opacity,
+            pigments: row.pigments,
+            lightfastness: row.lightfastness,
+            series: row.series,
+            drying_speed: row.drying_speed,
+            role: row.role,
+        }
+        .attributes();
+        UserPaint {
+            id: row.id,
+            name: row.name,
+            hex: row.hex,
+            source: UserPaintSource::from_code(&row.source),
+            attributes,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn map_user_paint_error(e: sqlx::Error) -> ServerFnError {
+    match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            ServerFnError::new("You already have a paint with that name")
+        }
+        e => ServerFnError::new(e.to_string()),
+    }
+}
+
+/// List the user's own paints
+#[server]
+pub async fn list_user_paints() -> Result<Vec<UserPaint>, ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(state
+        .users
+        .list_user_paints(&user.id)
+        .await
+        .into_iter()
+        .map(UserPaint::from_row)
+        .collect())
+}
+
+/// Add a paint of the user's own. With a measured reflectance curve (on
+/// `SpectralGrid::PAINT_DATA`, as returned by `import_measurements`) that
+/// curve is stored as is; otherwise one is reconstructed from `colour` with
+/// LHTSS. `colour` may be in any notation `TargetColour::parse` accepts.
+/// Without a role in `attributes`, one is detected from the curve.
+#[server]
+pub async fn add_user_paint(
+    name: String,
+    colour: String,
+    reflectance: Option<Vec<f64>>,
+    attributes: PaintAttributes,
+) -> Result<UserPaint, ServerFnError> {
+    use ndarray::Array1;
+    use uuid::Uuid;
+    use crate::db::{CurveMetadata, CurveOrigin, SpectralCurve, UserPaintRow};
+    use crate::models::{to_hex, xyz_to_srgb, SpectralGrid, TargetColour};
+    use crate::server_fns::{get_current_user, validate_measured_curve};
+    use crate::services::colorimetry::Colorimetry;
+    use crate::services::paint_mixing::PaintMixingService;
+    use crate::services::paint_roles::detect_role;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ServerFnError::new("Paint name cannot be empty"));
+    }
+
+    let colorimetry = Colorimetry::default();
+    let (curve, source) = match reflectance {
+        Some(curve) => {
+            validate_measured_curve(&curve)?;
+            (curve, UserPaintSource::Measured)
+        }
+        None => {
+            let target = TargetColour::parse(&colour).map_err(ServerFnError::new)?;
+            // Reconstruction, and building the lookup table on first use, block
+            let service_colorimetry = colorimetry.clone();
+            let (curve, grid) = tokio::task::spawn_blocking(move || {
+                let service = PaintMixingService::new(service_colorimetry);
+                service
+                    .calculate_target_reflectance(target.to_xyz())
+                    .map(|curve| (curve, service.grid()))
+            })
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?
+            .map_err(|e| ServerFnError::new(format!("Failed to reconstruct a reflectance curve: {}", e)))?;
+            // Paint curves are stored on the paint data grid, whatever grid LHTSS solved on
+            let curve = SpectralGrid::PAINT_DATA
+                .resample(&grid, &curve.to_vec())
+                .ok_or_else(|| ServerFnError::new("Could not resample the reconstructed curve"))?;
+            (curve, UserPaintSource::Reconstructed)
+        }
+    };
+
+    // Show the paint as its curve actually looks, not as the colour that was typed
+    let on_grid = colorimetry
+        .grid()
+        .resample(&SpectralGrid::PAINT_DATA, &curve)
+        .ok_or_else(|| ServerFnError::new("Could not resample the paint curve"))?;
+    let on_grid = Array1::from_vec(on_grid);
+    let hex = to_hex(xyz_to_srgb(colorimetry.reflectance_to_xyz(&on_grid)));
+    let role = attributes.role.or_else(|| detect_role(&on_grid, &colorimetry));
+    let origin = match source {
+        UserPaintSource::Measured => CurveOrigin::Measured,
+        UserPaintSource::Reconstructed => CurveOrigin::Reconstructed,
+    };
+    let spectral_curve = SpectralCurve::new(
+        SpectralGrid::PAINT_DATA,
+        curve,
+        CurveMetadata {
+            origin,
+            ..Default::default()
+        },
+    )
+    .encode();
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let row = UserPaintRow {
+        id: Uuid::new_v4().to_string(),
+        user_id: user.id,
+        name,
+        spectral_curve,
+        hex,
+        source: source.as_str().to_string(),
+        opacity: attributes.opacity.map(|o| o.code().to_string()),
+        pigments: Some(attributes.pigments.join(", ")).filter(|p| !p.is_empty()),
+        lightfastness: attributes.lightfastness.map(|lf| lf.code().to_string()),
+        series: attributes.series.map(i64::from),
+        drying_speed: attributes.drying_speed.map(|d| d.code().to_string()),
+        role: role.map(|r| r.code().to_string()),
+        created_at: String::new(),
+    };
+    state
+        .users
+        .insert_user_paint(&row)
+        .await
+        .map_err(map_user_paint_error)?;
+
+    Ok(UserPaint::from_row(row))
+}
+
+/// Delete one of the user's own paints; palettes that used it simply skip it
+#[server]
+pub async fn delete_user_paint(id: String) -> Result<(), ServerFnError> {
+    use crate::server_fns::get_current_user;
+
+    let user = get_current_user()
+        .await?
+        .ok_or_else(|| ServerFnError::new("Not authenticated"))?;
+
+    use axum::Extension;
+    use leptos_axum::extract;
+    use crate::state::AppState;
+
+    let Extension(state) = extract::<Extension<AppState>>()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let deleted = state
+        .users
+        .delete_user_paint(&id, &user.id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !deleted {
+        return Err(ServerFnError::new("Paint not found"));
+    }
+    Ok(())
+}
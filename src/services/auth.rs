@@ -6,7 +6,7 @@ use chrono::{Duration, Utc};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::db::{self, Db, User};
+use crate::db::{User, UserStore};
 
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
@@ -42,20 +42,20 @@ fn hash_token(token: &str) -> String {
     hex::encode(Sha256::digest(token.as_bytes()))
 }
 
-pub async fn register(db: &Db, email: &str, password: &str) -> Result<String, AuthError> {
-    if db::get_user_by_email(db, email).await.is_some() {
+pub async fn register(users: &dyn UserStore, email: &str, password: &str) -> Result<String, AuthError> {
+    if users.get_user_by_email(email).await.is_some() {
         return Err(AuthError::EmailExists);
     }
     let id = Uuid::new_v4().to_string();
     let hash = hash_password(password)?;
-    db::create_user(db, &id, email, &hash)
+    users.create_user(&id, email, &hash)
         .await
         .map_err(|e| AuthError::Other(e.to_string()))?;
     Ok(id)
 }
 
-pub async fn login(db: &Db, email: &str, password: &str) -> Result<User, AuthError> {
-    let user = db::get_user_by_email(db, email)
+pub async fn login(users: &dyn UserStore, email: &str, password: &str) -> Result<User, AuthError> {
+    let user = users.get_user_by_email(email)
         .await
         .ok_or(AuthError::InvalidCredentials)?;
 
@@ -76,40 +76,40 @@ pub async fn login(db: &Db, email: &str, password: &str) -> Result<User, AuthErr
         } else {
             None
         };
-        let _ = db::update_failed_attempts(db, &user.id, attempts, locked.as_deref()).await;
+        let _ = users.update_failed_attempts(&user.id, attempts, locked.as_deref()).await;
         return Err(AuthError::InvalidCredentials);
     }
 
-    let _ = db::update_failed_attempts(db, &user.id, 0, None).await;
+    let _ = users.update_failed_attempts(&user.id, 0, None).await;
     Ok(user)
 }
 
-pub async fn create_verification_token(db: &Db, user_id: &str) -> Result<String, AuthError> {
-    create_token(db, user_id, "verify", 24).await
+pub async fn create_verification_token(users: &dyn UserStore, user_id: &str) -> Result<String, AuthError> {
+    create_token(users, user_id, "verify", 24).await
 }
 
-pub async fn create_reset_token(db: &Db, user_id: &str) -> Result<String, AuthError> {
-    create_token(db, user_id, "reset", 1).await
+pub async fn create_reset_token(users: &dyn UserStore, user_id: &str) -> Result<String, AuthError> {
+    create_token(users, user_id, "reset", 1).await
 }
 
-async fn create_token(db: &Db, user_id: &str, kind: &str, hours: i64) -> Result<String, AuthError> {
+async fn create_token(users: &dyn UserStore, user_id: &str, kind: &str, hours: i64) -> Result<String, AuthError> {
     let token = Uuid::new_v4().to_string();
     let expires = (Utc::now() + Duration::hours(hours)).to_rfc3339();
-    db::create_token(
-        db,
-        &Uuid::new_v4().to_string(),
-        user_id,
-        kind,
-        &hash_token(&token),
-        &expires,
-    )
-    .await
-    .map_err(|e| AuthError::Other(e.to_string()))?;
+    users
+        .create_token(
+            &Uuid::new_v4().to_string(),
+            user_id,
+            kind,
+            &hash_token(&token),
+            &expires,
+        )
+        .await
+        .map_err(|e| AuthError::Other(e.to_string()))?;
     Ok(token)
 }
 
-pub async fn verify_token(db: &Db, token: &str, kind: &str) -> Result<String, AuthError> {
-    let (id, user_id, expires) = db::get_token(db, &hash_token(token), kind)
+pub async fn verify_token(users: &dyn UserStore, token: &str, kind: &str) -> Result<String, AuthError> {
+    let (id, user_id, expires) = users.get_token(&hash_token(token), kind)
         .await
         .ok_or(AuthError::InvalidToken)?;
     if chrono::DateTime::parse_from_rfc3339(&expires)
@@ -118,21 +118,21 @@ pub async fn verify_token(db: &Db, token: &str, kind: &str) -> Result<String, Au
     {
         return Err(AuthError::InvalidToken);
     }
-    let _ = db::delete_token(db, &id).await;
+    let _ = users.delete_token(&id).await;
     Ok(user_id)
 }
 
-pub async fn verify_email(db: &Db, token: &str) -> Result<(), AuthError> {
-    let user_id = verify_token(db, token, "verify").await?;
-    db::verify_user_email(db, &user_id)
+pub async fn verify_email(users: &dyn UserStore, token: &str) -> Result<(), AuthError> {
+    let user_id = verify_token(users, token, "verify").await?;
+    users.verify_user_email(&user_id)
         .await
         .map_err(|e| AuthError::Other(e.to_string()))
 }
 
-pub async fn reset_password(db: &Db, token: &str, new_password: &str) -> Result<(), AuthError> {
-    let user_id = verify_token(db, token, "reset").await?;
+pub async fn reset_password(users: &dyn UserStore, token: &str, new_password: &str) -> Result<(), AuthError> {
+    let user_id = verify_token(users, token, "reset").await?;
     let hash = hash_password(new_password)?;
-    db::update_password(db, &user_id, &hash)
+    users.update_password(&user_id, &hash)
         .await
         .map_err(|e| AuthError::Other(e.to_string()))
 }
@@ -0,0 +1,90 @@
+//! Background batch mixing jobs
+//!
+//! A batch runs on the blocking thread pool (and rayon inside it) of the server
+//! that started it. Its progress and results are written to the `batch_jobs`
+//! table of the user database, so the browser can poll any server for them.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::task::JoinHandle;
+
+use crate::db::{BatchJobRow, UserStore};
+use crate::models::BatchMixResult;
+
+/// How often a running job writes its progress
+const HEARTBEAT: Duration = Duration::from_secs(2);
+
+/// A running job whose progress has not moved for this long lost its server
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Jobs are deleted this long after their last update
+pub const JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Progress of one batch, shared with the threads mixing it
+#[derive(Default)]
+pub struct BatchJob {
+    completed: AtomicUsize,
+}
+
+impl BatchJob {
+    /// Record that one more target is done
+    pub fn advance(&self) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Relaxed)
+    }
+}
+
+/// Seconds since the Unix epoch, as stored in `batch_jobs`
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Oldest `updated_at` of a job that is still running
+pub fn live_since(now: i64) -> i64 {
+    now - STALE_AFTER.as_secs() as i64
+}
+
+/// Whether the server running an unfinished job stopped before it finished
+pub fn is_stale(job: &BatchJobRow, now: i64) -> bool {
+    !job.finished && job.updated_at <= live_since(now)
+}
+
+/// Write the job's progress until the worker is done, then its results
+pub async fn track(
+    users: Arc<dyn UserStore>,
+    id: String,
+    job: Arc<BatchJob>,
+    worker: JoinHandle<Vec<BatchMixResult>>,
+) {
+    let mut interval = tokio::time::interval(HEARTBEAT);
+    interval.tick().await;
+    while !worker.is_finished() {
+        interval.tick().await;
+        if let Err(e) = users
+            .update_batch_progress(&id, job.completed() as i64, now())
+            .await
+        {
+            eprintln!("Failed to record progress of batch {}: {}", id, e);
+        }
+    }
+
+    let outcome = match worker.await {
+        Ok(results) => serde_json::to_string(&results).map_err(|e| e.to_string()),
+        Err(_) => Err("Batch mixing failed".to_string()),
+    };
+    let (results, error) = match &outcome {
+        Ok(results) => (Some(results.as_str()), None),
+        Err(e) => (None, Some(e.as_str())),
+    };
+    if let Err(e) = users.finish_batch_job(&id, results, error, now()).await {
+        eprintln!("Failed to record results of batch {}: {}", id, e);
+    }
+}
@@ -0,0 +1,270 @@
+//! Colorimetry for reflectance curves: CIE 1964 10° observer weighted by an
+//! illuminant, normalised so a perfect white reflector has Y = 1.
+//!
+//! The observer and illuminant tables are sampled every 10 nm from 380 to
+//! 730 nm and resampled onto whichever grid the reflectance curves use. Table
+//! wavelengths beyond the ends of that grid are counted against its end
+//! samples, as if the curve held its end values.
+
+use ndarray::{Array2, ArrayView1, Axis};
+
+use crate::models::{xyz_to_lab, SpectralGrid, D65_WHITE};
+
+/// Grid of the colour matching function and illuminant tables (380–730 nm)
+const TABLE_GRID: SpectralGrid = SpectralGrid::new(380, 10, TABLE_LEN);
+const TABLE_LEN: usize = 36;
+
+/// CIE 1964 10° colour matching functions, 380–730 nm in 10 nm steps
+const X_BAR: [f64; TABLE_LEN] = [
+    0.000160, 0.002362, 0.019110, 0.084736, 0.204492, 0.314679, 0.383734, 0.370702, 0.302273,
+    0.195618, 0.080507, 0.016172, 0.003816, 0.037465, 0.117749, 0.236491, 0.376772, 0.529826,
+    0.705224, 0.878655, 1.014160, 1.118520, 1.123990, 1.030480, 0.856297, 0.647467, 0.431567,
+    0.268329, 0.152568, 0.081261, 0.040851, 0.019941, 0.009577, 0.004539, 0.002175, 0.001060,
+];
+const Y_BAR: [f64; TABLE_LEN] = [
+    0.000017, 0.000253, 0.002004, 0.008756, 0.021391, 0.038676, 0.062077, 0.089456, 0.128201,
+    0.185190, 0.253589, 0.339133, 0.460777, 0.606741, 0.761757, 0.875211, 0.961988, 0.991761,
+    0.997340, 0.955552, 0.868934, 0.777405, 0.658341, 0.527963, 0.398057, 0.283493, 0.179828,
+    0.107633, 0.060281, 0.031800, 0.015905, 0.007749, 0.003718, 0.001762, 0.000846, 0.000415,
+];
+const Z_BAR: [f64; TABLE_LEN] = [
+    0.000705, 0.010482, 0.086011, 0.389366, 0.972542, 1.553480, 1.967280, 1.994800, 1.745370,
+    1.317560, 0.772125, 0.415254, 0.218502, 0.112044, 0.060709, 0.030451, 0.013676, 0.003988,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+];
+
+/// CIE D65 relative spectral power, 380–730 nm
+const D65_SPD: [f64; TABLE_LEN] = [
+    49.9755, 54.6482, 82.7549, 91.4860, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+    115.923, 108.811, 109.354, 107.802, 104.790, 107.689, 104.405, 104.046, 100.000, 96.3342,
+    95.7880, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778,
+    78.2842, 69.7213, 71.6091, 74.3490, 61.6040, 69.8856,
+];
+
+/// CIE D50 relative spectral power, 380–730 nm
+const D50_SPD: [f64; TABLE_LEN] = [
+    24.49, 29.87, 49.31, 56.51, 60.03, 57.82, 74.82, 87.25, 90.61, 91.37, 95.11, 91.96, 95.72,
+    96.61, 97.13, 102.10, 100.75, 102.32, 100.00, 97.74, 98.92, 93.50, 97.69, 99.27, 99.04,
+    95.72, 98.86, 95.67, 98.19, 103.00, 99.13, 87.38, 91.60, 92.89, 76.85, 86.51,
+];
+
+/// Bradford cone response matrix used for chromatic adaptation
+const BRADFORD: [[f64; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+const BRADFORD_INV: [[f64; 3]; 3] = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+/// Light source the paints are viewed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Illuminant {
+    /// Average daylight
+    #[default]
+    D65,
+    /// Horizon daylight, the graphic arts viewing standard
+    D50,
+    /// Incandescent tungsten
+    A,
+}
+
+impl Illuminant {
+    /// Relative spectral power on the table grid
+    fn spd(&self) -> [f64; TABLE_LEN] {
+        match self {
+            Illuminant::D65 => D65_SPD,
+            Illuminant::D50 => D50_SPD,
+            Illuminant::A => std::array::from_fn(|i| {
+                // Planckian radiator at 2856 K, normalised to 100 at 560 nm
+                let wavelength = TABLE_GRID.wavelength(i);
+                let c2: f64 = 1.435e7 / 2848.0;
+                100.0 * (560.0 / wavelength).powi(5) * ((c2 / 560.0).exp() - 1.0)
+                    / ((c2 / wavelength).exp() - 1.0)
+            }),
+        }
+    }
+}
+
+/// Observer and illuminant used to turn reflectance into XYZ and Lab
+#[derive(Debug, Clone)]
+pub struct Colorimetry {
+    illuminant: Illuminant,
+    grid: SpectralGrid,
+    t_matrix: Array2<f64>,
+    white: [f64; 3],
+}
+
+impl Default for Colorimetry {
+    fn default() -> Self {
+        Colorimetry::new(Illuminant::default())
+    }
+}
+
+impl Colorimetry {
+    /// Colorimetry for curves on the paint data grid
+    pub fn new(illuminant: Illuminant) -> Self {
+        Colorimetry::with_grid(illuminant, SpectralGrid::PAINT_DATA)
+    }
+
+    /// Colorimetry for curves sampled on `grid`
+    pub fn with_grid(illuminant: Illuminant, grid: SpectralGrid) -> Self {
+        let spd = illuminant.spd();
+        let weighted: [[f64; TABLE_LEN]; 3] = [X_BAR, Y_BAR, Z_BAR]
+            .map(|cmf| std::array::from_fn(|i| cmf[i] * spd[i]));
+
+        let mut t_matrix = Array2::zeros((3, grid.count));
+        // Observer × illuminant at each grid wavelength the tables cover...
+        let scale = grid.step as f64 / TABLE_GRID.step as f64;
+        for (i, wl) in grid.wavelengths().enumerate() {
+            if wl >= TABLE_GRID.start as f64 && wl <= TABLE_GRID.end() {
+                for (row, table) in weighted.iter().enumerate() {
+                    t_matrix[[row, i]] = TABLE_GRID.interpolate(table, wl) * scale;
+                }
+            }
+        }
+        // ...plus the table wavelengths off either end of the grid
+        for (k, wl) in TABLE_GRID.wavelengths().enumerate() {
+            let column = if wl < grid.start as f64 {
+                0
+            } else if wl > grid.end() {
+                grid.count - 1
+            } else {
+                continue;
+            };
+            for (row, table) in weighted.iter().enumerate() {
+                t_matrix[[row, column]] += table[k];
+            }
+        }
+
+        let norm = t_matrix.row(1).sum();
+        t_matrix /= norm;
+        let white = t_matrix.sum_axis(Axis(1));
+
+        Colorimetry {
+            illuminant,
+            grid,
+            white: [white[0], white[1], white[2]],
+            t_matrix,
+        }
+    }
+
+    pub fn illuminant(&self) -> Illuminant {
+        self.illuminant
+    }
+
+    /// Wavelengths reflectance curves must be sampled at
+    pub fn grid(&self) -> SpectralGrid {
+        self.grid
+    }
+
+    /// 3×n matrix taking a reflectance curve on the grid to XYZ
+    pub fn t_matrix(&self) -> &Array2<f64> {
+        &self.t_matrix
+    }
+
+    /// XYZ of a perfect white reflector under the illuminant
+    pub fn white(&self) -> [f64; 3] {
+        self.white
+    }
+
+    /// Adapt a D65-relative XYZ colour to this illuminant and observer (Bradford)
+    pub fn adapt_from_d65(&self, xyz: [f64; 3]) -> [f64; 3] {
+        bradford(xyz, D65_WHITE, self.white)
+    }
+
+    /// XYZ of a reflectance curve on the grid
+    pub fn reflectance_to_xyz<'a>(&self, reflectance: impl Into<ArrayView1<'a, f64>>) -> [f64; 3] {
+        let xyz = self.t_matrix.dot(&reflectance.into());
+        [xyz[0], xyz[1], xyz[2]]
+    }
+
+    /// CIELAB of a reflectance curve, relative to the illuminant white
+    pub fn reflectance_to_lab<'a>(&self, reflectance: impl Into<ArrayView1<'a, f64>>) -> [f64; 3] {
+        xyz_to_lab(self.reflectance_to_xyz(reflectance), self.white)
+    }
+}
+
+/// Adapt an XYZ colour seen against the white `from` to one seen against `to`
+fn bradford(xyz: [f64; 3], from: [f64; 3], to: [f64; 3]) -> [f64; 3] {
+    let src = mul(&BRADFORD, from);
+    let dst = mul(&BRADFORD, to);
+    let cone = mul(&BRADFORD, xyz);
+    let scaled = [0, 1, 2].map(|i| cone[i] * dst[i] / src[i]);
+    mul(&BRADFORD_INV, scaled)
+}
+
+fn mul(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3], tolerance: f64) {
+        for i in 0..3 {
+            assert!(
+                (actual[i] - expected[i]).abs() < tolerance,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn perfect_reflector_is_the_illuminant_white() {
+        // CIE 1964 10° white points, Y = 1
+        let whites = [
+            (Illuminant::D65, [0.94811, 1.0, 1.07304]),
+            (Illuminant::D50, [0.96720, 1.0, 0.81427]),
+            (Illuminant::A, [1.11144, 1.0, 0.35200]),
+        ];
+        let grids = [
+            SpectralGrid::PAINT_DATA,
+            SpectralGrid::new(380, 5, 71),
+            SpectralGrid::new(360, 1, 471),
+        ];
+        for grid in grids {
+            for (illuminant, expected) in whites {
+                let colorimetry = Colorimetry::with_grid(illuminant, grid);
+                let xyz = colorimetry.reflectance_to_xyz(&Array1::from_elem(grid.count, 1.0));
+                assert_close(xyz, colorimetry.white(), 1e-12);
+                assert_close(xyz, expected, 2e-3);
+            }
+        }
+
+        // A coarse grid still normalises to Y = 1
+        let coarse = SpectralGrid::new(400, 20, 16);
+        let colorimetry = Colorimetry::with_grid(Illuminant::D65, coarse);
+        let xyz = colorimetry.reflectance_to_xyz(&Array1::from_elem(coarse.count, 1.0));
+        assert!((xyz[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn adapting_between_the_same_white_is_the_identity() {
+        let colours = [[0.2, 0.3, 0.4], D65_WHITE, [0.5, 0.2, 0.05], [0.0, 0.0, 0.0]];
+        for white in [D65_WHITE, Colorimetry::new(Illuminant::A).white()] {
+            for xyz in colours {
+                assert_close(bradford(xyz, white, white), xyz, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn d65_adaptation_only_changes_observer() {
+        // sRGB's D65 white is the 2° observer's; under the 10° observer's D65
+        // it lands on that observer's white, and colours move only as much
+        let colorimetry = Colorimetry::new(Illuminant::D65);
+        assert_close(colorimetry.adapt_from_d65(D65_WHITE), colorimetry.white(), 1e-6);
+        for xyz in [[0.2, 0.3, 0.4], [0.5, 0.2, 0.05]] {
+            assert_close(colorimetry.adapt_from_d65(xyz), xyz, 0.01);
+        }
+    }
+}
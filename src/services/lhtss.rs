@@ -1,9 +1,31 @@
 //! LHTSS (Least Hue Trace Spectral Source) algorithm for color mixing
 //!
-//! Converts sRGB colors to spectral reflectance curves for accurate paint mixing.
+//! Converts XYZ colors to spectral reflectance curves for accurate paint mixing.
 
 use nalgebra::{DMatrix, DVector};
-use ndarray::{s, Array1, Array2, Order};
+use ndarray::{s, Array1, Array2, Axis, Order};
+
+/// A point in the LHTSS Newton iteration: the reflectance is
+/// (tanh z + 1) / 2, and `lambda` holds the multipliers of the XYZ constraint
+#[derive(Debug, Clone)]
+pub struct LhtssState {
+    pub z: Array1<f64>,
+    pub lambda: Array1<f64>,
+}
+
+impl LhtssState {
+    /// The usual starting point, a flat 50% curve
+    pub fn flat(bands: usize) -> Self {
+        LhtssState {
+            z: Array1::zeros(bands),
+            lambda: Array1::zeros(3),
+        }
+    }
+
+    pub fn reflectance(&self) -> Array1<f64> {
+        (self.z.mapv(|x: f64| x.tanh()) + 1.0) / 2.0
+    }
+}
 
 /// LHTSS algorithm implementation for spectral reflectance computation
 #[derive(Clone)]
@@ -12,37 +34,62 @@ pub struct LHTSS {
 }
 
 impl LHTSS {
-    /// Create a new LHTSS instance with the given T-matrix (3x36 wavelength-to-XYZ transform)
+    /// Create a new LHTSS instance with the given T-matrix (3×n wavelength-to-XYZ
+    /// transform for curves on some spectral grid, normalised so a perfect white
+    /// reflector has Y = 1). Reflectance is computed on the same grid.
     pub fn new(t_matrix: Array2<f64>) -> Self {
-        assert_eq!(
-            t_matrix.shape(),
-            [3, 36],
-            "T-matrix must be 3x36, got {:?}",
+        assert!(
+            t_matrix.nrows() == 3 && t_matrix.ncols() >= 2,
+            "T-matrix must be 3xn, got {:?}",
             t_matrix.shape()
         );
         Self { t_matrix }
     }
 
-    /// Compute target reflectance curve from sRGB color
-    pub fn compute_reflectance_target(&self, srgb: [u8; 3]) -> Result<Array1<f64>, String> {
+    /// Number of wavelengths the reflectance curves are sampled at
+    pub fn bands(&self) -> usize {
+        self.t_matrix.ncols()
+    }
+
+    /// Compute target reflectance curve from an XYZ color in the T-matrix's
+    /// illuminant and observer. Colors outside the object color solid (no
+    /// reflectance in 0..1 can produce them) fail to converge.
+    pub fn compute_reflectance_target(&self, xyz: [f64; 3]) -> Result<Array1<f64>, String> {
+        self.compute_reflectance_target_from(xyz, LhtssState::flat(self.bands()))
+            .map(|(reflectance, _)| reflectance)
+    }
+
+    /// As `compute_reflectance_target`, but starting Newton's method from
+    /// `start`, such as a nearby colour's solution. Also returns the state
+    /// the solution was read from.
+    pub fn compute_reflectance_target_from(
+        &self,
+        xyz: [f64; 3],
+        start: LhtssState,
+    ) -> Result<(Array1<f64>, LhtssState), String> {
+        let n = self.bands();
+
         // Special cases
-        if srgb.iter().all(|&x| x == 0) {
-            return Ok(Array1::from_elem(31, 0.0001)); // Black
+        if xyz[1] <= 1e-6 {
+            return Ok((Array1::from_elem(n, 0.0001), start)); // Black
         }
-        if srgb.iter().all(|&x| x == 255) {
-            return Ok(Array1::from_elem(31, 1.0)); // White
+        let white = self.t_matrix.sum_axis(Axis(1));
+        if xyz.iter().zip(white.iter()).all(|(c, w)| (c - w).abs() < 1e-4) {
+            return Ok((Array1::from_elem(n, 1.0), start)); // White
         }
 
-        let rgb = self.srgb_to_linear(srgb);
+        let target = Array1::from_vec(xyz.to_vec());
 
         // Initialize optimization variables
-        let mut z = Array1::zeros(36);
-        let mut lambda = Array1::zeros(3);
+        let LhtssState { mut z, mut lambda } = start;
         let d = self.create_difference_matrix();
         let max_iter = 500; // Increased from 100 for better convergence
         let ftol = 1e-6; // Slightly relaxed tolerance
 
-        let mut best_z = z.clone();
+        let mut best = LhtssState {
+            z: z.clone(),
+            lambda: lambda.clone(),
+        };
         let mut best_error = f64::MAX;
 
         for _iter in 0..max_iter {
@@ -52,9 +99,9 @@ impl LHTSS {
 
             let f1 = &d.dot(&z) + &d1.dot(&self.t_matrix.t()).dot(&lambda);
             let t_d0 = self.t_matrix.dot(&d0);
-            let f2 = &t_d0 - &rgb;
+            let f2 = &t_d0 - &target;
 
-            let mut f = Vec::with_capacity(39);
+            let mut f = Vec::with_capacity(n + 3);
             f.extend(f1.iter());
             f.extend(f2.iter());
             let f = Array1::from_vec(f);
@@ -63,35 +110,34 @@ impl LHTSS {
             let error: f64 = f.iter().map(|x| x * x).sum();
             if error < best_error {
                 best_error = error;
-                best_z = z.clone();
+                best = LhtssState {
+                    z: z.clone(),
+                    lambda: lambda.clone(),
+                };
             }
 
             let j = self.create_jacobian(&z, &d, &lambda, &d1, &d2)?;
             let neg_f = f.mapv(|x: f64| -x);
             let delta = self.solve_linear_system(&j, &neg_f)?;
 
-            z = z + Array1::from_vec(delta.slice(s![..36]).to_vec());
-            lambda = lambda + Array1::from_vec(delta.slice(s![36..]).to_vec());
+            z = z + Array1::from_vec(delta.slice(s![..n]).to_vec());
+            lambda = lambda + Array1::from_vec(delta.slice(s![n..]).to_vec());
 
             if f.iter().all(|&x| x.abs() < ftol) {
-                let full_range = (z.mapv(|x: f64| x.tanh()) + 1.0) / 2.0;
-                // Return wavelengths 400nm to 700nm (31 values from index 2 to 32)
-                let reduced_range = full_range.slice(s![2..33]).to_owned();
-                return Ok(reduced_range);
+                let state = LhtssState { z, lambda };
+                return Ok((state.reflectance(), state));
             }
         }
 
         // If we didn't converge within tolerance, use the best solution found
         // This handles difficult colors that don't fully converge but get close
         if best_error < 1.0 {
-            let full_range = (best_z.mapv(|x: f64| x.tanh()) + 1.0) / 2.0;
-            let reduced_range = full_range.slice(s![2..33]).to_owned();
-            return Ok(reduced_range);
+            return Ok((best.reflectance(), best));
         }
 
         Err(format!(
-            "LHTSS did not converge for RGB({},{},{}), best error: {:.6}",
-            srgb[0], srgb[1], srgb[2], best_error
+            "LHTSS did not converge for XYZ({:.4},{:.4},{:.4}), best error: {:.6}",
+            xyz[0], xyz[1], xyz[2], best_error
         ))
     }
 
@@ -111,84 +157,20 @@ impl LHTSS {
         mixed
     }
 
-    /// Convert sRGB to linear RGB
-    pub fn srgb_to_linear(&self, srgb: [u8; 3]) -> Array1<f64> {
-        Array1::from_vec(
-            srgb.iter()
-                .map(|&x| {
-                    let x = x as f64 / 255.0;
-                    if x <= 0.04045 {
-                        x / 12.92
-                    } else {
-                        ((x + 0.055) / 1.055).powf(2.4)
-                    }
-                })
-                .collect(),
-        )
-    }
-
-    /// Convert reflectance to XYZ color space
-    pub fn reflectance_to_xyz(&self, reflectance: &Array1<f64>) -> [f64; 3] {
-        // Pad reflectance to 36 values if needed
-        let r = if reflectance.len() == 31 {
-            let mut full = Array1::zeros(36);
-            full.slice_mut(s![2..33]).assign(reflectance);
-            full[0] = reflectance[0];
-            full[1] = reflectance[0];
-            full[33] = reflectance[30];
-            full[34] = reflectance[30];
-            full[35] = reflectance[30];
-            full
-        } else {
-            reflectance.clone()
-        };
-
-        let xyz = self.t_matrix.dot(&r);
-        [xyz[0], xyz[1], xyz[2]]
-    }
-
-    /// Convert XYZ to Lab color space
-    pub fn xyz_to_lab(&self, xyz: &[f64; 3]) -> [f64; 3] {
-        let xn = 95.047;
-        let yn = 100.0;
-        let zn = 108.883;
-
-        let f = |t: f64| {
-            if t > 0.008856 {
-                t.powf(1.0 / 3.0)
-            } else {
-                7.787 * t + 16.0 / 116.0
-            }
-        };
-
-        let fx = f(xyz[0] / xn);
-        let fy = f(xyz[1] / yn);
-        let fz = f(xyz[2] / zn);
-
-        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
-    }
-
-    /// Calculate Delta E (color difference) between two Lab colors
-    pub fn delta_e(&self, lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
-        let dl = lab2[0] - lab1[0];
-        let da = lab2[1] - lab1[1];
-        let db = lab2[2] - lab1[2];
-        (dl * dl + da * da + db * db).sqrt()
-    }
-
     fn create_difference_matrix(&self) -> Array2<f64> {
-        let mut d = Array2::zeros((36, 36));
-        for i in 0..36 {
+        let n = self.bands();
+        let mut d = Array2::zeros((n, n));
+        for i in 0..n {
             d[[i, i]] = 4.0;
             if i > 0 {
                 d[[i, i - 1]] = -2.0;
             }
-            if i < 35 {
+            if i < n - 1 {
                 d[[i, i + 1]] = -2.0;
             }
         }
         d[[0, 0]] = 2.0;
-        d[[35, 35]] = 2.0;
+        d[[n - 1, n - 1]] = 2.0;
         d
     }
 
@@ -207,7 +189,7 @@ impl LHTSS {
         let lambda_reshaped = lambda.clone().into_shape_with_order(((3, 1), Order::RowMajor)).map_err(|e| e.to_string())?;
         let d2_t_lambda = temp
             .dot(&lambda_reshaped)
-            .into_shape_with_order((36, Order::RowMajor))
+            .into_shape_with_order((n, Order::RowMajor))
             .map_err(|e| e.to_string())?;
         let top_left = d + &Array2::from_diag(&d2_t_lambda);
         j.slice_mut(s![..n, ..n]).assign(&top_left);
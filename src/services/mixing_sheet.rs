@@ -0,0 +1,320 @@
+//! Printable mixing sheets: recipes laid out on A4 and rendered as SVG or PDF
+//!
+//! Both formats are written by hand from the same page layout, so no external
+//! renderer is needed.
+
+use std::fmt::Write;
+
+use crate::models::{parse_hex, MixingResult};
+
+/// A4 in PostScript points
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+
+const TARGET_SWATCH: f64 = 48.0;
+const PAINT_SWATCH: f64 = 12.0;
+const LINE_HEIGHT: f64 = 16.0;
+const ENTRY_GAP: f64 = 14.0;
+
+/// One recipe on the sheet
+pub struct SheetEntry {
+    pub label: String,
+    pub target_hex: String,
+    pub recipe: Option<MixingResult>,
+}
+
+enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        size: f64,
+        fill: [u8; 3],
+    },
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        bold: bool,
+        text: String,
+    },
+    Line {
+        y: f64,
+    },
+}
+
+/// Lay out the sheet into pages of shapes, with y measured from the top of the page
+fn layout(title: &str, entries: &[SheetEntry]) -> Vec<Vec<Shape>> {
+    let mut pages = vec![vec![]];
+    let mut y = MARGIN + 18.0;
+    pages[0].push(Shape::Text {
+        x: MARGIN,
+        y,
+        size: 18.0,
+        bold: true,
+        text: title.to_string(),
+    });
+    y += 20.0;
+
+    for entry in entries {
+        let paint_count = entry.recipe.as_ref().map_or(1, |r| r.paints.len().max(1));
+        let height = TARGET_SWATCH.max(LINE_HEIGHT * (paint_count as f64 + 1.0));
+
+        if y + height + ENTRY_GAP > PAGE_HEIGHT - MARGIN {
+            pages.push(vec![]);
+            y = MARGIN;
+        }
+        let page = pages.last_mut().unwrap();
+        let top = y + ENTRY_GAP;
+
+        page.push(Shape::Line { y: y + ENTRY_GAP / 2.0 });
+        page.push(Shape::Rect {
+            x: MARGIN,
+            y: top,
+            size: TARGET_SWATCH,
+            fill: parse_hex(&entry.target_hex).unwrap_or([128, 128, 128]),
+        });
+
+        let text_x = MARGIN + TARGET_SWATCH + 12.0;
+        let mut heading = format!("{}  {}", entry.label, entry.target_hex);
+        if let Some(recipe) = &entry.recipe {
+            let _ = write!(heading, "  ΔE {:.2}", recipe.error);
+        }
+        page.push(Shape::Text {
+            x: text_x,
+            y: top + 11.0,
+            size: 11.0,
+            bold: true,
+            text: heading,
+        });
+
+        match &entry.recipe {
+            Some(recipe) => {
+                for (i, parts) in recipe.parts().into_iter().enumerate() {
+                    let line_y = top + LINE_HEIGHT * (i as f64 + 1.0);
+                    let hex = recipe.hex_colors.get(i).map(String::as_str).unwrap_or_default();
+                    page.push(Shape::Rect {
+                        x: text_x,
+                        y: line_y + 2.0,
+                        size: PAINT_SWATCH,
+                        fill: parse_hex(hex).unwrap_or([128, 128, 128]),
+                    });
+
+                    let name = &recipe.paints[i];
+                    let text = match recipe.brands.get(i).filter(|b| !b.is_empty()) {
+                        Some(brand) => format!("{} part{}  {} ({})", parts, plural(parts), name, brand),
+                        None => format!("{} part{}  {}", parts, plural(parts), name),
+                    };
+                    page.push(Shape::Text {
+                        x: text_x + PAINT_SWATCH + 6.0,
+                        y: line_y + 12.0,
+                        size: 10.0,
+                        bold: false,
+                        text,
+                    });
+                }
+            }
+            None => page.push(Shape::Text {
+                x: text_x,
+                y: top + LINE_HEIGHT + 12.0,
+                size: 10.0,
+                bold: false,
+                text: "No recipe".to_string(),
+            }),
+        }
+
+        y = top + height;
+    }
+
+    pages
+}
+
+fn plural(n: u32) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Render the sheet as a single SVG, with pages stacked vertically
+pub fn render_svg(title: &str, entries: &[SheetEntry]) -> String {
+    let pages = layout(title, entries);
+    let height = PAGE_HEIGHT * pages.len() as f64;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}pt" height="{h}pt" viewBox="0 0 {w} {h}" font-family="Helvetica, Arial, sans-serif">"#,
+        w = PAGE_WIDTH,
+        h = height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    for (n, page) in pages.iter().enumerate() {
+        let offset = PAGE_HEIGHT * n as f64;
+        for shape in page {
+            match shape {
+                Shape::Rect { x, y, size, fill } => {
+                    let _ = writeln!(
+                        svg,
+                        r##"<rect x="{x}" y="{}" width="{size}" height="{size}" fill="#{:02x}{:02x}{:02x}" stroke="#999" stroke-width="0.5"/>"##,
+                        y + offset,
+                        fill[0],
+                        fill[1],
+                        fill[2]
+                    );
+                }
+                Shape::Text { x, y, size, bold, text } => {
+                    let weight = if *bold { r#" font-weight="bold""# } else { "" };
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{x}" y="{}" font-size="{size}"{weight}>{}</text>"#,
+                        y + offset,
+                        escape_xml(text)
+                    );
+                }
+                Shape::Line { y } => {
+                    let _ = writeln!(
+                        svg,
+                        r##"<line x1="{MARGIN}" y1="{y}" x2="{}" y2="{y}" stroke="#ddd" stroke-width="0.5"/>"##,
+                        PAGE_WIDTH - MARGIN,
+                        y = y + offset
+                    );
+                }
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render the sheet as a PDF using the built-in Helvetica fonts
+pub fn render_pdf(title: &str, entries: &[SheetEntry]) -> Vec<u8> {
+    let pages = layout(title, entries);
+
+    // Objects 1-4 are fixed; each page then takes a page object and a content stream
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        vec![],
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+
+    let mut kids = Vec::with_capacity(pages.len());
+    for page in &pages {
+        let content = page_content(page);
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(&content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    )
+    .into_bytes();
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+/// Drawing operators for one page; PDF measures y from the bottom
+fn page_content(shapes: &[Shape]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for shape in shapes {
+        match shape {
+            Shape::Rect { x, y, size, fill } => {
+                let [r, g, b] = fill.map(|c| c as f64 / 255.0);
+                out.extend_from_slice(
+                    format!(
+                        "{r:.3} {g:.3} {b:.3} rg 0.6 G 0.5 w {x:.2} {:.2} {size:.2} {size:.2} re B\n",
+                        PAGE_HEIGHT - y - size
+                    )
+                    .as_bytes(),
+                );
+            }
+            Shape::Text { x, y, size, bold, text } => {
+                let font = if *bold { "F2" } else { "F1" };
+                out.extend_from_slice(
+                    format!("0 g BT /{font} {size} Tf {x:.2} {:.2} Td (", PAGE_HEIGHT - y).as_bytes(),
+                );
+                out.extend_from_slice(&pdf_string(text));
+                out.extend_from_slice(b") Tj ET\n");
+            }
+            Shape::Line { y } => {
+                out.extend_from_slice(
+                    format!(
+                        "0.87 G 0.5 w {MARGIN:.2} {y:.2} m {:.2} {y:.2} l S\n",
+                        PAGE_WIDTH - MARGIN,
+                        y = PAGE_HEIGHT - y
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+    }
+    out
+}
+
+/// Encode text for a PDF string literal in WinAnsi (Latin-1 for the characters we need)
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                out.push(c as u8);
+            }
+            // WinAnsi has no Greek, so spell out ΔE
+            'Δ' => out.extend_from_slice(b"Delta "),
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => out.push(c as u32 as u8),
+            _ => out.push(b'?'),
+        }
+    }
+    out
+}
@@ -1,10 +1,30 @@
 #[cfg(feature = "ssr")]
 pub mod auth;
 #[cfg(feature = "ssr")]
+pub mod batch;
+#[cfg(feature = "ssr")]
+pub mod colorimetry;
+#[cfg(feature = "ssr")]
 pub mod email;
 #[cfg(feature = "ssr")]
 pub mod lhtss;
 #[cfg(feature = "ssr")]
+pub mod mixing_sheet;
+#[cfg(feature = "ssr")]
 pub mod optimization;
 #[cfg(feature = "ssr")]
+pub mod paint_cache;
+#[cfg(feature = "ssr")]
 pub mod paint_mixing;
+#[cfg(feature = "ssr")]
+pub mod paint_roles;
+#[cfg(feature = "ssr")]
+pub mod paint_validation;
+#[cfg(feature = "ssr")]
+pub mod pigments;
+#[cfg(feature = "ssr")]
+pub mod spectral_import;
+#[cfg(feature = "ssr")]
+pub mod swatches;
+#[cfg(feature = "ssr")]
+pub mod target_reflectance;
@@ -4,17 +4,20 @@
 
 use ndarray::Array1;
 
-use crate::models::ColorError;
-
 /// Convert reflectance R to Kubelka-Munk K/S ratio
 /// Formula: K/S = (1 - R)² / (2R)
 #[inline]
 fn reflectance_to_ks(r: f64) -> f64 {
     // Clamp reflectance to avoid division by zero and negative values
-    let r = r.max(0.001).min(0.999);
+    let r = r.clamp(0.001, 0.999);
     (1.0 - r).powi(2) / (2.0 * r)
 }
 
+/// K/S at each wavelength of a reflectance curve
+pub fn ks_curve(reflectance: &Array1<f64>) -> Array1<f64> {
+    reflectance.mapv(reflectance_to_ks)
+}
+
 /// Convert Kubelka-Munk K/S ratio back to reflectance R
 /// Formula: R = 1 + K/S - √(K/S² + 2·K/S)
 #[inline]
@@ -24,62 +27,89 @@ fn ks_to_reflectance(ks: f64) -> f64 {
         return 1.0; // Pure white (no absorption)
     }
     let r = 1.0 + ks - (ks * ks + 2.0 * ks).sqrt();
-    r.max(0.0).min(1.0)
+    r.clamp(0.0, 1.0)
 }
 
-/// Mix reflectance curves using Kubelka-Munk theory
-/// This is the physically correct way to mix subtractive colors (paints)
-pub fn kubelka_munk_mix(reflectance_data: &[Array1<f64>], weights: &[f64]) -> Array1<f64> {
-    let n = reflectance_data[0].len();
-    let mut mixed = Array1::zeros(n);
+/// Mix paints given by their K/S curves, writing the mixture's reflectance
+/// into `mixed`. Pigments add in K/S space, so the mixture's K/S at each
+/// wavelength is the weighted average of the paints'. Takes at least one
+/// paint, one weight per paint and curves as long as `mixed`.
+pub fn mix_ks_into(ks: &[&[f64]], weights: &[f64], mixed: &mut [f64]) {
+    debug_assert!(!ks.is_empty(), "no paints to mix");
+    debug_assert_eq!(ks.len(), weights.len(), "one weight per paint");
+    debug_assert!(ks.iter().all(|curve| curve.len() == mixed.len()), "curve lengths differ");
     let sum_weights: f64 = weights.iter().sum();
-
     if sum_weights <= 0.0 {
-        return mixed;
+        mixed.fill(0.0);
+        return;
     }
 
-    // Normalize weights
-    let normalized_weights: Vec<f64> = weights.iter().map(|w| w / sum_weights).collect();
-
-    for i in 0..n {
-        // Convert each paint's reflectance at this wavelength to K/S
-        // Then mix the K/S values (weighted average - pigments are additive in K/S space)
+    for (i, out) in mixed.iter_mut().enumerate() {
         let mut ks_sum = 0.0;
-        for (j, &weight) in normalized_weights.iter().enumerate() {
-            let r = reflectance_data[j][i];
-            let ks = reflectance_to_ks(r);
-            ks_sum += ks * weight;
+        for (curve, &weight) in ks.iter().zip(weights) {
+            ks_sum += curve[i] * (weight / sum_weights);
         }
+        *out = ks_to_reflectance(ks_sum);
+    }
+}
+
+/// Working buffers for `optimize_weights`, reused from one combination of
+/// paints to the next so the search does not allocate per iteration
+#[derive(Debug, Default)]
+pub struct MixScratch {
+    current: Vec<f64>,
+    trial: Vec<f64>,
+    /// The best weights found so far
+    weights: Vec<f64>,
+    gradients: Vec<f64>,
+    mixed: Vec<f64>,
+}
+
+impl MixScratch {
+    /// The optimised weights, normalised to sum to 1
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
 
-        // Convert mixed K/S back to reflectance
-        mixed[i] = ks_to_reflectance(ks_sum);
+    /// Reflectance of the mixture at the optimised weights
+    pub fn mixed(&self) -> &[f64] {
+        &self.mixed
     }
-    mixed
+}
+
+/// Mean squared difference between two curves
+fn squared_error(target: &[f64], mixed: &[f64]) -> f64 {
+    let sum: f64 = target.iter().zip(mixed).map(|(t, m)| (t - m) * (t - m)).sum();
+    sum / target.len() as f64
 }
 
 /// Optimize paint weights to minimize error between mixed reflectance and target
-/// Uses Kubelka-Munk theory for physically accurate paint mixing
-pub fn optimize_weights(
-    selected_paints: &[(String, Array1<f64>, String)],
-    initial_weights: &[f64],
-    target_reflectance: &Array1<f64>,
-) -> Result<Vec<f64>, ColorError> {
-    let n = initial_weights.len();
-    let mut weights = initial_weights.to_vec();
+/// Uses Kubelka-Munk theory for physically accurate paint mixing. Starts from
+/// equal parts of each paint; the result is read from `scratch`.
+pub fn optimize_weights(ks: &[&[f64]], target_reflectance: &[f64], scratch: &mut MixScratch) {
+    let n = ks.len();
+    let MixScratch {
+        current: weights,
+        trial,
+        weights: best,
+        gradients,
+        mixed,
+    } = scratch;
+    weights.clear();
+    weights.resize(n, 1.0 / n as f64);
+    best.clear();
+    best.extend_from_slice(weights);
+    gradients.clear();
+    gradients.resize(n, 0.0);
+    mixed.clear();
+    mixed.resize(target_reflectance.len(), 0.0);
 
     let max_iterations = 1000;
     let tolerance = 1e-8;
     let mut alpha = 0.5; // Start with smaller step size for K-M optimization
 
-    let mut best_weights = weights.clone();
     let mut best_error = f64::MAX;
 
-    // Extract reflectance arrays
-    let reflectances: Vec<&Array1<f64>> = selected_paints
-        .iter()
-        .map(|(_, r, _)| r)
-        .collect();
-
     for iteration in 0..max_iterations {
         // Normalize weights
         let sum: f64 = weights.iter().sum();
@@ -90,17 +120,15 @@ pub fn optimize_weights(
         }
 
         // Calculate mixed reflectance using Kubelka-Munk
-        let reflectance_data: Vec<Array1<f64>> = reflectances.iter().map(|r| (*r).clone()).collect();
-        let mixed = kubelka_munk_mix(&reflectance_data, &weights);
+        mix_ks_into(ks, weights, mixed);
 
         // Calculate error (mean squared error in reflectance space)
-        let diff: Array1<f64> = target_reflectance - &mixed;
-        let current_error = diff.mapv(|x| x * x).mean().unwrap_or(f64::MAX);
+        let current_error = squared_error(target_reflectance, mixed);
 
         // Track best solution
         if current_error < best_error {
             best_error = current_error;
-            best_weights = weights.clone();
+            best.copy_from_slice(weights);
         }
 
         if current_error < tolerance {
@@ -113,63 +141,67 @@ pub fn optimize_weights(
         }
 
         // Calculate gradients using finite differences
-        let mut gradients = Vec::with_capacity(n);
         let delta = 0.001;
 
         for i in 0..n {
-            let mut test_weights = weights.clone();
-            test_weights[i] += delta;
+            trial.clear();
+            trial.extend_from_slice(weights);
+            trial[i] += delta;
 
-            let sum: f64 = test_weights.iter().sum();
-            for w in test_weights.iter_mut() {
+            let sum: f64 = trial.iter().sum();
+            for w in trial.iter_mut() {
                 *w /= sum;
             }
 
-            let test_mixed = kubelka_munk_mix(&reflectance_data, &test_weights);
-            let test_diff: Array1<f64> = target_reflectance - &test_mixed;
-            let test_error = test_diff.mapv(|x| x * x).mean().unwrap_or(f64::MAX);
+            mix_ks_into(ks, trial, mixed);
+            let test_error = squared_error(target_reflectance, mixed);
 
-            gradients.push((test_error - current_error) / delta);
+            gradients[i] = (test_error - current_error) / delta;
         }
 
         // Update weights using gradient descent
         for i in 0..n {
             weights[i] -= alpha * gradients[i];
-            weights[i] = weights[i].max(0.0).min(1.0);
+            weights[i] = weights[i].clamp(0.0, 1.0);
         }
     }
 
     // Use best weights found
-    let sum: f64 = best_weights.iter().sum();
+    let sum: f64 = best.iter().sum();
     if sum > 0.0 {
-        for w in best_weights.iter_mut() {
+        for w in best.iter_mut() {
             *w /= sum;
         }
     }
-
-    Ok(best_weights)
+    mix_ks_into(ks, best, mixed);
 }
 
-/// Compute mean squared error between mixed and target reflectance
-pub fn compute_error(mixed_reflectance: &Array1<f64>, target_reflectance: &Array1<f64>) -> f64 {
-    let diff = target_reflectance - mixed_reflectance;
-    diff.mapv(|x| x * x).mean().unwrap_or(f64::MAX)
-}
 
-/// Compute weighted geometric mean of reflectance curves (alternative mixing method)
-pub fn weighted_geometric_mean(reflectance_data: &[Array1<f64>], weights: &[f64]) -> Array1<f64> {
-    let n = reflectance_data[0].len();
-    let mut mixed = Array1::zeros(n);
-    let sum_weights: f64 = weights.iter().sum();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for i in 0..n {
-        let mut product = 1.0;
-        for (j, &weight) in weights.iter().enumerate() {
-            // Clamp to avoid log(0) issues
-            let r = reflectance_data[j][i].max(0.001);
-            product *= r.powf(weight);
+    #[test]
+    fn one_paint_mixes_to_itself() {
+        let reflectance = Array1::from_vec(vec![0.05, 0.2, 0.5, 0.8, 0.95]);
+        let ks = ks_curve(&reflectance);
+        let mut mixed = vec![0.0; reflectance.len()];
+        for weight in [0.01, 0.5, 1.0, 7.0] {
+            mix_ks_into(&[ks.as_slice().unwrap()], &[weight], &mut mixed);
+            for (mixed, expected) in mixed.iter().zip(&reflectance) {
+                assert!((mixed - expected).abs() < 1e-12, "{} != {}", mixed, expected);
+            }
         }
-        mixed[i] = product.powf(1.0 / sum_weights);
     }
-    mixed
+
+    #[test]
+    fn two_paints_mix_in_ks() {
+        // R = 0.5 has K/S 0.25 and R = 0.2 has K/S 1.6; one part to three
+        // gives K/S 1.2625, which is R = 1 + K/S - √(K/S² + 2·K/S)
+        let ks = [ks_curve(&Array1::from_elem(1, 0.5)), ks_curve(&Array1::from_elem(1, 0.2))];
+        let ks = [ks[0].as_slice().unwrap(), ks[1].as_slice().unwrap()];
+        let mut mixed = [0.0];
+        mix_ks_into(&ks, &[1.0, 3.0], &mut mixed);
+        assert!((mixed[0] - 0.232_991_130_839_778_7).abs() < 1e-12, "{}", mixed[0]);
+    }
 }
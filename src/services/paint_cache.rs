@@ -0,0 +1,185 @@
+//! The paint catalogue held in memory with its curves decoded, so mixing
+//! requests neither query the reference database nor decode curves.
+//!
+//! The catalogue is the reference database with the admins' edits from the
+//! user database applied. It is loaded at startup and loaded again whenever an
+//! admin edits it, here or on another server sharing the user database;
+//! requests work on whichever snapshot was current when they began.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use ndarray::Array1;
+
+use crate::db::{self, BrandRow, Db, PaintColor, UserStore};
+use crate::models::{PaintAttributes, SpectralGrid};
+use crate::services::optimization::ks_curve;
+
+/// A paint's curves on the paint data grid, which mixing uses
+#[derive(Debug, Clone)]
+pub struct PaintCurves {
+    pub reflectance: Array1<f64>,
+    /// Kubelka-Munk K/S at each wavelength of `reflectance`
+    pub ks: Array1<f64>,
+}
+
+impl PaintCurves {
+    pub fn new(reflectance: Array1<f64>) -> Self {
+        PaintCurves {
+            ks: ks_curve(&reflectance),
+            reflectance,
+        }
+    }
+}
+
+/// A paint decoded for mixing
+#[derive(Debug, Clone)]
+pub struct CachedPaint {
+    pub name: String,
+    pub hex: String,
+    pub attributes: PaintAttributes,
+    /// The decoded curves, or why the stored curve cannot be used
+    pub curves: Result<PaintCurves, String>,
+}
+
+impl CachedPaint {
+    /// Decode a paint row, resampling its curve onto the paint data grid
+    pub fn from_color(color: PaintColor) -> Self {
+        let curves = color
+            .curve()
+            .map(|curve| {
+                PaintCurves::new(Array1::from_vec(curve.resampled(SpectralGrid::PAINT_DATA)))
+            })
+            .map_err(|e| e.to_string());
+        CachedPaint {
+            attributes: color.attributes(),
+            hex: color.d65_10deg_hex.unwrap_or_else(|| "#808080".to_string()),
+            name: color._id,
+            curves,
+        }
+    }
+}
+
+/// A catalogued brand and its current paints, in catalogue order
+pub struct CachedBrand {
+    pub row: BrandRow,
+    pub paints: Arc<Vec<CachedPaint>>,
+}
+
+/// Every catalogued brand as it was when loaded
+#[derive(Default)]
+pub struct PaintCatalogue {
+    /// In display name order
+    brands: Vec<CachedBrand>,
+    index: HashMap<String, usize>,
+}
+
+impl PaintCatalogue {
+    pub async fn load(db: &Db, users: &dyn UserStore) -> Self {
+        let overlay = users.catalogue_overlay().await.unwrap_or_else(|e| {
+            eprintln!("Failed to read the catalogue edits: {}", e);
+            Default::default()
+        });
+        let mut brands = Vec::new();
+        let rows = db::list_brands(db, &overlay).await.unwrap_or_else(|e| {
+            eprintln!("Failed to read the paint brands: {}", e);
+            vec![]
+        });
+        for row in rows {
+            // A brand that cannot be read is kept, with no paints to mix
+            let paints = match db::get_paint_colors(db, &overlay, &row.id).await {
+                Ok(paints) => paints.into_iter().map(CachedPaint::from_color).collect(),
+                Err(e) => {
+                    eprintln!("Failed to read the paints of {}: {}", row.id, e);
+                    vec![]
+                }
+            };
+            brands.push(CachedBrand {
+                row,
+                paints: Arc::new(paints),
+            });
+        }
+        let index = brands
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.row.id.clone(), i))
+            .collect();
+        PaintCatalogue { brands, index }
+    }
+
+    pub fn brands(&self) -> &[CachedBrand] {
+        &self.brands
+    }
+
+    pub fn brand(&self, id: &str) -> Option<&CachedBrand> {
+        self.index.get(id).map(|&i| &self.brands[i])
+    }
+}
+
+/// A loaded catalogue with where it came from
+#[derive(Default)]
+struct Snapshot {
+    /// Number of the load that produced it
+    load: u64,
+    /// Catalogue revision it was loaded at, if that could be read
+    revision: Option<i64>,
+    catalogue: Arc<PaintCatalogue>,
+}
+
+/// The current catalogue snapshot, shared by every request
+#[derive(Clone, Default)]
+pub struct PaintCache {
+    current: Arc<RwLock<Snapshot>>,
+    loads: Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl PaintCache {
+    pub async fn load(db: &Db, users: &dyn UserStore) -> Self {
+        let cache = PaintCache::default();
+        cache.reload(db, users).await;
+        cache
+    }
+
+    pub fn catalogue(&self) -> Arc<PaintCatalogue> {
+        self.current.read().unwrap().catalogue.clone()
+    }
+
+    /// Load the catalogue again after it has been edited. Of two reloads that
+    /// overlap, the one that started last wins.
+    pub async fn reload(&self, db: &Db, users: &dyn UserStore) {
+        let load = self.loads.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        // Read before loading, so an edit made during the load is picked up
+        // by the next refresh
+        let revision = match users.catalogue_revision().await {
+            Ok(revision) => Some(revision),
+            Err(e) => {
+                eprintln!("Failed to read the catalogue revision: {}", e);
+                None
+            }
+        };
+        let catalogue = Arc::new(PaintCatalogue::load(db, users).await);
+        let mut current = self.current.write().unwrap();
+        if current.load < load {
+            *current = Snapshot {
+                load,
+                revision,
+                catalogue,
+            };
+        }
+    }
+
+    /// Reload if the catalogue has been edited since the current snapshot was
+    /// loaded, such as by another server sharing the user database
+    pub async fn refresh(&self, db: &Db, users: &dyn UserStore) {
+        let revision = match users.catalogue_revision().await {
+            Ok(revision) => revision,
+            Err(e) => {
+                eprintln!("Failed to read the catalogue revision: {}", e);
+                return;
+            }
+        };
+        if self.current.read().unwrap().revision != Some(revision) {
+            self.reload(db, users).await;
+        }
+    }
+}
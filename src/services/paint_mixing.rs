@@ -2,323 +2,243 @@
 //!
 //! Uses Kubelka-Munk theory for physically accurate subtractive color mixing.
 
-use ndarray::{Array1, Array2};
+use ndarray::Array1;
 use rayon::prelude::*;
 
-use crate::models::{ColorError, MixingResult};
-use crate::services::lhtss::LHTSS;
-use crate::services::optimization::{kubelka_munk_mix, optimize_weights};
+use crate::models::{
+    delta_e76, ColorError, MixChoice, MixingResult, PaintAttributes, PaintRole, SpectralGrid,
+};
+use crate::services::colorimetry::Colorimetry;
+use crate::services::optimization::{optimize_weights, MixScratch};
+use crate::services::pigments::mix_warnings;
+use crate::services::target_reflectance::TargetReflectance;
+
+/// A paint available to the mixing search
+#[derive(Debug, Clone)]
+pub struct MixPaint {
+    pub name: String,
+    /// Display name of the brand the paint belongs to
+    pub brand: String,
+    /// Reflectance on the mixing service's spectral grid
+    pub reflectance: Array1<f64>,
+    /// Kubelka-Munk K/S of `reflectance`, precomputed for the mixing kernel
+    pub ks: Array1<f64>,
+    pub hex: String,
+    pub attributes: PaintAttributes,
+    /// Chosen by the palette for its role over other paints with the same role
+    pub preferred: bool,
+}
+
+impl MixPaint {
+    pub fn has_role(&self, role: PaintRole) -> bool {
+        self.attributes.role == Some(role)
+    }
+}
+
+/// The paint filling a role: the palette's preferred one, else of several
+/// whites the lightest, of several blacks the darkest, otherwise the first
+pub fn role_paint(paints: &[MixPaint], role: PaintRole) -> Option<&MixPaint> {
+    let mut candidates = paints.iter().filter(|p| p.has_role(role));
+    if let Some(preferred) = candidates.clone().find(|p| p.preferred) {
+        return Some(preferred);
+    }
+    let lightness = |p: &&MixPaint| p.reflectance.mean().unwrap_or(0.0);
+    match role {
+        PaintRole::White => candidates.max_by(|a, b| lightness(a).total_cmp(&lightness(b))),
+        PaintRole::Black => candidates.min_by(|a, b| lightness(a).total_cmp(&lightness(b))),
+        _ => candidates.next(),
+    }
+}
 
 /// Paint mixing service that finds optimal paint combinations for a target color
 pub struct PaintMixingService {
-    t_matrix: Array2<f64>,
+    colorimetry: Colorimetry,
+    targets: std::sync::Arc<TargetReflectance>,
 }
 
 impl PaintMixingService {
-    /// Create a new paint mixing service with the T-matrix for color conversion
-    pub fn new(t_matrix: Array2<f64>) -> Self {
-        Self { t_matrix }
+    /// Create a new paint mixing service judging colour under the given colorimetry
+    pub fn new(colorimetry: Colorimetry) -> Self {
+        Self {
+            targets: TargetReflectance::for_colorimetry(&colorimetry),
+            colorimetry,
+        }
     }
 
-    /// Calculate target reflectance from RGB color using LHTSS algorithm
-    pub fn calculate_target_reflectance(&self, rgb: [u8; 3]) -> Result<Array1<f64>, String> {
-        let lhtss = LHTSS::new(self.t_matrix.clone());
-        lhtss.compute_reflectance_target(rgb)
+    /// Wavelengths the target and paint curves must be sampled at
+    pub fn grid(&self) -> SpectralGrid {
+        self.colorimetry.grid()
+    }
+
+    /// Calculate target reflectance from a D65-relative XYZ color (Y = 1 for
+    /// white) using the LHTSS algorithm under the service's illuminant, on
+    /// the service's spectral grid. Repeated colours come from a cache.
+    pub fn calculate_target_reflectance(&self, xyz: [f64; 3]) -> Result<Array1<f64>, String> {
+        self.targets.reflectance(xyz)
     }
 
     /// Find optimal paint combinations for a target color
     pub fn find_combinations(
         &self,
         target_reflectance: &Array1<f64>,
-        paint_data: &[(String, Array1<f64>, String)],
+        paint_data: &[MixPaint],
         mix_choice: &str,
     ) -> Result<Vec<MixingResult>, ColorError> {
-        let results = match mix_choice.to_lowercase().as_str() {
-            "black + white + 2 colours" => {
-                self.find_black_white_n_colors(target_reflectance, paint_data, 2)?
-            }
-            "black + white + 3 colours" => {
-                self.find_black_white_n_colors(target_reflectance, paint_data, 3)?
-            }
-            "all available colours" => {
-                self.find_all_available_colors(target_reflectance, paint_data)?
-            }
-            "neutral greys" => self.find_neutral_greys(target_reflectance, paint_data)?,
-            "no black" => self.find_no_black(target_reflectance, paint_data)?,
-            _ => return Err(ColorError::OptimizationError("Invalid mix choice".into())),
+        let combinations = match mix_choice.parse()? {
+            MixChoice::BlackWhite2Colors => black_white_n_colors(paint_data, 2)?,
+            MixChoice::BlackWhite3Colors => black_white_n_colors(paint_data, 3)?,
+            MixChoice::AllAvailableColors => all_available_colors(paint_data),
+            MixChoice::NeutralGreys => neutral_greys(paint_data),
+            MixChoice::NoBlack => no_black(paint_data),
         };
-
-        // Sort by error and take top 5
-        let mut sorted = results;
-        sorted.sort_by(|a, b| {
-            a.error
-                .partial_cmp(&b.error)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        Ok(sorted.into_iter().take(5).collect())
+        Ok(self.best_mixes(target_reflectance, &combinations, 5))
     }
 
-    /// Find combinations using white + black + N other colors
-    fn find_black_white_n_colors(
+    /// Optimise every combination's weights in parallel, each worker thread
+    /// reusing one set of buffers, and return the `count` closest mixes
+    fn best_mixes(
         &self,
         target: &Array1<f64>,
-        paint_data: &[(String, Array1<f64>, String)],
-        n_extra: usize,
-    ) -> Result<Vec<MixingResult>, ColorError> {
-        // Find white and black
-        let white = paint_data
-            .iter()
-            .find(|(name, _, _)| name.to_lowercase().trim() == "titanium white")
-            .ok_or_else(|| ColorError::MissingColor("Titanium White".into()))?
-            .clone();
-        let black = paint_data
-            .iter()
-            .find(|(name, _, _)| name.to_lowercase().trim() == "ivory black")
-            .ok_or_else(|| ColorError::MissingColor("Ivory Black".into()))?
-            .clone();
+        combinations: &[Vec<&MixPaint>],
+        count: usize,
+    ) -> Vec<MixingResult> {
+        let target_lab = self.colorimetry.reflectance_to_lab(target);
+        let target = target.to_vec();
 
-        // Get other colors (excluding white, black, and warm white)
-        let other_paints: Vec<_> = paint_data
-            .iter()
-            .filter(|(name, _, _)| {
-                let name_lower = name.to_lowercase();
-                name_lower.trim() != "titanium white"
-                    && name_lower.trim() != "ivory black"
-                    && name_lower.trim() != "warm white"
-            })
-            .cloned()
-            .collect();
-
-        // Generate combinations based on n_extra
-        let combinations: Vec<Vec<(String, Array1<f64>, String)>> = if n_extra == 2 {
-            // 2 extra colors
-            let mut combos = Vec::new();
-            for (i, paint2) in other_paints.iter().enumerate() {
-                for paint3 in other_paints.iter().skip(i + 1) {
-                    combos.push(vec![
-                        white.clone(),
-                        black.clone(),
-                        paint2.clone(),
-                        paint3.clone(),
-                    ]);
-                }
-            }
-            combos
-        } else if n_extra == 3 {
-            // 3 extra colors
-            let mut combos = Vec::new();
-            for (i, paint2) in other_paints.iter().enumerate() {
-                for (j, paint3) in other_paints.iter().enumerate().skip(i + 1) {
-                    for paint4 in other_paints.iter().skip(j + 1) {
-                        combos.push(vec![
-                            white.clone(),
-                            black.clone(),
-                            paint2.clone(),
-                            paint3.clone(),
-                            paint4.clone(),
-                        ]);
-                    }
-                }
-            }
-            combos
-        } else {
-            Vec::new()
-        };
-
-        // Process in parallel
-        let target_clone = target.clone();
-        let results: Vec<MixingResult> = combinations
+        let mut scored: Vec<(f64, usize, Vec<f64>)> = combinations
             .par_iter()
-            .filter_map(|paints| {
-                let n = paints.len();
-                let initial_weights = vec![1.0 / n as f64; n];
-                optimize_weights(paints, &initial_weights, &target_clone)
-                    .ok()
-                    .map(|weights| self.create_result(paints, weights, &target_clone))
-            })
+            .enumerate()
+            .map_init(
+                || (MixScratch::default(), Vec::with_capacity(MAX_MIX_PAINTS)),
+                |(scratch, ks), (i, paints)| {
+                    ks.clear();
+                    ks.extend(
+                        paints
+                            .iter()
+                            .map(|p| p.ks.as_slice().expect("K/S curves are contiguous")),
+                    );
+                    optimize_weights(ks, &target, scratch);
+
+                    // Delta E under the service's illuminant
+                    let mixed_lab = self.colorimetry.reflectance_to_lab(scratch.mixed());
+                    (delta_e76(&mixed_lab, &target_lab), i, scratch.weights().to_vec())
+                },
+            )
             .collect();
 
-        Ok(results)
+        // Sort by error, keeping the combinations' order among equals
+        scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored
+            .into_iter()
+            .take(count)
+            .map(|(error, i, weights)| create_result(&combinations[i], weights, error))
+            .collect()
     }
+}
 
-    /// Find combinations using all available colors
-    fn find_all_available_colors(
-        &self,
-        target: &Array1<f64>,
-        paint_data: &[(String, Array1<f64>, String)],
-    ) -> Result<Vec<MixingResult>, ColorError> {
-        let mut all_combinations: Vec<Vec<(String, Array1<f64>, String)>> = Vec::new();
-
-        // Try 3, 4, and 5 paint combinations
-        for n_paints in 3..=5 {
-            for i in 0..paint_data.len().saturating_sub(n_paints - 1) {
-                let combo: Vec<_> = paint_data[i..i + n_paints].to_vec();
-                all_combinations.push(combo);
+/// Most paints in any combination the search tries
+const MAX_MIX_PAINTS: usize = 5;
+
+/// Combinations of white + black + N other colors
+fn black_white_n_colors(
+    paint_data: &[MixPaint],
+    n_extra: usize,
+) -> Result<Vec<Vec<&MixPaint>>, ColorError> {
+    // Find white and black
+    let white = role_paint(paint_data, PaintRole::White)
+        .ok_or_else(|| ColorError::MissingColor(PaintRole::White.label().into()))?;
+    let black = role_paint(paint_data, PaintRole::Black)
+        .ok_or_else(|| ColorError::MissingColor(PaintRole::Black.label().into()))?;
+
+    // Get other colors (excluding every white and black)
+    let other_paints: Vec<_> = paint_data
+        .iter()
+        .filter(|p| !p.has_role(PaintRole::White) && !p.has_role(PaintRole::Black))
+        .collect();
+
+    // Generate combinations based on n_extra
+    let mut combos = Vec::new();
+    if n_extra == 2 {
+        for (i, paint2) in other_paints.iter().enumerate() {
+            for paint3 in other_paints.iter().skip(i + 1) {
+                combos.push(vec![white, black, *paint2, *paint3]);
             }
         }
-
-        // Process in parallel
-        let target_clone = target.clone();
-        let results: Vec<MixingResult> = all_combinations
-            .par_iter()
-            .filter_map(|combo| {
-                let initial_weights = vec![1.0 / combo.len() as f64; combo.len()];
-                optimize_weights(combo, &initial_weights, &target_clone)
-                    .ok()
-                    .map(|weights| self.create_result(combo, weights, &target_clone))
-            })
-            .collect();
-
-        Ok(results)
-    }
-
-    /// Find combinations using neutral greys
-    fn find_neutral_greys(
-        &self,
-        target: &Array1<f64>,
-        paint_data: &[(String, Array1<f64>, String)],
-    ) -> Result<Vec<MixingResult>, ColorError> {
-        let grey_paints: Vec<_> = paint_data
-            .iter()
-            .filter(|(name, _, _)| {
-                name.to_lowercase().contains("grey") || name.to_lowercase().contains("gray")
-            })
-            .cloned()
-            .collect();
-
-        if grey_paints.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        let other_paints: Vec<_> = paint_data
-            .iter()
-            .filter(|(name, _, _)| {
-                let name_lower = name.to_lowercase();
-                !name_lower.contains("grey")
-                    && !name_lower.contains("gray")
-                    && name_lower.trim() != "titanium white"
-                    && name_lower.trim() != "ivory black"
-                    && name_lower.trim() != "warm white"
-            })
-            .cloned()
-            .collect();
-
-        // Generate combinations
-        let mut combinations: Vec<Vec<(String, Array1<f64>, String)>> = Vec::new();
-        for grey in &grey_paints {
-            for (i, paint2) in other_paints.iter().enumerate() {
-                for paint3 in other_paints.iter().skip(i + 1) {
-                    combinations.push(vec![grey.clone(), paint2.clone(), paint3.clone()]);
+    } else if n_extra == 3 {
+        for (i, paint2) in other_paints.iter().enumerate() {
+            for (j, paint3) in other_paints.iter().enumerate().skip(i + 1) {
+                for paint4 in other_paints.iter().skip(j + 1) {
+                    combos.push(vec![white, black, *paint2, *paint3, *paint4]);
                 }
             }
         }
-
-        // Process in parallel
-        let target_clone = target.clone();
-        let results: Vec<MixingResult> = combinations
-            .par_iter()
-            .filter_map(|combo| {
-                let initial_weights = vec![1.0 / combo.len() as f64; combo.len()];
-                optimize_weights(combo, &initial_weights, &target_clone)
-                    .ok()
-                    .map(|weights| self.create_result(combo, weights, &target_clone))
-            })
-            .collect();
-
-        Ok(results)
     }
+    Ok(combos)
+}
 
-    /// Find combinations without black
-    fn find_no_black(
-        &self,
-        target: &Array1<f64>,
-        paint_data: &[(String, Array1<f64>, String)],
-    ) -> Result<Vec<MixingResult>, ColorError> {
-        let available: Vec<_> = paint_data
-            .iter()
-            .filter(|(name, _, _)| !name.to_lowercase().contains("black"))
-            .cloned()
-            .collect();
-
-        let mut all_combinations: Vec<Vec<(String, Array1<f64>, String)>> = Vec::new();
+/// Runs of 3 up to `MAX_MIX_PAINTS` neighbouring paints from all available colors
+fn all_available_colors(paint_data: &[MixPaint]) -> Vec<Vec<&MixPaint>> {
+    let mut all_combinations = Vec::new();
+    for n_paints in 3..=MAX_MIX_PAINTS {
+        for window in paint_data.windows(n_paints) {
+            all_combinations.push(window.iter().collect());
+        }
+    }
+    all_combinations
+}
 
-        for n_paints in 3..=4 {
-            for i in 0..available.len().saturating_sub(n_paints - 1) {
-                let combo: Vec<_> = available[i..i + n_paints].to_vec();
-                all_combinations.push(combo);
+/// Combinations of a neutral grey and two other colors
+fn neutral_greys(paint_data: &[MixPaint]) -> Vec<Vec<&MixPaint>> {
+    let grey_paints: Vec<_> = paint_data
+        .iter()
+        .filter(|p| p.has_role(PaintRole::NeutralGrey))
+        .collect();
+
+    let other_paints: Vec<_> = paint_data
+        .iter()
+        .filter(|p| {
+            !p.has_role(PaintRole::NeutralGrey)
+                && !p.has_role(PaintRole::White)
+                && !p.has_role(PaintRole::Black)
+        })
+        .collect();
+
+    let mut combinations = Vec::new();
+    for grey in &grey_paints {
+        for (i, paint2) in other_paints.iter().enumerate() {
+            for paint3 in other_paints.iter().skip(i + 1) {
+                combinations.push(vec![*grey, *paint2, *paint3]);
             }
         }
-
-        // Process in parallel
-        let target_clone = target.clone();
-        let results: Vec<MixingResult> = all_combinations
-            .par_iter()
-            .filter_map(|combo| {
-                let initial_weights = vec![1.0 / combo.len() as f64; combo.len()];
-                optimize_weights(combo, &initial_weights, &target_clone)
-                    .ok()
-                    .map(|weights| self.create_result(combo, weights, &target_clone))
-            })
-            .collect();
-
-        Ok(results)
     }
+    combinations
+}
 
-    fn create_result(
-        &self,
-        paints: &[(String, Array1<f64>, String)],
-        weights: Vec<f64>,
-        target: &Array1<f64>,
-    ) -> MixingResult {
-        // Calculate mixed reflectance using Kubelka-Munk
-        let reflectance_data: Vec<Array1<f64>> = paints.iter().map(|(_, r, _)| r.clone()).collect();
-        let mixed = kubelka_munk_mix(&reflectance_data, &weights);
-
-        // Calculate Delta E error using LHTSS color space conversion
-        let lhtss = LHTSS::new(self.t_matrix.clone());
-        let mixed_xyz = lhtss.reflectance_to_xyz(&mixed);
-        let target_xyz = lhtss.reflectance_to_xyz(target);
-        let mixed_lab = lhtss.xyz_to_lab(&mixed_xyz);
-        let target_lab = lhtss.xyz_to_lab(&target_xyz);
-        let error = lhtss.delta_e(&mixed_lab, &target_lab);
-
-        MixingResult {
-            paints: paints.iter().map(|(name, _, _)| name.clone()).collect(),
-            weights,
-            error,
-            hex_colors: paints.iter().map(|(_, _, hex)| hex.clone()).collect(),
+/// Runs of 3 and 4 neighbouring paints, leaving out black
+fn no_black(paint_data: &[MixPaint]) -> Vec<Vec<&MixPaint>> {
+    let available: Vec<_> = paint_data
+        .iter()
+        .filter(|p| !p.has_role(PaintRole::Black))
+        .collect();
+
+    let mut all_combinations = Vec::new();
+    for n_paints in 3..=4 {
+        for window in available.windows(n_paints) {
+            all_combinations.push(window.to_vec());
         }
     }
+    all_combinations
 }
 
-/// Get default T-matrix for D65 illuminant, 10-degree observer
-pub fn get_default_t_matrix() -> Array2<f64> {
-    // Standard CIE 1964 10-degree observer color matching functions
-    // scaled for D65 illuminant, 36 wavelengths from 380nm to 730nm (10nm steps)
-    let x_bar = [
-        0.000160, 0.002362, 0.019110, 0.084736, 0.204492, 0.314679, 0.383734, 0.370702, 0.302273,
-        0.195618, 0.080507, 0.016172, 0.003816, 0.037465, 0.117749, 0.236491, 0.376772, 0.529826,
-        0.705224, 0.878655, 1.014160, 1.118520, 1.123990, 1.030480, 0.856297, 0.647467, 0.431567,
-        0.268329, 0.152568, 0.081261, 0.040851, 0.019941, 0.009577, 0.004539, 0.002175, 0.001060,
-    ];
-    let y_bar = [
-        0.000017, 0.000253, 0.002004, 0.008756, 0.021391, 0.038676, 0.062077, 0.089456, 0.128201,
-        0.185190, 0.253589, 0.339133, 0.460777, 0.606741, 0.761757, 0.875211, 0.961988, 0.991761,
-        0.997340, 0.955552, 0.868934, 0.777405, 0.658341, 0.527963, 0.398057, 0.283493, 0.179828,
-        0.107633, 0.060281, 0.031800, 0.015905, 0.007749, 0.003718, 0.001762, 0.000846, 0.000415,
-    ];
-    let z_bar = [
-        0.000705, 0.010482, 0.086011, 0.389366, 0.972542, 1.553480, 1.967280, 1.994800, 1.745370,
-        1.317560, 0.772125, 0.415254, 0.218502, 0.112044, 0.060709, 0.030451, 0.013676, 0.003988,
-        0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
-        0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
-    ];
+fn create_result(paints: &[&MixPaint], weights: Vec<f64>, error: f64) -> MixingResult {
+    let warnings = mix_warnings(paints, &weights);
 
-    let mut t_matrix = Array2::zeros((3, 36));
-    for i in 0..36 {
-        t_matrix[[0, i]] = x_bar[i];
-        t_matrix[[1, i]] = y_bar[i];
-        t_matrix[[2, i]] = z_bar[i];
+    MixingResult {
+        paints: paints.iter().map(|p| p.name.clone()).collect(),
+        brands: paints.iter().map(|p| p.brand.clone()).collect(),
+        weights,
+        error,
+        hex_colors: paints.iter().map(|p| p.hex.clone()).collect(),
+        warnings,
     }
-    t_matrix
 }
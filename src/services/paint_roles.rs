@@ -0,0 +1,252 @@
+//! Paint roles detected from reflectance curves
+//!
+//! Brands name the same paint differently ("Titanium White (Linseed)", "Mars
+//! Black"), so the white, black, earth and neutral grey of a palette are
+//! recognised by how their curves look rather than by name. Detection is a
+//! starting point; roles are stored per paint and can be corrected.
+
+use ndarray::Array1;
+
+use crate::db::SpectralCurve;
+use crate::models::{PaintRole, SpectralGrid};
+use crate::services::colorimetry::Colorimetry;
+
+/// Whites are at least this light (CIELAB L*)
+const WHITE_MIN_LIGHTNESS: f64 = 85.0;
+/// Blacks are at most this light
+const BLACK_MAX_LIGHTNESS: f64 = 32.0;
+/// Neutral greys are mid-tones; darker low chroma paints are usually the
+/// masstone of a deep blue, green or violet
+const GREY_MIN_LIGHTNESS: f64 = 36.0;
+/// Largest CIELAB chroma of a white
+const WHITE_MAX_CHROMA: f64 = 8.0;
+/// Largest chroma of a black. Dark blues and greens measure within a few
+/// units of neutral, so this is tight.
+const BLACK_MAX_CHROMA: f64 = 2.5;
+/// Largest chroma of a black per unit of lightness. The darkest blues have
+/// less chroma than a lamp black, but far more for how dark they are.
+const BLACK_MAX_SATURATION: f64 = 0.3;
+const GREY_MAX_CHROMA: f64 = 6.0;
+/// Flat curves reflect red and blue about equally; a red to blue ratio above
+/// this is a warm colour however dark, which is what separates umbers from blacks
+const FLAT_MAX_TILT: f64 = 1.3;
+const FLAT_MIN_TILT: f64 = 0.75;
+/// Whites and blacks reflect evenly: between `SPREAD_NM` the highest point of
+/// the curve is at most this many times the lowest
+const WHITE_MAX_SPREAD: f64 = 1.6;
+const BLACK_MAX_SPREAD: f64 = 1.5;
+/// Titanium and zinc absorb in the violet, so the ends of the visible range
+/// are left out of the spread
+const SPREAD_NM: std::ops::RangeInclusive<f64> = 420.0..=680.0;
+/// Reflectance below this is measurement noise, so the spread and tilt of a
+/// very dark curve are taken from this floor
+const NOISE_FLOOR: f64 = 0.005;
+/// Earths rise gradually towards red; cadmiums and other clean warm colours
+/// rise far more steeply
+const EARTH_MAX_TILT: f64 = 15.0;
+/// CIELAB hue angles, in degrees, of red-brown through yellow-olive earths.
+/// Alizarins and other crimsons sit just below.
+const EARTH_HUES: std::ops::RangeInclusive<f64> = 28.0..=95.0;
+/// Near-black paints have too little colour for their hue to mean anything
+const EARTH_MIN_CHROMA: f64 = 2.0;
+const EARTH_MAX_CHROMA: f64 = 60.0;
+const EARTH_MIN_LIGHTNESS: f64 = 8.0;
+const EARTH_MAX_LIGHTNESS: f64 = 62.0;
+/// Earths climb, if unevenly, from blue to red. Dioxazine and other violets
+/// reflect far more in the blue than in the green; a fall of more than this
+/// before the red rise rules a paint out.
+const EARTH_MAX_FALL: f64 = 2.0;
+/// Where the red rise of a warm colour starts, in nm
+const RED_RISE_NM: f64 = 600.0;
+
+/// Guess a paint's role from its reflectance curve on the colorimetry's grid
+pub fn detect_role(reflectance: &Array1<f64>, colorimetry: &Colorimetry) -> Option<PaintRole> {
+    let [l, a, b] = colorimetry.reflectance_to_lab(reflectance);
+    let chroma = a.hypot(b);
+    let hue = b.atan2(a).to_degrees().rem_euclid(360.0);
+
+    let grid = colorimetry.grid();
+    let values = reflectance.as_slice()?;
+    let blue = grid.interpolate(values, 460.0).max(NOISE_FLOOR);
+    let red = grid.interpolate(values, 680.0).max(NOISE_FLOOR);
+    let tilt = red / blue;
+    let flat = (FLAT_MIN_TILT..=FLAT_MAX_TILT).contains(&tilt);
+    let spread = spread(values, grid);
+
+    if l >= WHITE_MIN_LIGHTNESS && chroma <= WHITE_MAX_CHROMA && spread <= WHITE_MAX_SPREAD {
+        Some(PaintRole::White)
+    } else if flat
+        && l <= BLACK_MAX_LIGHTNESS
+        && chroma <= BLACK_MAX_CHROMA
+        && chroma <= BLACK_MAX_SATURATION * l
+        && spread <= BLACK_MAX_SPREAD
+    {
+        Some(PaintRole::Black)
+    } else if flat && l >= GREY_MIN_LIGHTNESS && chroma <= GREY_MAX_CHROMA {
+        Some(PaintRole::NeutralGrey)
+    } else if tilt > FLAT_MAX_TILT
+        && tilt <= EARTH_MAX_TILT
+        && EARTH_HUES.contains(&hue)
+        && (EARTH_MIN_CHROMA..=EARTH_MAX_CHROMA).contains(&chroma)
+        && (EARTH_MIN_LIGHTNESS..=EARTH_MAX_LIGHTNESS).contains(&l)
+        && fall_before_red(values, grid) <= EARTH_MAX_FALL
+    {
+        Some(PaintRole::Earth)
+    } else {
+        None
+    }
+}
+
+/// Highest reflectance over the lowest within `SPREAD_NM`, both at least the
+/// noise floor
+fn spread(values: &[f64], grid: SpectralGrid) -> f64 {
+    let floored = grid
+        .wavelengths()
+        .zip(values)
+        .filter(|(wavelength, _)| SPREAD_NM.contains(wavelength))
+        .map(|(_, v)| v.max(NOISE_FLOOR));
+    let (low, high) = floored.fold((f64::MAX, 0.0_f64), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    high / low
+}
+
+/// Largest drop, as a ratio, from any point of the curve to a later one
+/// before the red rise
+fn fall_before_red(values: &[f64], grid: SpectralGrid) -> f64 {
+    let mut peak = 0.0_f64;
+    let mut fall = 1.0_f64;
+    for (wavelength, &value) in grid.wavelengths().zip(values) {
+        if wavelength > RED_RISE_NM {
+            break;
+        }
+        let value = value.max(NOISE_FLOOR);
+        peak = peak.max(value);
+        fall = fall.max(peak / value);
+    }
+    fall
+}
+
+/// Guess the role of a stored curve, whatever grid it is on
+pub fn detect_curve_role(curve: &SpectralCurve, colorimetry: &Colorimetry) -> Option<PaintRole> {
+    detect_role(&Array1::from_vec(curve.resampled(colorimetry.grid())), colorimetry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CurveMetadata;
+
+    // Curves from the reference database, 400 to 700 nm in 10 nm steps
+
+    const MH_TITANIUM_WHITE: [f64; 31] = [
+        0.4369, 0.6770, 0.7916, 0.8259, 0.8444, 0.8603, 0.8714, 0.8805, 0.8866, 0.8950, 0.8966,
+        0.8971, 0.8940, 0.8939, 0.8916, 0.8926, 0.8943, 0.8986, 0.8975, 0.8972, 0.8985, 0.8992,
+        0.8962, 0.8979, 0.8970, 0.8998, 0.9030, 0.9029, 0.9036, 0.9084, 0.9100,
+    ];
+    const MH_NEUTRAL_GREY: [f64; 31] = [
+        0.1798, 0.1908, 0.1936, 0.1944, 0.1937, 0.1930, 0.1922, 0.1906, 0.1891, 0.1882, 0.1867,
+        0.1852, 0.1843, 0.1832, 0.1810, 0.1805, 0.1817, 0.1832, 0.1837, 0.1838, 0.1825, 0.1810,
+        0.1787, 0.1766, 0.1741, 0.1742, 0.1736, 0.1719, 0.1708, 0.1698, 0.1681,
+    ];
+    const GAMBLIN_LAMP_BLACK: [f64; 31] = [
+        0.0229, 0.0233, 0.0236, 0.0240, 0.0242, 0.0244, 0.0247, 0.0250, 0.0252, 0.0255, 0.0257,
+        0.0260, 0.0263, 0.0265, 0.0267, 0.0270, 0.0271, 0.0274, 0.0276, 0.0278, 0.0281, 0.0283,
+        0.0286, 0.0288, 0.0290, 0.0293, 0.0296, 0.0298, 0.0300, 0.0302, 0.0305,
+    ];
+    const WILLIAMSBURG_IVORY_BLACK: [f64; 31] = [
+        0.0118, 0.0118, 0.0118, 0.0128, 0.0133, 0.0135, 0.0133, 0.0128, 0.0124, 0.0123, 0.0124,
+        0.0124, 0.0125, 0.0125, 0.0125, 0.0125, 0.0124, 0.0124, 0.0125, 0.0127, 0.0127, 0.0127,
+        0.0126, 0.0126, 0.0126, 0.0126, 0.0126, 0.0125, 0.0125, 0.0124, 0.0125,
+    ];
+    const GAMBLIN_BURNT_SIENNA: [f64; 31] = [
+        0.0352, 0.0361, 0.0368, 0.0376, 0.0385, 0.0392, 0.0399, 0.0403, 0.0409, 0.0415, 0.0423,
+        0.0432, 0.0444, 0.0460, 0.0483, 0.0520, 0.0578, 0.0669, 0.0783, 0.0913, 0.1040, 0.1159,
+        0.1263, 0.1352, 0.1433, 0.1510, 0.1583, 0.1654, 0.1727, 0.1800, 0.1875,
+    ];
+    const DR_INDIAN_RED: [f64; 31] = [
+        0.0139, 0.0139, 0.0139, 0.0143, 0.0146, 0.0146, 0.0146, 0.0146, 0.0147, 0.0147, 0.0152,
+        0.0156, 0.0163, 0.0173, 0.0190, 0.0225, 0.0302, 0.0458, 0.0704, 0.1008, 0.1289, 0.1495,
+        0.1625, 0.1707, 0.1774, 0.1843, 0.1927, 0.2028, 0.2157, 0.2305, 0.2465,
+    ];
+    const DR_PRUSSIAN_BLUE: [f64; 31] = [
+        0.0031, 0.0031, 0.0031, 0.0036, 0.0036, 0.0033, 0.0029, 0.0030, 0.0036, 0.0034, 0.0032,
+        0.0031, 0.0032, 0.0033, 0.0035, 0.0038, 0.0039, 0.0041, 0.0043, 0.0046, 0.0048, 0.0049,
+        0.0050, 0.0051, 0.0052, 0.0054, 0.0055, 0.0057, 0.0060, 0.0059, 0.0065,
+    ];
+    const DR_CRIMSON_ALIZARIN: [f64; 31] = [
+        0.0087, 0.0087, 0.0087, 0.0095, 0.0096, 0.0095, 0.0092, 0.0088, 0.0085, 0.0085, 0.0088,
+        0.0091, 0.0094, 0.0097, 0.0100, 0.0102, 0.0105, 0.0113, 0.0126, 0.0153, 0.0204, 0.0298,
+        0.0454, 0.0665, 0.0882, 0.1057, 0.1176, 0.1262, 0.1342, 0.1434, 0.1552,
+    ];
+    const MH_DEEP_PURPLE_DIOXAZINE: [f64; 31] = [
+        0.0541, 0.0445, 0.0417, 0.0376, 0.0371, 0.0359, 0.0347, 0.0331, 0.0302, 0.0251, 0.0226,
+        0.0229, 0.0279, 0.0363, 0.0373, 0.0451, 0.0591, 0.0705, 0.0718, 0.0684, 0.0693, 0.0720,
+        0.0722, 0.0699, 0.0699, 0.0770, 0.0872, 0.1018, 0.1129, 0.1211, 0.1239,
+    ];
+    const VG_LAMP_BLACK: [f64; 31] = [
+        0.0027, 0.0027, 0.0027, 0.0030, 0.0032, 0.0032, 0.0031, 0.0029, 0.0028, 0.0030, 0.0031,
+        0.0031, 0.0032, 0.0032, 0.0032, 0.0032, 0.0032, 0.0033, 0.0034, 0.0035, 0.0036, 0.0036,
+        0.0037, 0.0038, 0.0039, 0.0039, 0.0041, 0.0043, 0.0049, 0.0049, 0.0051,
+    ];
+    const MH_RAW_UMBER: [f64; 31] = [
+        0.0492, 0.0489, 0.0508, 0.0530, 0.0521, 0.0515, 0.0501, 0.0486, 0.0471, 0.0469, 0.0531,
+        0.0586, 0.0570, 0.0543, 0.0497, 0.0514, 0.0570, 0.0594, 0.0553, 0.0550, 0.0578, 0.0598,
+        0.0619, 0.0632, 0.0597, 0.0581, 0.0600, 0.0649, 0.0670, 0.0697, 0.0724,
+    ];
+    const VG_VANDYKE_BROWN: [f64; 31] = [
+        0.0090, 0.0090, 0.0090, 0.0101, 0.0106, 0.0108, 0.0108, 0.0104, 0.0103, 0.0107, 0.0108,
+        0.0110, 0.0112, 0.0114, 0.0116, 0.0119, 0.0122, 0.0126, 0.0129, 0.0133, 0.0134, 0.0135,
+        0.0135, 0.0135, 0.0135, 0.0136, 0.0138, 0.0139, 0.0142, 0.0144, 0.0147,
+    ];
+    const SENNELIER_PERMANENT_ALIZARIN_CRIMSON: [f64; 31] = [
+        0.0778, 0.0707, 0.0694, 0.0691, 0.0731, 0.0773, 0.0823, 0.0827, 0.0867, 0.0900, 0.0895,
+        0.0917, 0.0887, 0.0880, 0.0836, 0.0773, 0.0694, 0.0612, 0.0807, 0.1783, 0.3106, 0.4235,
+        0.4881, 0.5103, 0.5150, 0.5127, 0.5097, 0.5125, 0.5221, 0.5280, 0.5410,
+    ];
+    const WILLIAMSBURG_OLIVE_GREEN: [f64; 31] = [
+        0.0721, 0.0686, 0.0733, 0.0733, 0.0746, 0.0786, 0.0800, 0.0790, 0.0969, 0.1285, 0.1893,
+        0.2506, 0.2645, 0.2482, 0.2309, 0.2156, 0.2025, 0.1915, 0.1925, 0.2074, 0.2257, 0.2373,
+        0.2401, 0.2410, 0.2378, 0.2381, 0.2399, 0.2422, 0.2476, 0.2545, 0.2649,
+    ];
+
+    fn role(values: [f64; 31]) -> Option<PaintRole> {
+        let curve =
+            SpectralCurve::new(SpectralGrid::PAINT_DATA, values.to_vec(), CurveMetadata::default());
+        detect_curve_role(&curve, &Colorimetry::default())
+    }
+
+    #[test]
+    fn whites_greys_and_blacks() {
+        assert_eq!(role(MH_TITANIUM_WHITE), Some(PaintRole::White));
+        assert_eq!(role(MH_NEUTRAL_GREY), Some(PaintRole::NeutralGrey));
+        assert_eq!(role(GAMBLIN_LAMP_BLACK), Some(PaintRole::Black));
+        assert_eq!(role(WILLIAMSBURG_IVORY_BLACK), Some(PaintRole::Black));
+        // So dark that its slight rise towards red is measurement noise
+        assert_eq!(role(VG_LAMP_BLACK), Some(PaintRole::Black));
+    }
+
+    #[test]
+    fn earths() {
+        assert_eq!(role(GAMBLIN_BURNT_SIENNA), Some(PaintRole::Earth));
+        assert_eq!(role(DR_INDIAN_RED), Some(PaintRole::Earth));
+    }
+
+    #[test]
+    fn crimsons_blues_and_violets_are_not_earths() {
+        assert_eq!(role(DR_PRUSSIAN_BLUE), None);
+        assert_eq!(role(DR_CRIMSON_ALIZARIN), None);
+        assert_eq!(role(MH_DEEP_PURPLE_DIOXAZINE), None);
+        assert_eq!(role(SENNELIER_PERMANENT_ALIZARIN_CRIMSON), None);
+    }
+
+    #[test]
+    fn dark_earths_are_not_blacks() {
+        // Nearly as neutral as a black, but rising towards red
+        assert_eq!(role(MH_RAW_UMBER), Some(PaintRole::Earth));
+        assert_eq!(role(VG_VANDYKE_BROWN), Some(PaintRole::Earth));
+    }
+
+    #[test]
+    fn a_light_green_is_not_white() {
+        assert_eq!(role(WILLIAMSBURG_OLIVE_GREEN), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Integrity checks for the built-in paint data
+//!
+//! Run at startup and by the `validate` subcommand. Errors make a paint
+//! unusable or wrong in mixes; warnings are worth fixing in the data but do
+//! not stop it being mixed.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use ndarray::Array1;
+
+use crate::db::{self, CurveDecodeError, Db, PaintColor, UserStore};
+use crate::models::{delta_e76, parse_hex, srgb_to_lab, to_hex, xyz_to_srgb, PaintRole};
+use crate::services::colorimetry::Colorimetry;
+
+/// ΔE76 between the stored swatch colour and the colour of the curve beyond
+/// which the two are reported as inconsistent
+pub const HEX_TOLERANCE: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// What a validation issue is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// The spectral blob is missing or cannot be decoded
+    Encoding,
+    SpectralLength,
+    ValueRange,
+    /// Wavelengths do not increase, or do not cover the mixing grid
+    Grid,
+    /// `d65_10deg_hex` is missing or disagrees with the curve
+    Hex,
+    /// Leading or trailing whitespace, or an empty name
+    Name,
+    DuplicateName,
+    /// No paint in the brand has the white role
+    MissingWhite,
+    MissingBlack,
+    /// The data could not be read, or the file failed its integrity check
+    Database,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub check: Check,
+    pub brand: String,
+    pub paint: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.paint {
+            Some(paint) => write!(f, "{}: {} / {:?}: {}", severity, self.brand, paint, self.message),
+            None => write!(f, "{}: {}: {}", severity, self.brand, self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub paints_checked: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|i| i.severity == severity).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// One line totals, e.g. "439 paints checked: 2 errors, 31 warnings"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} paints checked: {} errors, {} warnings",
+            self.paints_checked,
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        writeln!(f, "{}", self.summary())
+    }
+}
+
+/// Label of issues about the reference database as a whole
+const DATABASE: &str = "reference database";
+
+/// Check the database file and every catalogued brand, with the admins' edits
+/// from the user database applied
+pub async fn validate_paint_data(db: &Db, users: &dyn UserStore) -> ValidationReport {
+    let colorimetry = Colorimetry::default();
+    let mut report = ValidationReport::default();
+    let error = |check, brand: &str, paint, message| ValidationIssue {
+        severity: Severity::Error,
+        check,
+        brand: brand.to_string(),
+        paint,
+        message,
+    };
+
+    match db::integrity_problems(db).await {
+        Ok(problems) => {
+            if let Some(first) = problems.first() {
+                let more = match problems.len() - 1 {
+                    0 => String::new(),
+                    n => format!(" and {} more problems", n),
+                };
+                let message = format!("integrity check failed: {}{}", first, more);
+                report.issues.push(error(Check::Database, DATABASE, None, message));
+            }
+        }
+        Err(e) => {
+            let message = format!("could not run the integrity check: {}", e);
+            report.issues.push(error(Check::Database, DATABASE, None, message));
+        }
+    }
+
+    let overlay = match users.catalogue_overlay().await {
+        Ok(overlay) => overlay,
+        Err(e) => {
+            let message = format!("could not read the catalogue edits: {}", e);
+            report.issues.push(error(Check::Database, DATABASE, None, message));
+            Default::default()
+        }
+    };
+    let brands = match db::get_paint_brands(db, &overlay).await {
+        Ok(brands) => brands,
+        Err(e) => {
+            let message = format!("could not read the brands: {}", e);
+            report.issues.push(error(Check::Database, DATABASE, None, message));
+            vec![]
+        }
+    };
+    for brand in brands {
+        match db::get_paint_colors(db, &overlay, &brand).await {
+            Ok(paints) => {
+                report.paints_checked += paints.len();
+                report.issues.extend(validate_brand(&brand, &paints, &colorimetry));
+            }
+            Err(e) => {
+                let message = format!("could not read the paints: {}", e);
+                report.issues.push(error(Check::Database, &brand, None, message));
+            }
+        }
+    }
+
+    match db::list_curve_migration_failures(db).await {
+        Ok(failures) => report.issues.extend(failures.into_iter().map(|f| {
+            let message = format!("curve was not converted to the versioned encoding: {}", f.error);
+            error(Check::Encoding, &f.brand_id, Some(f.name), message)
+        })),
+        Err(e) => {
+            let message = format!("could not read the unconverted curves: {}", e);
+            report.issues.push(error(Check::Database, DATABASE, None, message));
+        }
+    }
+    report
+}
+
+/// Check one brand's paints, individually and as a set
+pub fn validate_brand(brand: &str, paints: &[PaintColor], colorimetry: &Colorimetry) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |severity, check, paint: Option<&str>, message: String| {
+        issues.push(ValidationIssue {
+            severity,
+            check,
+            brand: brand.to_string(),
+            paint: paint.map(str::to_string),
+            message,
+        })
+    };
+
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    // Swatches that disagree with their curves, as (paint, message, ΔE)
+    let mut mismatched = Vec::new();
+    for paint in paints {
+        let name = paint._id.as_str();
+
+        if name.trim().is_empty() {
+            issue(Severity::Error, Check::Name, Some(name), "name is empty".to_string());
+        } else if name.trim() != name {
+            issue(
+                Severity::Warning,
+                Check::Name,
+                Some(name),
+                "name has leading or trailing whitespace".to_string(),
+            );
+        }
+        let key = name.trim().to_lowercase();
+        if let Some(first) = seen.insert(key, name) {
+            issue(
+                Severity::Error,
+                Check::DuplicateName,
+                Some(name),
+                format!("same name as {:?}", first),
+            );
+        }
+
+        let curve = match paint.curve() {
+            Ok(curve) => curve,
+            Err(e) => {
+                let check = match e {
+                    CurveDecodeError::WrongLength { .. } => Check::SpectralLength,
+                    CurveDecodeError::OutOfRange => Check::ValueRange,
+                    CurveDecodeError::NonIncreasingGrid => Check::Grid,
+                    _ => Check::Encoding,
+                };
+                issue(Severity::Error, check, Some(name), e.to_string());
+                continue;
+            }
+        };
+
+        let needed = colorimetry.grid();
+        if (curve.grid.start as f64) > needed.start as f64 || curve.grid.end() < needed.end() {
+            issue(
+                Severity::Warning,
+                Check::Grid,
+                Some(name),
+                format!("curve covers {} but mixing needs {}", curve.grid, needed),
+            );
+        }
+
+        let xyz = colorimetry.reflectance_to_xyz(&Array1::from_vec(curve.resampled(needed)));
+        let computed = xyz_to_srgb(xyz);
+        match paint.d65_10deg_hex.as_deref().and_then(parse_hex) {
+            None => issue(
+                Severity::Warning,
+                Check::Hex,
+                Some(name),
+                format!("missing or invalid d65_10deg_hex; the curve gives {}", to_hex(computed)),
+            ),
+            Some(stored) => {
+                let difference = delta_e76(&srgb_to_lab(stored), &srgb_to_lab(computed));
+                if difference > HEX_TOLERANCE {
+                    let message = format!(
+                        "d65_10deg_hex {} differs from the curve's {} (ΔE {:.1})",
+                        to_hex(stored),
+                        to_hex(computed),
+                        difference
+                    );
+                    mismatched.push((name, message, difference));
+                }
+            }
+        }
+    }
+
+    // Whole brands can have swatches from another source than their curves,
+    // so several mismatches are summed up in one warning
+    match &mismatched[..] {
+        [] => {}
+        [(name, message, _)] => issue(Severity::Warning, Check::Hex, Some(name), message.clone()),
+        _ => {
+            let (name, message, _) = mismatched
+                .iter()
+                .max_by(|a, b| a.2.total_cmp(&b.2))
+                .expect("several mismatches");
+            issue(
+                Severity::Warning,
+                Check::Hex,
+                None,
+                format!(
+                    "{} paints' d65_10deg_hex differ from their curves by more than ΔE {:.0}, \
+                     worst {:?}: {}",
+                    mismatched.len(),
+                    HEX_TOLERANCE,
+                    name,
+                    message
+                ),
+            );
+        }
+    }
+
+    let has = |role| paints.iter().any(|p| p.attributes().role == Some(role));
+    for (role, check) in [
+        (PaintRole::White, Check::MissingWhite),
+        (PaintRole::Black, Check::MissingBlack),
+    ] {
+        if !has(role) {
+            issue(
+                Severity::Warning,
+                check,
+                None,
+                format!(
+                    "no paint has the {} role, so black + white mixes need one from another brand",
+                    role.label()
+                ),
+            );
+        }
+    }
+
+    issues
+}
@@ -0,0 +1,206 @@
+//! Pigment knowledge used to flag risky paint mixtures
+//!
+//! Works on Colour Index codes (PW6, PB29, ...) from the paint metadata.
+
+use std::collections::BTreeSet;
+
+use crate::models::MixWarning;
+use crate::services::paint_mixing::MixPaint;
+
+/// More distinct non-white pigments than this in one mix is likely to turn muddy
+pub const MAX_CLEAN_PIGMENTS: usize = 3;
+
+/// Lead based pigments (lead white, chrome yellow, lead-tin and Naples yellow, red lead)
+const LEAD_PIGMENTS: &[&str] = &["PW1", "PY34", "PY41", "PY46", "PR104", "PR105"];
+
+/// Copper based pigments (verdigris, emerald green)
+const COPPER_PIGMENTS: &[&str] = &["PG20", "PG21"];
+
+/// Sulphide pigments (cadmiums, vermilion, ultramarine)
+const SULPHIDE_PIGMENTS: &[&str] = &[
+    "PY35", "PY37", "PO20", "PR108", "PR113", "PR106", "PB29", "PV15",
+];
+
+/// Known chemically problematic pigment pairs, as `(group a, group b, reason)`
+const INCOMPATIBLE_GROUPS: &[(&[&str], &[&str], &str)] = &[
+    (
+        LEAD_PIGMENTS,
+        SULPHIDE_PIGMENTS,
+        "lead and sulphide pigments can darken to lead sulphide",
+    ),
+    (
+        COPPER_PIGMENTS,
+        SULPHIDE_PIGMENTS,
+        "copper and sulphide pigments can blacken to copper sulphide",
+    ),
+];
+
+/// White pigments are ignored when counting pigments for mud risk
+fn is_white(pigment: &str) -> bool {
+    pigment.starts_with("PW")
+}
+
+/// Analyse a weighted mix and return any pigment warnings. Paints the mix
+/// leaves out, with no weight, are ignored.
+pub fn mix_warnings(paints: &[&MixPaint], weights: &[f64]) -> Vec<MixWarning> {
+    debug_assert_eq!(paints.len(), weights.len(), "one weight per paint");
+    let mixed: Vec<(&MixPaint, f64)> = paints
+        .iter()
+        .zip(weights)
+        .filter(|(_, &weight)| weight > 0.0)
+        .map(|(&paint, &weight)| (paint, weight))
+        .collect();
+    let mut warnings = Vec::new();
+
+    // Mud risk: count distinct chromatic pigments across all paints
+    let distinct: BTreeSet<&str> = mixed
+        .iter()
+        .flat_map(|(p, _)| p.attributes.pigments.iter().map(String::as_str))
+        .filter(|p| !is_white(p))
+        .collect();
+    if distinct.len() > MAX_CLEAN_PIGMENTS {
+        warnings.push(MixWarning::ManyPigments {
+            count: distinct.len(),
+        });
+    }
+
+    // Chemically problematic pairs, reported once per pair of paints
+    for (i, (a, _)) in mixed.iter().enumerate() {
+        for (b, _) in mixed.iter().skip(i + 1) {
+            if let Some(reason) = incompatibility(&a.attributes.pigments, &b.attributes.pigments) {
+                warnings.push(MixWarning::IncompatiblePigments {
+                    first: a.name.trim().to_string(),
+                    second: b.name.trim().to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+        }
+    }
+
+    // Fugitive paint as the largest component
+    let total: f64 = mixed.iter().map(|(_, weight)| weight).sum();
+    let dominant = mixed
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some((paint, weight)) = dominant {
+        if paint
+            .attributes
+            .lightfastness
+            .is_some_and(|lf| lf.is_fugitive())
+        {
+            warnings.push(MixWarning::FugitiveDominant {
+                paint: paint.name.trim().to_string(),
+                share: weight / total,
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Check whether two paints' pigment lists contain a known problematic pair
+fn incompatibility(a: &[String], b: &[String]) -> Option<&'static str> {
+    let has_any = |pigments: &[String], group: &[&str]| {
+        pigments.iter().any(|p| group.contains(&p.as_str()))
+    };
+
+    INCOMPATIBLE_GROUPS.iter().find_map(|(group_a, group_b, reason)| {
+        let clash = (has_any(a, group_a) && has_any(b, group_b))
+            || (has_any(a, group_b) && has_any(b, group_a));
+        clash.then_some(*reason)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Lightfastness, PaintAttributes};
+    use ndarray::Array1;
+
+    fn paint(name: &str, pigments: &[&str], lightfastness: Option<Lightfastness>) -> MixPaint {
+        MixPaint {
+            name: name.to_string(),
+            brand: "Test".to_string(),
+            reflectance: Array1::zeros(31),
+            ks: Array1::zeros(31),
+            hex: "#808080".to_string(),
+            attributes: PaintAttributes {
+                pigments: pigments.iter().map(|p| p.to_string()).collect(),
+                lightfastness,
+                ..Default::default()
+            },
+            preferred: false,
+        }
+    }
+
+    #[test]
+    fn many_pigments() {
+        let white = paint("Titanium White", &["PW6"], None);
+        let blue = paint("Ultramarine", &["PB29"], None);
+        let red = paint("Quinacridone Red", &["PV19"], None);
+        let yellow = paint("Hansa Yellow", &["PY3", "PY74"], None);
+
+        // White does not count towards the limit
+        let clean = mix_warnings(&[&white, &blue, &red], &[0.4, 0.3, 0.3]);
+        assert_eq!(clean, vec![]);
+        let muddy = mix_warnings(&[&white, &blue, &red, &yellow], &[0.4, 0.2, 0.2, 0.2]);
+        assert_eq!(muddy, vec![MixWarning::ManyPigments { count: MAX_CLEAN_PIGMENTS + 1 }]);
+    }
+
+    #[test]
+    fn lead_or_copper_with_a_sulphide() {
+        let lead_white = paint(" Flake White ", &["PW1"], None);
+        let emerald = paint("Emerald Green", &["PG21"], None);
+        let cadmium = paint("Cadmium Red", &["PR108"], None);
+
+        let warnings = mix_warnings(&[&lead_white, &cadmium], &[0.5, 0.5]);
+        assert_eq!(
+            warnings,
+            vec![MixWarning::IncompatiblePigments {
+                first: "Flake White".to_string(),
+                second: "Cadmium Red".to_string(),
+                reason: "lead and sulphide pigments can darken to lead sulphide".to_string(),
+            }]
+        );
+        let warnings = mix_warnings(&[&cadmium, &emerald], &[0.5, 0.5]);
+        assert!(matches!(
+            &warnings[..],
+            [MixWarning::IncompatiblePigments { reason, .. }] if reason.contains("copper")
+        ));
+        assert_eq!(mix_warnings(&[&lead_white, &emerald], &[0.5, 0.5]), vec![]);
+    }
+
+    #[test]
+    fn fugitive_dominant() {
+        let fugitive = paint("Alizarin", &["PR83"], Some(Lightfastness::IV));
+        let lasting = paint("Ultramarine", &["PB29"], Some(Lightfastness::I));
+
+        let warnings = mix_warnings(&[&lasting, &fugitive], &[1.0, 3.0]);
+        assert_eq!(
+            warnings,
+            vec![MixWarning::FugitiveDominant {
+                paint: "Alizarin".to_string(),
+                share: 0.75,
+            }]
+        );
+        // A fugitive paint in the minority is not flagged
+        assert_eq!(mix_warnings(&[&lasting, &fugitive], &[3.0, 1.0]), vec![]);
+        let very_fugitive = paint("Mauve", &["PV1"], Some(Lightfastness::V));
+        assert_eq!(mix_warnings(&[&very_fugitive], &[1.0]).len(), 1);
+    }
+
+    #[test]
+    fn paints_left_out_of_the_mix_are_ignored() {
+        let white = paint("Flake White", &["PW1"], None);
+        let blue = paint("Ultramarine", &["PB29"], Some(Lightfastness::I));
+        let red = paint("Quinacridone Red", &["PV19"], None);
+        let yellow = paint("Hansa Yellow", &["PY3", "PY74"], None);
+        let fugitive = paint("Alizarin", &["PR83"], Some(Lightfastness::IV));
+
+        let warnings = mix_warnings(
+            &[&white, &blue, &red, &yellow, &fugitive],
+            &[0.0, 0.5, 0.3, 0.0, 0.0],
+        );
+        assert_eq!(warnings, vec![]);
+    }
+}
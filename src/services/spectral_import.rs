@@ -0,0 +1,462 @@
+//! Reflectance measurements exported by spectrophotometers
+//!
+//! Reads CGATS (`.txt`/`.cgats`/`.it8`), CxF3 (`.cxf`) and CSV files and
+//! resamples each curve to the grid the paint data uses.
+
+use crate::models::SpectralGrid;
+
+/// How far a measurement may stop short of the grid before it is rejected;
+/// the end value is held over the gap
+const MAX_EXTRAPOLATION_NM: f64 = 10.0;
+
+/// A measured reflectance curve on `SpectralGrid::PAINT_DATA` (values in 0..=1)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasuredSample {
+    pub name: String,
+    pub reflectance: Vec<f64>,
+}
+
+/// How a file writes reflectance, decided once for all of its samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scale {
+    Percent,
+    Fraction,
+}
+
+impl Scale {
+    /// Percent if any value in the file is above 1.5, which fluorescent
+    /// samples in 0..=1 do not reach
+    fn detect(samples: &[RawSample]) -> Scale {
+        let max = samples
+            .iter()
+            .flat_map(|s| s.values.iter().copied())
+            .fold(f64::NEG_INFINITY, f64::max);
+        if max > 1.5 {
+            Scale::Percent
+        } else {
+            Scale::Fraction
+        }
+    }
+
+    fn factor(self) -> f64 {
+        match self {
+            Scale::Percent => 0.01,
+            Scale::Fraction => 1.0,
+        }
+    }
+}
+
+/// A curve as read from the file, at the instrument's own wavelengths
+struct RawSample {
+    name: String,
+    wavelengths: Vec<f64>,
+    values: Vec<f64>,
+}
+
+/// Parse a measurement file, detecting the format from its contents
+pub fn import_measurements(filename: &str, data: &[u8]) -> Result<Vec<MeasuredSample>, String> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_start_matches('\u{feff}');
+    let lower_name = filename.to_lowercase();
+
+    let (raw, declared) = if text.trim_start().starts_with('<') || lower_name.ends_with(".cxf") {
+        (parse_cxf(text)?, None)
+    } else if text.contains("BEGIN_DATA_FORMAT") {
+        parse_cgats(text)?
+    } else {
+        (parse_csv(text)?, None)
+    };
+    if raw.is_empty() {
+        return Err("No spectral measurements found in the file".to_string());
+    }
+    let scale = declared.unwrap_or_else(|| Scale::detect(&raw));
+
+    raw.into_iter()
+        .map(|sample| {
+            let reflectance = resample(&sample.wavelengths, &sample.values, scale)
+                .map_err(|e| format!("{}: {}", sample.name, e))?;
+            Ok(MeasuredSample {
+                name: sample.name,
+                reflectance,
+            })
+        })
+        .collect()
+}
+
+/// Linearly interpolate onto the paint data grid, scaling to 0..=1
+fn resample(wavelengths: &[f64], values: &[f64], scale: Scale) -> Result<Vec<f64>, String> {
+    if wavelengths.len() != values.len() || wavelengths.len() < 2 {
+        return Err("Not enough spectral values".to_string());
+    }
+    let mut points: Vec<(f64, f64)> = wavelengths.iter().copied().zip(values.iter().copied()).collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let grid = SpectralGrid::PAINT_DATA;
+    let (first, last) = (points[0].0, points[points.len() - 1].0);
+    if first > grid.start as f64 + MAX_EXTRAPOLATION_NM || last < grid.end() - MAX_EXTRAPOLATION_NM {
+        return Err(format!(
+            "Measured from {} to {} nm, but {}–{} nm is needed",
+            first,
+            last,
+            grid.start,
+            grid.end()
+        ));
+    }
+
+    let scale = scale.factor();
+    Ok(grid
+        .sample_points(&points)
+        .into_iter()
+        .map(|value| (value * scale).clamp(0.0, 1.0))
+        .collect())
+}
+
+/// Wavelength named by a CGATS or CSV column header such as `SPECTRAL_NM400`,
+/// `SPECTRAL_PCT400`, `SPECTRAL_DEC400`, `nm400`, `R400` or plain `400`
+fn header_wavelength(field: &str) -> Option<f64> {
+    let upper = field.trim().trim_matches('"').to_uppercase();
+    let digits_at = upper.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, number) = upper.split_at(digits_at);
+    let known_prefix = matches!(
+        prefix.trim_end_matches('_'),
+        "" | "SPECTRAL_NM" | "SPECTRAL" | "SPECTRAL_PCT" | "SPECTRAL_DEC" | "SPEC" | "NM" | "R"
+    );
+    let wl: f64 = number.trim_end_matches("NM").trim().parse().ok()?;
+    (known_prefix && (300.0..=850.0).contains(&wl)).then_some(wl)
+}
+
+/// Split a line on whitespace, keeping quoted strings together
+fn cgats_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// CGATS.17 / IT8.7 data with spectral fields, and the scale their names
+/// declare (`SPECTRAL_PCT` or `SPECTRAL_DEC`)
+fn parse_cgats(text: &str) -> Result<(Vec<RawSample>, Option<Scale>), String> {
+    let mut lines = text.lines().map(str::trim);
+    let mut fields = Vec::new();
+    for line in lines.by_ref() {
+        if line.starts_with("BEGIN_DATA_FORMAT") {
+            break;
+        }
+    }
+    for line in lines.by_ref() {
+        if line.starts_with("END_DATA_FORMAT") {
+            break;
+        }
+        fields.extend(cgats_tokens(line));
+    }
+
+    let spectral: Vec<(usize, f64)> = fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| header_wavelength(f).map(|wl| (i, wl)))
+        .collect();
+    if spectral.is_empty() {
+        return Err("The CGATS file has no spectral fields".to_string());
+    }
+    let declared = |prefix: &str| {
+        spectral
+            .iter()
+            .all(|&(i, _)| fields[i].to_uppercase().starts_with(prefix))
+    };
+    let scale = if declared("SPECTRAL_PCT") {
+        Some(Scale::Percent)
+    } else if declared("SPECTRAL_DEC") {
+        Some(Scale::Fraction)
+    } else {
+        None
+    };
+    let name_field = ["SAMPLE_NAME", "SAMPLE_ID", "SampleID"]
+        .iter()
+        .find_map(|n| fields.iter().position(|f| f.eq_ignore_ascii_case(n)));
+
+    for line in lines.by_ref() {
+        if line.starts_with("BEGIN_DATA") {
+            break;
+        }
+    }
+    let mut samples = Vec::new();
+    for line in lines {
+        if line.starts_with("END_DATA") {
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let tokens = cgats_tokens(line);
+        if tokens.len() < fields.len() {
+            return Err(format!("Short CGATS data line: {}", line));
+        }
+        let values = spectral
+            .iter()
+            .map(|&(i, _)| tokens[i].parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Invalid spectral value in: {}", line))?;
+        samples.push(RawSample {
+            name: name_field
+                .map(|i| tokens[i].clone())
+                .unwrap_or_else(|| format!("Sample {}", samples.len() + 1)),
+            wavelengths: spectral.iter().map(|&(_, wl)| wl).collect(),
+            values,
+        });
+    }
+    Ok((samples, scale))
+}
+
+/// CSV with either one sample per row (wavelength headers) or one sample per
+/// column (wavelengths down the first column). Comma, semicolon or tab separated.
+fn parse_csv(text: &str) -> Result<Vec<RawSample>, String> {
+    let rows: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let header = rows.first().ok_or("Empty CSV file")?;
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| header.matches(*d).count())
+        .unwrap_or(',');
+    let split = |line: &str| -> Vec<String> {
+        line.split(delimiter)
+            .map(|c| c.trim().trim_matches('"').to_string())
+            .collect()
+    };
+    let header = split(header);
+
+    // One sample per row: wavelength columns in the header
+    let columns: Vec<(usize, f64)> = header
+        .iter()
+        .enumerate()
+        .filter_map(|(i, h)| header_wavelength(h).map(|wl| (i, wl)))
+        .collect();
+    if columns.len() >= 3 {
+        let name_col = (0..header.len()).find(|i| !columns.iter().any(|(c, _)| c == i));
+        return rows[1..]
+            .iter()
+            .enumerate()
+            .map(|(n, line)| {
+                let cells = split(line);
+                let values = columns
+                    .iter()
+                    .map(|&(i, _)| cells.get(i).and_then(|c| c.parse::<f64>().ok()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| format!("Invalid spectral values in row {}", n + 2))?;
+                Ok(RawSample {
+                    name: name_col
+                        .and_then(|i| cells.get(i).cloned())
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| format!("Sample {}", n + 1)),
+                    wavelengths: columns.iter().map(|&(_, wl)| wl).collect(),
+                    values,
+                })
+            })
+            .collect();
+    }
+
+    // One sample per column: wavelengths down the first column
+    let data: Vec<Vec<String>> = rows[1..].iter().map(|l| split(l)).collect();
+    let wavelengths = data
+        .iter()
+        .map(|cells| cells.first().and_then(|c| header_wavelength(c)))
+        .collect::<Option<Vec<_>>>()
+        .filter(|w| w.len() >= 3)
+        .ok_or("Expected wavelengths across the header row or down the first column")?;
+    (1..header.len())
+        .map(|col| {
+            let values = data
+                .iter()
+                .map(|cells| cells.get(col).and_then(|c| c.parse::<f64>().ok()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("Invalid spectral values in column {}", col + 1))?;
+            Ok(RawSample {
+                name: Some(header[col].clone())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| format!("Sample {}", col)),
+                wavelengths: wavelengths.clone(),
+                values,
+            })
+        })
+        .collect()
+}
+
+/// CxF3 colour exchange: every `Object` with a `ReflectanceSpectrum`. The start
+/// wavelength comes from the spectrum (or the first `WavelengthRange`) and the
+/// increment from the `WavelengthRange`, defaulting to 10 nm.
+fn parse_cxf(text: &str) -> Result<Vec<RawSample>, String> {
+    let range = find_tag(text, "WavelengthRange").map(|(tag, _)| tag);
+    let default_start = range.and_then(|t| attr(t, "StartWL")).and_then(|v| v.parse().ok());
+    let increment: f64 = range
+        .and_then(|t| attr(t, "Increment"))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10.0);
+
+    let mut samples = Vec::new();
+    let mut rest = text;
+    while let Some((tag, after)) = find_tag(rest, "Object") {
+        rest = after;
+        let body_end = rest.find("Object>").unwrap_or(rest.len());
+        let body = &rest[..body_end];
+        let Some((spectrum_tag, spectrum_rest)) = find_tag(body, "ReflectanceSpectrum") else {
+            continue;
+        };
+        let values = spectrum_rest[..spectrum_rest.find('<').unwrap_or(spectrum_rest.len())]
+            .split_whitespace()
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "Invalid ReflectanceSpectrum values".to_string())?;
+        let start: f64 = attr(spectrum_tag, "StartWL")
+            .and_then(|v| v.parse().ok())
+            .or(default_start)
+            .ok_or("ReflectanceSpectrum without a start wavelength")?;
+        samples.push(RawSample {
+            name: attr(tag, "Name").unwrap_or_else(|| format!("Sample {}", samples.len() + 1)),
+            wavelengths: (0..values.len()).map(|i| start + increment * i as f64).collect(),
+            values,
+        });
+        rest = &rest[body_end..];
+    }
+    Ok(samples)
+}
+
+/// Find the next opening tag with this local name (any namespace prefix),
+/// returning the tag's text and what follows it
+fn find_tag<'a>(text: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find('<').map(|i| i + offset) {
+        let end = text[start..].find('>')? + start;
+        let tag = &text[start + 1..end];
+        let tag_name = tag.split_whitespace().next().unwrap_or("").trim_end_matches('/');
+        let local = tag_name.rsplit(':').next().unwrap_or(tag_name);
+        if local == name && !tag.starts_with('/') {
+            return Some((tag, &text[end + 1..]));
+        }
+        offset = end + 1;
+    }
+    None
+}
+
+/// Value of an attribute in a tag's text
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{}=\"", name);
+    let start = tag
+        .match_indices(&pattern)
+        .find(|(i, _)| *i == 0 || tag[..*i].ends_with(char::is_whitespace))?
+        .0
+        + pattern.len();
+    let end = tag[start..].find('"')? + start;
+    Some(unescape(&tag[start..end]))
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header cells 400..=700 nm every 10 nm with a prefix
+    fn wavelength_fields(prefix: &str) -> Vec<String> {
+        (400..=700).step_by(10).map(|wl| format!("{}{}", prefix, wl)).collect()
+    }
+
+    fn values(value: f64) -> Vec<String> {
+        vec![value.to_string(); 31]
+    }
+
+    fn cgats(prefix: &str, rows: &[(&str, f64)]) -> String {
+        let mut text = format!(
+            "CGATS.17\nBEGIN_DATA_FORMAT\nSAMPLE_ID SAMPLE_NAME {}\nEND_DATA_FORMAT\nBEGIN_DATA\n",
+            wavelength_fields(prefix).join(" ")
+        );
+        for (i, (name, value)) in rows.iter().enumerate() {
+            text += &format!("{} {} {}\n", i + 1, name, values(*value).join(" "));
+        }
+        text + "END_DATA\n"
+    }
+
+    #[test]
+    fn cgats_quoted_names() {
+        let text = cgats("SPECTRAL_NM", &[("\"Cadmium Red Deep\"", 0.25), ("\"Ultramarine\"", 0.5)]);
+        let samples = import_measurements("chart.txt", text.as_bytes()).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].name, "Cadmium Red Deep");
+        assert_eq!(samples[0].reflectance, vec![0.25; 31]);
+        assert_eq!(samples[1].name, "Ultramarine");
+    }
+
+    #[test]
+    fn cgats_field_names_declare_the_scale() {
+        // A black measured in percent stays below 1.5 everywhere
+        let text = cgats("SPECTRAL_PCT", &[("Black", 1.2)]);
+        let samples = import_measurements("black.txt", text.as_bytes()).unwrap();
+        assert!((samples[0].reflectance[0] - 0.012).abs() < 1e-12);
+
+        let text = cgats("SPECTRAL_DEC", &[("White", 0.9)]);
+        let samples = import_measurements("white.txt", text.as_bytes()).unwrap();
+        assert!((samples[0].reflectance[0] - 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scale_is_decided_for_the_whole_file() {
+        let text = cgats("SPECTRAL_NM", &[("White", 90.0), ("Black", 1.2)]);
+        let samples = import_measurements("chart.txt", text.as_bytes()).unwrap();
+        assert!((samples[0].reflectance[0] - 0.9).abs() < 1e-12);
+        assert!((samples[1].reflectance[0] - 0.012).abs() < 1e-12);
+
+        let text = cgats("SPECTRAL_NM", &[("White", 0.9), ("Black", 0.012)]);
+        let samples = import_measurements("chart.txt", text.as_bytes()).unwrap();
+        assert!((samples[1].reflectance[0] - 0.012).abs() < 1e-12);
+    }
+
+    #[test]
+    fn csv_sample_per_row() {
+        let text = format!(
+            "name,{}\nRed,{}\nBlue,{}\n",
+            wavelength_fields("nm").join(","),
+            values(0.2).join(","),
+            values(0.4).join(",")
+        );
+        let samples = import_measurements("samples.csv", text.as_bytes()).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].name, "Red");
+        assert_eq!(samples[1].reflectance, vec![0.4; 31]);
+    }
+
+    #[test]
+    fn csv_sample_per_column() {
+        let mut text = "nm;Red;Blue\n".to_string();
+        for wl in (380..=730).step_by(10) {
+            text += &format!("{};{};{}\n", wl, 20, 40);
+        }
+        let samples = import_measurements("samples.csv", text.as_bytes()).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].name, "Blue");
+        assert_eq!(samples[0].reflectance, vec![0.2; 31]);
+    }
+
+    #[test]
+    fn short_measurements_are_rejected() {
+        let text = "nm,Red\n420,0.2\n500,0.3\n700,0.4\n";
+        let err = import_measurements("samples.csv", text.as_bytes()).unwrap_err();
+        assert!(err.contains("Measured from 420 to 700 nm"), "{}", err);
+    }
+}
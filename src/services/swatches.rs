@@ -0,0 +1,370 @@
+//! Swatch file formats used by digital painting tools
+//!
+//! Export: Adobe ASE, GIMP GPL, Procreate `.swatches` and CSS custom properties.
+//! Import: GPL and ASE, as a list of named colours.
+
+use std::fmt::Write;
+
+use crate::models::{lab_to_srgb, to_hex};
+
+/// Procreate palettes hold at most this many swatches
+const PROCREATE_MAX_SWATCHES: usize = 30;
+
+/// A named colour in a swatch file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swatch {
+    pub name: String,
+    pub rgb: [u8; 3],
+}
+
+/// GIMP palette (`.gpl`)
+pub fn export_gpl(title: &str, swatches: &[Swatch]) -> String {
+    let mut out = format!("GIMP Palette\nName: {}\nColumns: 8\n#\n", single_line(title));
+    for s in swatches {
+        let _ = writeln!(
+            out,
+            "{:3} {:3} {:3}\t{}",
+            s.rgb[0],
+            s.rgb[1],
+            s.rgb[2],
+            single_line(&s.name)
+        );
+    }
+    out
+}
+
+/// CSS custom properties on `:root`, one per swatch
+pub fn export_css(title: &str, swatches: &[Swatch]) -> String {
+    let mut out = format!("/* {} */\n:root {{\n", single_line(title).replace("*/", ""));
+    let mut used = Vec::with_capacity(swatches.len());
+    for s in swatches {
+        // Keep property names unique when two paints share a name
+        let base = css_ident(&s.name);
+        let mut name = base.clone();
+        let mut n = 2;
+        while used.contains(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        let _ = writeln!(out, "  --{}: {};", name, to_hex(s.rgb));
+        used.push(name);
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn css_ident(name: &str) -> String {
+    let ident = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    match ident.chars().next() {
+        None => "colour".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("c-{}", ident),
+        _ => ident,
+    }
+}
+
+fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// Adobe Swatch Exchange (`.ase`), RGB global colours
+pub fn export_ase(swatches: &[Swatch]) -> Vec<u8> {
+    let mut out = b"ASEF".to_vec();
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&(swatches.len() as u32).to_be_bytes());
+
+    for s in swatches {
+        let name: Vec<u16> = s.name.encode_utf16().chain(std::iter::once(0)).collect();
+
+        let mut block = Vec::new();
+        block.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        for unit in &name {
+            block.extend_from_slice(&unit.to_be_bytes());
+        }
+        block.extend_from_slice(b"RGB ");
+        for c in s.rgb {
+            block.extend_from_slice(&(c as f32 / 255.0).to_be_bytes());
+        }
+        // Colour type: 0 global, 1 spot, 2 normal
+        block.extend_from_slice(&0u16.to_be_bytes());
+
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        out.extend_from_slice(&block);
+    }
+    out
+}
+
+/// Procreate palette (`.swatches`): a zip holding `Swatches.json` with HSB colours
+pub fn export_procreate(title: &str, swatches: &[Swatch]) -> Vec<u8> {
+    let colours: Vec<serde_json::Value> = swatches
+        .iter()
+        .take(PROCREATE_MAX_SWATCHES)
+        .map(|s| {
+            let [h, sat, v] = rgb_to_hsb(s.rgb);
+            serde_json::json!({
+                "hue": h,
+                "saturation": sat,
+                "brightness": v,
+                "alpha": 1,
+                "colorSpace": 0,
+            })
+        })
+        .collect();
+    let json = serde_json::json!([{ "name": title, "swatches": colours }]).to_string();
+
+    stored_zip("Swatches.json", json.as_bytes())
+}
+
+/// Hue, saturation and brightness, each in 0..=1
+fn rgb_to_hsb(rgb: [u8; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    [hue, saturation, max]
+}
+
+/// A zip archive with a single uncompressed file
+fn stored_zip(name: &str, data: &[u8]) -> Vec<u8> {
+    let crc = crc32(data);
+    let size = data.len() as u32;
+    let name = name.as_bytes();
+
+    // Fields shared by the local and central headers, from "version needed" to "extra length"
+    let mut common = Vec::new();
+    common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+    common.extend_from_slice(&0u16.to_le_bytes()); // flags
+    common.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+    common.extend_from_slice(&0u16.to_le_bytes()); // mod time
+    common.extend_from_slice(&0x21u16.to_le_bytes()); // mod date: 1980-01-01
+    common.extend_from_slice(&crc.to_le_bytes());
+    common.extend_from_slice(&size.to_le_bytes());
+    common.extend_from_slice(&size.to_le_bytes());
+    common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    out.extend_from_slice(&common);
+    out.extend_from_slice(name);
+    out.extend_from_slice(data);
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    out.extend_from_slice(&20u16.to_le_bytes()); // version made by
+    out.extend_from_slice(&common);
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    out.extend_from_slice(&0u16.to_le_bytes()); // disk number
+    out.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+    out.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+    out.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+    out.extend_from_slice(name);
+    let central_size = out.len() as u32 - central_offset;
+
+    out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    out.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&central_size.to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Parse a GIMP palette
+pub fn import_gpl(text: &str) -> Result<Vec<Swatch>, String> {
+    let mut lines = text.trim_start_matches('\u{feff}').lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err("Not a GIMP palette".to_string());
+    }
+
+    let mut swatches = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (line.contains(':') && !starts_with_digit(line)) {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let mut channel = || parts.next().and_then(|p| p.parse::<u8>().ok());
+        let (Some(r), Some(g), Some(b)) = (channel(), channel(), channel()) else {
+            return Err(format!("Invalid palette line: {}", line));
+        };
+        let name = parts.collect::<Vec<_>>().join(" ");
+        let rgb = [r, g, b];
+        swatches.push(Swatch {
+            name: if name.is_empty() { to_hex(rgb) } else { name },
+            rgb,
+        });
+    }
+    Ok(swatches)
+}
+
+fn starts_with_digit(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Parse an Adobe Swatch Exchange file. Groups are flattened; RGB, grey, CMYK
+/// and Lab colours are converted to sRGB
+pub fn import_ase(data: &[u8]) -> Result<Vec<Swatch>, String> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.take(4)? != b"ASEF" {
+        return Err("Not an ASE file".to_string());
+    }
+    reader.take(4)?; // version
+    let blocks = reader.u32()?;
+
+    let mut swatches = Vec::new();
+    for _ in 0..blocks {
+        let kind = reader.u16()?;
+        let len = reader.u32()? as usize;
+        let block = reader.take(len)?;
+        if kind != 0x0001 {
+            // Group start/end markers
+            continue;
+        }
+
+        let mut block = Reader { data: block, pos: 0 };
+        let name_len = block.u16()? as usize;
+        let name_units: Vec<u16> = (0..name_len).map(|_| block.u16()).collect::<Result<_, _>>()?;
+        let name = String::from_utf16_lossy(&name_units)
+            .trim_end_matches('\0')
+            .to_string();
+
+        let model = block.take(4)?;
+        let rgb = match model {
+            b"RGB " => {
+                let [r, g, b] = [block.f32()?, block.f32()?, block.f32()?];
+                [r, g, b].map(unit_to_u8)
+            }
+            b"Gray" => [unit_to_u8(block.f32()?); 3],
+            b"CMYK" => {
+                let [c, m, y, k] = [block.f32()?, block.f32()?, block.f32()?, block.f32()?];
+                [c, m, y].map(|v| unit_to_u8((1.0 - v) * (1.0 - k)))
+            }
+            b"LAB " => {
+                let [l, a, b] = [block.f32()?, block.f32()?, block.f32()?];
+                lab_to_srgb([l as f64 * 100.0, a as f64, b as f64])
+            }
+            other => {
+                return Err(format!(
+                    "Unsupported colour model: {}",
+                    String::from_utf8_lossy(other)
+                ))
+            }
+        };
+        swatches.push(Swatch {
+            name: if name.is_empty() { to_hex(rgb) } else { name },
+            rgb,
+        });
+    }
+    Ok(swatches)
+}
+
+fn unit_to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Big-endian cursor over an ASE file
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.data.len());
+        let Some(end) = end else {
+            return Err("Unexpected end of swatch file".to_string());
+        };
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swatches() -> Vec<Swatch> {
+        vec![
+            Swatch { name: "Cadmium Red".to_string(), rgb: [227, 38, 54] },
+            Swatch { name: "Bleu Céruléum".to_string(), rgb: [42, 82, 190] },
+            Swatch { name: "Titanium White".to_string(), rgb: [255, 255, 255] },
+        ]
+    }
+
+    #[test]
+    fn gpl_round_trip() {
+        let text = export_gpl("Studio", &swatches());
+        assert_eq!(import_gpl(&text).unwrap(), swatches());
+    }
+
+    #[test]
+    fn ase_round_trip() {
+        let data = export_ase(&swatches());
+        assert_eq!(import_ase(&data).unwrap(), swatches());
+    }
+
+    #[test]
+    fn truncated_ase_is_an_error() {
+        let data = export_ase(&swatches());
+        for len in [0, 3, 11, 20, data.len() - 1] {
+            assert_eq!(
+                import_ase(&data[..len]),
+                Err("Unexpected end of swatch file".to_string()),
+                "truncated to {} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn gpl_without_names_uses_hex() {
+        let swatches = import_gpl("GIMP Palette\nName: Test\n#\n 10  20  30\n").unwrap();
+        assert_eq!(swatches[0].name, to_hex([10, 20, 30]));
+    }
+}
@@ -0,0 +1,126 @@
+//! Target reflectance curves reconstructed with LHTSS, shared by every
+//! request. Finished curves are kept in an LRU cache, and a coarse table of
+//! solutions across the sRGB cube gives Newton's method a close starting
+//! point for colours that are not cached.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use hashlink::LruCache;
+use ndarray::Array1;
+use rayon::prelude::*;
+
+use crate::models::{
+    linear_rgb_to_xyz, linear_to_srgb, srgb_to_linear, xyz_to_linear_rgb, SpectralGrid,
+};
+use crate::services::colorimetry::{Colorimetry, Illuminant};
+use crate::services::lhtss::{LhtssState, LHTSS};
+
+/// Reconstructed curves kept per colorimetry
+const CACHE_CAPACITY: usize = 4096;
+
+/// Nodes along each axis of the sRGB lookup table
+const LUT_SIZE: usize = 9;
+
+/// LHTSS for one colorimetry, with its cache and lookup table
+pub struct TargetReflectance {
+    colorimetry: Colorimetry,
+    lhtss: LHTSS,
+    /// The `z` of the solution at each lookup table node, red-major
+    lut: Vec<Array1<f64>>,
+    /// Curves by the bits of the D65-relative XYZ they were reconstructed from
+    cache: Mutex<LruCache<[u64; 3], Array1<f64>>>,
+}
+
+impl TargetReflectance {
+    /// The shared reconstructor for a colorimetry. The first call for each
+    /// illuminant and grid builds its lookup table, which takes a moment, so
+    /// call it from a blocking task. Callers for other colorimetries do not
+    /// wait on the build.
+    pub fn for_colorimetry(colorimetry: &Colorimetry) -> Arc<Self> {
+        type Slot = Arc<OnceLock<Arc<TargetReflectance>>>;
+        static SHARED: OnceLock<Mutex<HashMap<(Illuminant, SpectralGrid), Slot>>> =
+            OnceLock::new();
+
+        let slot = SHARED
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry((colorimetry.illuminant(), colorimetry.grid()))
+            .or_default()
+            .clone();
+        slot.get_or_init(|| Arc::new(TargetReflectance::new(colorimetry.clone())))
+            .clone()
+    }
+
+    fn new(colorimetry: Colorimetry) -> Self {
+        let lhtss = LHTSS::new(colorimetry.t_matrix().clone());
+        let step = 1.0 / (LUT_SIZE - 1) as f64;
+        let lut = (0..LUT_SIZE.pow(3))
+            .into_par_iter()
+            .map(|i| {
+                let srgb = [i / (LUT_SIZE * LUT_SIZE), i / LUT_SIZE % LUT_SIZE, i % LUT_SIZE]
+                    .map(|n| n as f64 * step);
+                let xyz = colorimetry.adapt_from_d65(linear_rgb_to_xyz(srgb.map(srgb_to_linear)));
+                match lhtss.compute_reflectance_target_from(xyz, LhtssState::flat(lhtss.bands())) {
+                    Ok((_, state)) => state.z,
+                    Err(_) => Array1::zeros(lhtss.bands()),
+                }
+            })
+            .collect();
+        TargetReflectance {
+            colorimetry,
+            lhtss,
+            lut,
+            cache: Mutex::new(LruCache::new(CACHE_CAPACITY)),
+        }
+    }
+
+    /// Reflectance of a D65-relative XYZ colour (Y = 1 for white) under the
+    /// colorimetry's illuminant, on its grid
+    pub fn reflectance(&self, xyz: [f64; 3]) -> Result<Array1<f64>, String> {
+        let key = xyz.map(f64::to_bits);
+        if let Some(curve) = self.cache.lock().unwrap().get(&key) {
+            return Ok(curve.clone());
+        }
+
+        let adapted = self.colorimetry.adapt_from_d65(xyz);
+        let curve = match self
+            .lhtss
+            .compute_reflectance_target_from(adapted, self.initial_guess(xyz))
+        {
+            Ok((curve, _)) => curve,
+            // Should the table's guess lead Newton astray, start from flat as before
+            Err(_) => self.lhtss.compute_reflectance_target(adapted)?,
+        };
+        self.cache.lock().unwrap().insert(key, curve.clone());
+        Ok(curve)
+    }
+
+    /// The table's solutions around a colour's place in the sRGB cube,
+    /// interpolated trilinearly; out-of-gamut colours use the nearest face.
+    /// Only `z` is interpolated: starting the multipliers from zero converges
+    /// far faster than starting them from their neighbours' values.
+    fn initial_guess(&self, xyz: [f64; 3]) -> LhtssState {
+        let scale = (LUT_SIZE - 1) as f64;
+        let position = xyz_to_linear_rgb(xyz).map(|c| linear_to_srgb(c) * scale);
+        let base = position.map(|p| (p.floor() as usize).min(LUT_SIZE - 2));
+        let fraction = [0, 1, 2].map(|i| position[i] - base[i] as f64);
+
+        let mut guess = LhtssState::flat(self.lhtss.bands());
+        for corner in 0..8 {
+            let offset = [corner >> 2 & 1, corner >> 1 & 1, corner & 1];
+            let weight: f64 = (0..3)
+                .map(|i| if offset[i] == 1 { fraction[i] } else { 1.0 - fraction[i] })
+                .product();
+            if weight == 0.0 {
+                continue;
+            }
+            let [r, g, b] = [0, 1, 2].map(|i| base[i] + offset[i]);
+            guess
+                .z
+                .scaled_add(weight, &self.lut[(r * LUT_SIZE + g) * LUT_SIZE + b]);
+        }
+        guess
+    }
+}
@@ -1,10 +1,15 @@
 use std::sync::Arc;
 
-use crate::db::Db;
+use crate::db::{Db, UserStore};
 use crate::services::email::Email;
+use crate::services::paint_cache::PaintCache;
 
 #[derive(Clone)]
 pub struct AppState {
+    /// The paint reference database
     pub db: Db,
+    /// The paint catalogue from `db`, decoded for mixing
+    pub paints: PaintCache,
+    pub users: Arc<dyn UserStore>,
     pub email: Arc<Email>,
 }